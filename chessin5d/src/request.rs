@@ -376,6 +376,7 @@ mod sessions {
 
             for piece in &turn.pieces {
                 res.set(piece.position.file - 1, piece.position.rank - 1, parse_piece_name(&piece.piece, parse_player_color(&piece.player))).unwrap();
+                res.set_moved(piece.position.file - 1, piece.position.rank - 1, piece.hasMoved);
             }
 
             res
@@ -0,0 +1,421 @@
+/*
+    Bitboards: sets of squares of a board, stored as bits.
+
+    Boards of up to 64 squares (8x8) fit in a single `u64`; larger boards fall back to a boxed slice of words.
+    `Board` stores its pieces as one bitboard per kind of piece and one per color (`PieceBitboards`), which makes copying a board cheap.
+    The squares attacked by leapers and sliders within a board are precomputed for every board size, in `AttackTables`.
+*/
+
+use super::{game::*, kind::*};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// A set of squares of a board, the square `(x, y)` being the bit `x + y * width`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Bitboard {
+    /// Boards of up to 64 squares
+    Small(u64),
+    /// Larger boards, the square `i` being the bit `i % 64` of the word `i / 64`
+    Wide(Box<[u64]>),
+}
+
+impl Bitboard {
+    /// Creates an empty bitboard for a board of `len` squares
+    pub fn empty(len: usize) -> Self {
+        if len <= 64 {
+            Bitboard::Small(0)
+        } else {
+            Bitboard::Wide(vec![0; len.div_ceil(64)].into_boxed_slice())
+        }
+    }
+
+    /// Returns whether or not the square `i` is in the set
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        match self {
+            Bitboard::Small(bits) => bits >> i & 1 == 1,
+            Bitboard::Wide(words) => words[i / 64] >> (i % 64) & 1 == 1,
+        }
+    }
+
+    /// Adds (if `value` is true) or removes the square `i`
+    #[inline]
+    pub fn set(&mut self, i: usize, value: bool) {
+        let (word, bit) = match self {
+            Bitboard::Small(bits) => (bits, i),
+            Bitboard::Wide(words) => (&mut words[i / 64], i % 64),
+        };
+        if value {
+            *word |= 1 << bit;
+        } else {
+            *word &= !(1 << bit);
+        }
+    }
+
+    /// Returns the number of squares in the set
+    pub fn count(&self) -> u32 {
+        self.words().iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Returns whether or not the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.words().iter().all(|word| *word == 0)
+    }
+
+    /// Returns the words backing the set
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        match self {
+            Bitboard::Small(bits) => std::slice::from_ref(bits),
+            Bitboard::Wide(words) => words,
+        }
+    }
+
+    #[inline]
+    fn words_mut(&mut self) -> &mut [u64] {
+        match self {
+            Bitboard::Small(bits) => std::slice::from_mut(bits),
+            Bitboard::Wide(words) => words,
+        }
+    }
+
+    /// Returns the squares that are in both sets
+    pub fn and(&self, other: &Bitboard) -> Bitboard {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Returns the squares that are in either set
+    pub fn or(&self, other: &Bitboard) -> Bitboard {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Returns the squares that are in this set but not in `other`
+    pub fn and_not(&self, other: &Bitboard) -> Bitboard {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    /// Adds the squares of `other` to this set
+    pub fn or_assign(&mut self, other: &Bitboard) {
+        for (a, b) in self.words_mut().iter_mut().zip(other.words()) {
+            *a |= b;
+        }
+    }
+
+    fn combine<F: Fn(u64, u64) -> u64>(&self, other: &Bitboard, f: F) -> Bitboard {
+        match (self, other) {
+            (Bitboard::Small(a), Bitboard::Small(b)) => Bitboard::Small(f(*a, *b)),
+            _ => Bitboard::Wide(self.words().iter().zip(other.words()).map(|(a, b)| f(*a, *b)).collect()),
+        }
+    }
+
+    /// Returns the lowest square of the set
+    pub fn first(&self) -> Option<usize> {
+        self.words()
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+    }
+
+    /// Returns the highest square of the set
+    pub fn last(&self) -> Option<usize> {
+        self.words()
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + 63 - word.leading_zeros() as usize)
+    }
+
+    /// Iterates over the squares of the set, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words().iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    None
+                } else {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(i * 64 + bit)
+                }
+            })
+        })
+    }
+}
+
+/// The white pieces, in the order of `PieceBitboards::kinds`
+const KINDS_W: [Piece; 12] = [
+    Piece::PawnW,
+    Piece::KnightW,
+    Piece::BishopW,
+    Piece::RookW,
+    Piece::QueenW,
+    Piece::KingW,
+    Piece::UnicornW,
+    Piece::DragonW,
+    Piece::PrincessW,
+    Piece::BrawnW,
+    Piece::CommonKingW,
+    Piece::RoyalQueenW,
+];
+
+/// The black pieces, in the order of `PieceBitboards::kinds`
+const KINDS_B: [Piece; 12] = [
+    Piece::PawnB,
+    Piece::KnightB,
+    Piece::BishopB,
+    Piece::RookB,
+    Piece::QueenB,
+    Piece::KingB,
+    Piece::UnicornB,
+    Piece::DragonB,
+    Piece::PrincessB,
+    Piece::BrawnB,
+    Piece::CommonKingB,
+    Piece::RoyalQueenB,
+];
+
+/// Returns the index of the kind of `piece` in `PieceBitboards::kinds`, None if it is blank
+fn kind_index(piece: Piece) -> Option<usize> {
+    Some(match piece {
+        Piece::Blank => return None,
+        Piece::PawnW | Piece::PawnB => 0,
+        Piece::KnightW | Piece::KnightB => 1,
+        Piece::BishopW | Piece::BishopB => 2,
+        Piece::RookW | Piece::RookB => 3,
+        Piece::QueenW | Piece::QueenB => 4,
+        Piece::KingW | Piece::KingB => 5,
+        Piece::UnicornW | Piece::UnicornB => 6,
+        Piece::DragonW | Piece::DragonB => 7,
+        Piece::PrincessW | Piece::PrincessB => 8,
+        Piece::BrawnW | Piece::BrawnB => 9,
+        Piece::CommonKingW | Piece::CommonKingB => 10,
+        Piece::RoyalQueenW | Piece::RoyalQueenB => 11,
+    })
+}
+
+/// The pieces of a board, as one bitboard per kind of piece and one per color
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PieceBitboards {
+    /// The squares holding each kind of piece, regardless of its color (pawns, knights, bishops, rooks, queens, kings, unicorns, dragons, princesses, brawns, common kings and royal queens)
+    pub kinds: [Bitboard; 12],
+    pub white: Bitboard,
+    pub black: Bitboard,
+}
+
+impl PieceBitboards {
+    /// Creates the bitboards of an empty board of `len` squares
+    pub fn new(len: usize) -> Self {
+        PieceBitboards {
+            kinds: [(); 12].map(|_| Bitboard::empty(len)),
+            white: Bitboard::empty(len),
+            black: Bitboard::empty(len),
+        }
+    }
+
+    /// Returns the piece on the square `i`
+    #[inline]
+    pub fn get(&self, i: usize) -> Piece {
+        let kinds = if self.white.get(i) {
+            &KINDS_W
+        } else if self.black.get(i) {
+            &KINDS_B
+        } else {
+            return Piece::Blank;
+        };
+        self.kinds
+            .iter()
+            .position(|kind| kind.get(i))
+            .map(|k| kinds[k])
+            .unwrap_or(Piece::Blank)
+    }
+
+    /// Puts `piece` on the square `i`, replacing what was there
+    #[inline]
+    pub fn set(&mut self, i: usize, piece: Piece) {
+        if let Some(k) = kind_index(self.get(i)) {
+            self.kinds[k].set(i, false);
+        }
+        self.white.set(i, piece.is_white());
+        self.black.set(i, piece.is_black());
+        if let Some(k) = kind_index(piece) {
+            self.kinds[k].set(i, true);
+        }
+    }
+
+    /// Returns the squares holding a piece
+    pub fn occupied(&self) -> Bitboard {
+        self.white.or(&self.black)
+    }
+
+    /// Returns the squares holding `piece` (of its color); blank squares aren't counted in
+    pub fn squares_of(&self, piece: Piece) -> Bitboard {
+        let color = if piece.is_white() { &self.white } else { &self.black };
+        match kind_index(piece) {
+            Some(k) => self.kinds[k].and(color),
+            None => color.and_not(color),
+        }
+    }
+}
+
+/// The squares attacked by leapers and sliders within a board of a given size, precomputed for every square
+#[derive(Debug)]
+pub struct AttackTables {
+    pub width: u8,
+    pub height: u8,
+    /// `leaps[n][i]`: the squares reached from `i` by one step along the in-board vectors of the `n`-th n-gonal group (knight leaps, orthogonal and diagonal steps)
+    leaps: [Vec<Bitboard>; 3],
+    /// `rays[d][i]`: the squares from `i` (excluded) to the edge of the board along the direction `DIRECTIONS[d]`
+    rays: [Vec<Bitboard>; 8],
+}
+
+/// The in-board directions of sliders: the orthogonal ones (rooks), then the diagonal ones (bishops)
+const DIRECTIONS: [(isize, isize); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, -1), (-1, 1), (1, -1)];
+
+lazy_static! {
+    static ref ATTACK_TABLES: RwLock<HashMap<(u8, u8), Arc<AttackTables>>> = RwLock::new(HashMap::new());
+}
+
+/// Returns the attack tables of `width`x`height` boards, computing them on first use
+pub fn attack_tables(width: u8, height: u8) -> Arc<AttackTables> {
+    if let Some(tables) = ATTACK_TABLES.read().unwrap().get(&(width, height)) {
+        return tables.clone();
+    }
+    ATTACK_TABLES
+        .write()
+        .unwrap()
+        .entry((width, height))
+        .or_insert_with(|| Arc::new(AttackTables::new(width, height)))
+        .clone()
+}
+
+impl AttackTables {
+    fn new(width: u8, height: u8) -> Self {
+        let len = width as usize * height as usize;
+        let square = |x: isize, y: isize| {
+            if x >= 0 && x < width as isize && y >= 0 && y < height as isize {
+                Some((x + y * width as isize) as usize)
+            } else {
+                None
+            }
+        };
+
+        let leaps = [0, 1, 2].map(|n| {
+            let vectors: Vec<(isize, isize)> = super::moves::PERMUTATIONS[n]
+                .iter()
+                .filter(|(dl, dt, _, _)| *dl == 0 && *dt == 0)
+                .map(|(_, _, dx, dy)| (*dx, *dy))
+                .collect();
+            (0..len)
+                .map(|i| {
+                    let (x, y) = ((i % width as usize) as isize, (i / width as usize) as isize);
+                    let mut res = Bitboard::empty(len);
+                    for (dx, dy) in vectors.iter() {
+                        if let Some(j) = square(x + dx, y + dy) {
+                            res.set(j, true);
+                        }
+                    }
+                    res
+                })
+                .collect()
+        });
+
+        let rays = DIRECTIONS.map(|(dx, dy)| {
+            (0..len)
+                .map(|i| {
+                    let (mut x, mut y) = ((i % width as usize) as isize, (i / width as usize) as isize);
+                    let mut res = Bitboard::empty(len);
+                    while let Some(j) = square(x + dx, y + dy) {
+                        res.set(j, true);
+                        x += dx;
+                        y += dy;
+                    }
+                    res
+                })
+                .collect()
+        });
+
+        AttackTables {
+            width,
+            height,
+            leaps,
+            rays,
+        }
+    }
+
+    /// Returns the squares reached from `i` by one step along the in-board vectors of the `n`-th n-gonal group; `n` must be 0, 1 or 2
+    pub fn leaper(&self, n: usize, i: usize) -> &Bitboard {
+        &self.leaps[n][i]
+    }
+
+    /**
+        Returns the squares attacked from `i` by a slider along the in-board vectors of the `n`-th n-gonal group (`n` must be 1 or 2), given the `occupied` squares.
+        The squares of the first piece met in each direction are included, whatever its color.
+    **/
+    pub fn slider(&self, n: usize, i: usize, occupied: &Bitboard) -> Bitboard {
+        let directions = if n == 1 { 0..4 } else { 4..8 };
+        let mut res = Bitboard::empty(self.width as usize * self.height as usize);
+        for d in directions {
+            let ray = &self.rays[d][i];
+            let (dx, dy) = DIRECTIONS[d];
+            // Directions towards higher indices meet their first blocker on the lowest square
+            let blocker = if dy > 0 || dy == 0 && dx > 0 {
+                ray.and(occupied).first()
+            } else {
+                ray.and(occupied).last()
+            };
+            match blocker {
+                Some(b) => res.or_assign(&ray.and_not(&self.rays[d][b])),
+                None => res.or_assign(ray),
+            }
+        }
+        res
+    }
+}
+
+/**
+    Returns the squares of `board` that `piece`, standing on `(x, y)`, attacks without leaving the board: the squares it could capture on, were they held by an opponent piece.
+    Leapers and sliders of the first n-gonal groups use the precomputed `AttackTables`; other movements are followed step by step.
+**/
+pub fn board_attacks(board: &Board, piece: Piece, x: u8, y: u8) -> Bitboard {
+    let len = board.width as usize * board.height as usize;
+    let mut res = Bitboard::empty(len);
+    let kind = match piece.kind() {
+        Some(kind) => kind,
+        None => return res,
+    };
+    let tables = attack_tables(board.width, board.height);
+    let i = x as usize + y as usize * board.width as usize;
+    let occupied = board.bitboards.occupied();
+
+    for movement in kind.movements.iter().filter(|movement| movement.capture != Capture::Never) {
+        match (movement.vectors, movement.range) {
+            (Vectors::NGonal(n), Some(1)) if n <= 2 => res.or_assign(tables.leaper(n, i)),
+            (Vectors::NGonal(n), None) if n == 1 || n == 2 => res.or_assign(&tables.slider(n, i, &occupied)),
+            _ => {
+                for (dl, dt, dx, dy) in movement.vectors.list(piece.is_white()) {
+                    if dl != 0 || dt != 0 {
+                        continue;
+                    }
+                    let mut length: isize = 1;
+                    while movement.range.map(|range| length <= range as isize).unwrap_or(true) {
+                        let x0 = x as isize + dx * length;
+                        let y0 = y as isize + dy * length;
+                        if x0 < 0 || x0 >= board.width as isize || y0 < 0 || y0 >= board.height as isize {
+                            break;
+                        }
+                        let j = (x0 + y0 * board.width as isize) as usize;
+                        res.set(j, true);
+                        if occupied.get(j) {
+                            break;
+                        }
+                        length += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    res
+}
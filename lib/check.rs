@@ -0,0 +1,121 @@
+/*
+    Check detection: listing the pieces that attack the active player's royal pieces (kings and royal queens, see `Piece::is_royal`).
+
+    The active player is in check if the opponent could capture one of their royal pieces, were they to pass on every board that they must play on.
+    Those boards are thus considered as moved forward by one time step, unchanged; coordinates on them are reported on the existing board.
+    The same attack detection decides whether a king may castle, through `is_attacked`.
+*/
+
+use super::{game::*, moves::*, vboard::*};
+
+/// A piece attacking one of the active player's royal pieces
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// The `(l, t, x, y)` coordinates of the attacking piece
+    pub attacker: (i32, isize, u8, u8),
+    /// The attacking piece
+    pub piece: Piece,
+    /// The `(l, t, x, y)` coordinates of the attacked royal piece
+    pub king: (i32, isize, u8, u8),
+    /// The squares between the attacker and the king, starting from the attacker; empty unless the attacking piece slides
+    pub path: Vec<(i32, isize, u8, u8)>,
+}
+
+/// Returns every attack of the opponent's pieces on the royal pieces of `info.active_player`; the active player is in check if it isn't empty.
+/// Boards whose moves can't be generated (see `probable_moves`) are left out.
+pub fn checks<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> Vec<Check> {
+    let passed_boards: Vec<Board> = get_own_boards(virtual_boards, info)
+        .into_iter()
+        .filter(|b| b.is_active(info))
+        .cloned()
+        .map(|mut b| {
+            b.t += 1;
+            b
+        })
+        .collect();
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, passed_boards.iter().collect());
+
+    // Moves the coordinates on passed boards back onto the existing boards
+    let unpass = |(l, t, x, y): (i32, isize, u8, u8)| {
+        if passed_boards.iter().any(|b| b.l == l && b.t == t) {
+            (l, t - 1, x, y)
+        } else {
+            (l, t, x, y)
+        }
+    };
+
+    let mut res: Vec<Check> = Vec::new();
+    for board in get_opponent_boards(&merged_vboards, info) {
+        for mv in probable_moves_without_castling(&merged_vboards, board).unwrap_or_default() {
+            if mv.dst_piece.is_royal() && mv.dst_piece.is_own_piece(info.active_player) {
+                res.push(Check {
+                    attacker: unpass(mv.src),
+                    piece: mv.src_piece,
+                    king: unpass(mv.dst),
+                    path: path_between(mv.src_piece, mv.src, mv.dst).into_iter().map(unpass).collect(),
+                });
+            }
+        }
+    }
+    res
+}
+
+/// Returns whether or not `info.active_player` is in check (see `checks`)
+pub fn is_in_check<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> bool {
+    !checks(virtual_boards, info).is_empty()
+}
+
+/**
+    Returns whether or not the square `(x, y)` of `board` is attacked by the opponent of `board.active_player()`, ie. if an opponent piece could capture a king standing there once `board` is moved forward.
+    Attackers are looked for on every board the opponent can move on, including `board` itself, so attacks coming from other timelines or from the future are found.
+    Other boards that the active player has yet to play on are not moved forward.
+    The square is assumed to be attacked if the moves of an opponent board can't be generated (see `probable_moves`).
+**/
+pub fn is_attacked<T: VirtualBoardset>(virtual_boards: &T, board: &Board, x: u8, y: u8) -> bool {
+    let white = board.active_player();
+    let mut passed_board = board.clone();
+    passed_board.t += 1;
+    passed_board.set_unsafe(x, y, if white { Piece::KingW } else { Piece::KingB });
+
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, vec![&passed_board]);
+    let info = GameInfo {
+        active_player: white,
+        ..virtual_boards.game().info
+    };
+
+    get_opponent_boards(&merged_vboards, &info).into_iter().any(|b| {
+        probable_moves_without_castling(&merged_vboards, b)
+            .is_none_or(|moves| moves.iter().any(|mv| mv.dst == (passed_board.l, passed_board.t, x, y)))
+    })
+}
+
+/// Returns the squares strictly between `src` and `dst`, if `piece` slides from one to the other
+fn path_between(
+    piece: Piece,
+    src: (i32, isize, u8, u8),
+    dst: (i32, isize, u8, u8),
+) -> Vec<(i32, isize, u8, u8)> {
+    let deltas = [
+        (dst.0 - src.0) as isize,
+        (dst.1 - src.1) / 2,
+        dst.2 as isize - src.2 as isize,
+        dst.3 as isize - src.3 as isize,
+    ];
+    let n = deltas.iter().map(|d| d.abs()).max().unwrap_or(0);
+
+    if !piece.slides() || n < 2 || deltas.iter().any(|d| *d != 0 && d.abs() != n) {
+        return vec![];
+    }
+
+    let [dl, dt, dx, dy] = deltas.map(|d| d.signum());
+    (1..n)
+        .map(|i| {
+            (
+                src.0 + (i * dl) as i32,
+                src.1 + 2 * i * dt,
+                (src.2 as isize + i * dx) as u8,
+                (src.3 as isize + i * dy) as u8,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,67 @@
+/*
+    The error type returned by the library's fallible public functions, instead of `None`s or panics.
+*/
+
+use super::moves::MovesetError;
+use std::fmt;
+
+/// An error returned by the library; see its variants
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The input couldn't be read; `path` locates the offending value within it (eg. `timelines[0].states[2]`) and is empty if the input as a whole is at fault
+    Parse { path: String, message: String },
+    /// A 5dchess-notation piece index that doesn't correspond to any `Piece`
+    InvalidPiece(usize),
+    /// The square `(x, y)` is outside of the board
+    OutOfBounds(u8, u8),
+    /// The timeline `l` doesn't exist
+    MissingTimeline(i32),
+    /// The board `(l, t)` doesn't exist
+    MissingBoard(i32, isize),
+    /// The board `(l, t)` already exists and can't be added or played on again
+    BoardCollision(i32, isize),
+    /// The `index`-th moveset of a list isn't legal, for the given `reason`
+    IllegalMove { index: usize, moveset: String, reason: MovesetError },
+    /// The `index`-th moveset of a list couldn't be read as 5D notation
+    InvalidNotation { index: usize, moveset: String },
+}
+
+impl Error {
+    /// Creates a `Parse` error
+    pub fn parse<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+        Error::Parse {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { path, message } if path.is_empty() => write!(f, "Parse error: {}", message),
+            Error::Parse { path, message } => write!(f, "Parse error at {}: {}", path, message),
+            Error::InvalidPiece(raw) => write!(f, "Invalid piece: {}", raw),
+            Error::OutOfBounds(x, y) => write!(f, "Square ({}, {}) is out of bounds", x, y),
+            Error::MissingTimeline(l) => write!(f, "Timeline {} doesn't exist", l),
+            Error::MissingBoard(l, t) => write!(f, "Board {}/{} doesn't exist", l, t),
+            Error::BoardCollision(l, t) => write!(f, "Board already there: {}/{}", l, t),
+            Error::IllegalMove { index, moveset, reason } => write!(f, "Illegal moveset #{}: {} ({:?})", index, moveset, reason),
+            Error::InvalidNotation { index, moveset } => write!(f, "Invalid notation for moveset #{}: {}", index, moveset),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Errors of the serde deserializers (JSON, RON), along with the path at which they occured
+impl<E: fmt::Display> From<serde_path_to_error::Error<E>> for Error {
+    fn from(err: serde_path_to_error::Error<E>) -> Self {
+        let path = err.path().to_string();
+        Error::Parse {
+            // `serde_path_to_error` writes the root as `.`
+            path: if path == "." { String::new() } else { path },
+            message: err.into_inner().to_string(),
+        }
+    }
+}
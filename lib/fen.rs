@@ -0,0 +1,289 @@
+/*
+    A compact, single-line textual format for positions ("5D FEN"), for test fixtures and bug reports.
+
+    A position is written as space-separated fields: `<width>x<height> <w|b> <present> [=<settings>] <timeline> <timeline> ...`, where:
+
+    - `w` or `b` is the active player and `present` is `GameInfo::present` (the time coordinate `t`, not the turn)
+    - `settings` is written as `<promotions>,<rules>`, where `<promotions>` lists the pieces that pawns may promote to as uppercase letters (or `-` if they can't promote)
+      and `<rules>` is `C` if castling is allowed (`Game::castling`), `D` if pawns may double-step (`Game::pawn_double_step`), both or `-`;
+      the field is left out if the settings are those of `Game::new`, ie. `=QNRB,CD`
+    - each timeline is written as `<index>@<begins_at>[~<emerges_from>]:<board>:<board>...`, the index being displayed as in-game (`0`, `-1`, `+0`, `-0`, ...)
+    - each board is written as `<rows>,<castling>[,<moved>]`, with `<rows>` in the format of `variants::board_from_rows` and `<castling>` being `KQkq`, a subset of it or `-`
+
+    Whether each piece has moved is derived from the castling rights and from the rank of pawns and brawns (see `infer_moved_flags`):
+    pawns and brawns have moved unless they stand on their second rank, kings and rooks have moved unless they are needed for the castling rights and other pieces haven't moved.
+    `<moved>` lists the squares for which this guess is wrong (eg. `e2f3`, for a pawn that travelled back to `e2` and a knight that went to `f3`), so that the moved flags are read back exactly.
+
+    For instance, the standard starting position is `8x8 w 0 0@0:rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR,KQkq`.
+    Every board of every timeline is written, so en passant and time travel are preserved; the history can be shortened by only writing the last boards of a timeline and moving its `begins_at` accordingly.
+*/
+
+use super::error::Error;
+use super::game::*;
+use super::variants::{board_from_rows, board_to_rows, parse_promotions};
+
+impl Game {
+    /// Writes this game as a 5D FEN string; see the `fen` module
+    pub fn to_fen(&self) -> String {
+        let even = self.info.even_initial_timelines;
+        let mut res = format!(
+            "{}x{} {} {}",
+            self.width,
+            self.height,
+            if self.info.active_player { "w" } else { "b" },
+            self.info.present
+        );
+
+        let settings = write_settings(self);
+        if settings != write_settings(&Game::new(self.width, self.height)) {
+            res.push_str(" =");
+            res.push_str(&settings);
+        }
+
+        let mut timelines: Vec<&Timeline> = self.timelines.values().collect();
+        timelines.sort_by_key(|tl| tl.index);
+
+        for tl in timelines {
+            res.push(' ');
+            res.push_str(&write_timeline(tl.index, even));
+            res.push('@');
+            res.push_str(&tl.begins_at.to_string());
+            if let Some(emerges_from) = tl.emerges_from {
+                res.push('~');
+                res.push_str(&write_timeline(emerges_from, even));
+            }
+            for board in tl.states.iter() {
+                res.push(':');
+                res.push_str(&write_board(board, self.castling));
+            }
+        }
+
+        res
+    }
+
+    /// Reads a game from a 5D FEN string (see the `fen` module); returns `Error::Parse`, along with the field at fault, if the string is invalid
+    pub fn from_fen(raw: &str) -> Result<Game, Error> {
+        let mut fields = raw.split_whitespace().peekable();
+
+        let raw_size = fields.next().ok_or_else(|| Error::parse("", "empty string"))?;
+        let invalid_size = || Error::parse("size", format!("invalid size: {}", raw_size));
+        let mut size = raw_size.splitn(2, 'x');
+        let width: u8 = size.next().and_then(|w| w.parse().ok()).ok_or_else(invalid_size)?;
+        let height: u8 = size.next().and_then(|h| h.parse().ok()).ok_or_else(invalid_size)?;
+        let mut game = Game::new(width, height);
+
+        game.info.active_player = match fields.next() {
+            Some("w") => true,
+            Some("b") => false,
+            player => return Err(Error::parse("player", format!("expected w or b, got {:?}", player))),
+        };
+        let raw_present = fields.next().unwrap_or_default();
+        game.info.present = raw_present
+            .parse()
+            .map_err(|_| Error::parse("present", format!("invalid present: {}", raw_present)))?;
+
+        if let Some(raw_settings) = fields.peek().and_then(|field| field.strip_prefix('=')) {
+            read_settings(&mut game, raw_settings)
+                .ok_or_else(|| Error::parse("settings", format!("invalid settings: {}", raw_settings)))?;
+            fields.next();
+        }
+
+        let timelines: Vec<&str> = fields.collect();
+        let even = timelines.iter().any(|tl| tl.starts_with("-0@") || tl.starts_with("+0@"));
+        game.info.even_initial_timelines = even;
+
+        for (index, raw_timeline) in timelines.into_iter().enumerate() {
+            let path = format!("timelines[{}]", index);
+            let invalid_timeline = || Error::parse(path.as_str(), format!("invalid timeline: {}", raw_timeline));
+            let mut boards = raw_timeline.split(':');
+            let (l, begins_at, emerges_from) = boards
+                .next()
+                .and_then(|header| read_timeline_header(header, even))
+                .ok_or_else(invalid_timeline)?;
+
+            let mut timeline = Timeline::new(l, width, height, begins_at, emerges_from);
+            for (i, raw_board) in boards.enumerate() {
+                let t = begins_at + i as isize;
+                timeline.states.push(read_board(raw_board, l, t, width, height).ok_or_else(|| {
+                    Error::parse(format!("{}.boards[{}]", path, i), format!("invalid board: {}", raw_board))
+                })?);
+            }
+            if timeline.states.is_empty() {
+                return Err(Error::parse(path, "expected at least one board"));
+            }
+            if game.timelines.insert(l, timeline).is_some() {
+                return Err(Error::parse(path, format!("timeline {} is given twice", write_timeline(l, even))));
+            }
+        }
+
+        let no_timeline = || Error::parse("timelines", "expected at least one timeline");
+        game.info.min_timeline = *game.timelines.keys().min().ok_or_else(no_timeline)?;
+        game.info.max_timeline = *game.timelines.keys().max().ok_or_else(no_timeline)?;
+
+        Ok(game)
+    }
+}
+
+/// Writes the `<promotions>,<rules>` settings of `game`
+fn write_settings(game: &Game) -> String {
+    let mut promotions: String = game.promotions.iter().map(|p| p.to_string()).collect();
+    if promotions.is_empty() {
+        promotions.push('-');
+    }
+    let rules = match (game.castling, game.pawn_double_step) {
+        (true, true) => "CD",
+        (true, false) => "C",
+        (false, true) => "D",
+        (false, false) => "-",
+    };
+    format!("{},{}", promotions, rules)
+}
+
+/// Reads the `<promotions>,<rules>` settings into `game`; returns None if they are invalid
+fn read_settings(game: &mut Game, raw: &str) -> Option<()> {
+    let (promotions, rules) = raw.split_once(',')?;
+
+    game.promotions = if promotions == "-" {
+        Vec::new()
+    } else {
+        parse_promotions(promotions)?
+    };
+    if rules != "-" && !rules.chars().all(|c| c == 'C' || c == 'D') {
+        return None;
+    }
+    game.castling = rules.contains('C');
+    game.pawn_double_step = rules.contains('D');
+
+    Some(())
+}
+
+/// Reads the `<index>@<begins_at>[~<emerges_from>]` header of a timeline
+fn read_timeline_header(raw: &str, even: bool) -> Option<(i32, isize, Option<i32>)> {
+    let mut split = raw.splitn(2, '@');
+    let l = parse_timeline(split.next()?, even)?;
+    let begins_at = split.next()?;
+    let (begins_at, emerges_from) = match begins_at.find('~') {
+        Some(i) => (&begins_at[..i], Some(parse_timeline(&begins_at[i + 1..], even)?)),
+        None => (begins_at, None),
+    };
+    Some((l, begins_at.parse().ok()?, emerges_from))
+}
+
+/// Writes `board` as `<rows>,<castling>[,<moved>]`; `castling` tells whether the game allows castling at all
+fn write_board(board: &Board, castling: bool) -> String {
+    let rights = if castling { write_castling(board) } else { String::from("-") };
+    let mut res = format!("{},{}", board_to_rows(board), rights);
+
+    let mut inferred = board.clone();
+    // `board` was written by us, so the castling rights can always be matched to a king and a rook
+    if infer_moved_flags(&mut inferred, &rights).is_some() && inferred.moved != board.moved {
+        res.push(',');
+        for y in 0..board.height {
+            for x in 0..board.width {
+                if inferred.has_moved(x, y) != board.has_moved(x, y) {
+                    res.push(write_file(x));
+                    res.push_str(&(y + 1).to_string());
+                }
+            }
+        }
+    }
+
+    res
+}
+
+/// Writes the castling rights of `board`, eg. `KQkq` or `-` (see `Board::castling_rook`)
+fn write_castling(board: &Board) -> String {
+    let mut res = String::new();
+    for (white, long, c) in [(true, false, 'K'), (true, true, 'Q'), (false, false, 'k'), (false, true, 'q')] {
+        if board.castling_rook(white, long).is_some() {
+            res.push(c);
+        }
+    }
+    if res.is_empty() {
+        res.push('-');
+    }
+    res
+}
+
+/// Reads a `<rows>,<castling>[,<moved>]` board, locates its kings and sets the moved flags of its pieces
+fn read_board(raw: &str, l: i32, t: isize, width: u8, height: u8) -> Option<Board> {
+    let mut split = raw.splitn(3, ',');
+    let mut board = board_from_rows(split.next()?, l, t, width, height)?;
+
+    let castling = split.next()?;
+    if castling != "-" && !castling.chars().all(|c| "KQkq".contains(c)) {
+        return None;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            match board.get_unsafe(x, y) {
+                Piece::KingW => board.king_w = Some((x, y)),
+                Piece::KingB => board.king_b = Some((x, y)),
+                _ => {}
+            }
+        }
+    }
+    infer_moved_flags(&mut board, castling)?;
+
+    // The squares whose flag isn't the one that was inferred
+    let mut moved = split.next().unwrap_or_default();
+    while !moved.is_empty() {
+        let x = parse_file(moved.chars().next()?)?;
+        let digits = moved[1..].len() - moved[1..].trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let y = moved[1..1 + digits].parse::<u8>().ok()?.checked_sub(1)?;
+        if y >= height || x >= width {
+            return None;
+        }
+        board.set_moved(x, y, !board.has_moved(x, y));
+        moved = &moved[1 + digits..];
+    }
+
+    Some(board)
+}
+
+/**
+    Guesses the moved flags of the pieces of `board` from its `castling` rights (`KQkq`, a subset of it or `-`):
+    pawns and brawns have moved unless they stand on their second rank, kings and rooks have moved unless they are needed for the castling rights and other pieces haven't moved.
+    Returns None if a castling right has no king and rook to go with.
+**/
+fn infer_moved_flags(board: &mut Board, castling: &str) -> Option<()> {
+    let (width, height) = (board.width, board.height);
+    for y in 0..height {
+        for x in 0..width {
+            let piece = board.get_unsafe(x, y);
+            let moved = match piece {
+                Piece::PawnW | Piece::BrawnW => y != 1,
+                Piece::PawnB | Piece::BrawnB => y + 2 != height,
+                Piece::KingW | Piece::KingB | Piece::RookW | Piece::RookB => true,
+                _ => false,
+            };
+            board.set_moved(x, y, moved);
+        }
+    }
+
+    for (white, long, c) in [(true, false, 'K'), (true, true, 'Q'), (false, false, 'k'), (false, true, 'q')] {
+        if !castling.contains(c) {
+            continue;
+        }
+        let (king, rook) = if white {
+            (Piece::KingW, Piece::RookW)
+        } else {
+            (Piece::KingB, Piece::RookB)
+        };
+        // Time travel may bring several kings onto a board: the right goes to the first one with a rook on that side, like in `Board::castling_rook`
+        let (king_x, y, rook_x) = board.bitboards.squares_of(king).iter().find_map(|i| {
+            let (king_x, y) = ((i % width as usize) as u8, (i / width as usize) as u8);
+            let rook_x = if long {
+                (0..king_x).rev().find(|&x| board.get_unsafe(x, y) == rook)?
+            } else {
+                (king_x + 1..width).find(|&x| board.get_unsafe(x, y) == rook)?
+            };
+            Some((king_x, y, rook_x))
+        })?;
+        board.set_moved(king_x, y, false);
+        board.set_moved(rook_x, y, false);
+    }
+
+    Some(())
+}
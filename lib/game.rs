@@ -2,21 +2,41 @@
     Structures and functions related to the game's state.
 */
 
+use super::bitboard::*;
+use super::moves::Move;
+use super::error::Error;
+use super::schema::BoardRaw;
+use super::zobrist::{moved_key, piece_key};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::convert::TryFrom;
 use std::collections::HashMap;
 
-/// The main structure, contains the entire state of a game
-#[derive(Debug)]
+/// The main structure, contains the entire state of a game; see `schema` for its serialized form
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Game {
     pub timelines: HashMap<i32, Timeline>,
     pub width: u8,
     pub height: u8,
     pub info: GameInfo,
+    pub pawn_double_step: bool, // whether pawns that haven't moved yet may move two squares forward
+    pub castling: bool, // whether kings that haven't moved yet may castle with a rook that hasn't moved either
+    pub promotions: Vec<Piece>, // the pieces that pawns may promote to, as white pieces
+    #[serde(default)]
+    pub history: Vec<MadeMoveset>, // the movesets made with `make_moveset`, most recent last
+}
+
+/// A moveset made with `Game::make_moveset`, along with what is needed to unmake it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MadeMoveset {
+    pub moves: Vec<Move>,
+    pub boards: Vec<(i32, isize)>, // the `(l, t)` coordinates of the boards that it added
+    pub new_timelines: Vec<i32>, // the timelines that it created
+    pub previous_info: GameInfo, // the game's information before it was made
 }
 
 /// Information about whose turn it is, where the present is and timeline priority
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct GameInfo {
     pub present: isize,
     pub active_player: bool,
@@ -26,7 +46,7 @@ pub struct GameInfo {
 }
 
 /// Represents an in-game timeline
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Timeline {
     pub index: i32,
     pub states: Vec<Board>,
@@ -37,17 +57,19 @@ pub struct Timeline {
 }
 
 /// Represents an in-game board (be it active or not)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "BoardRaw", try_from = "BoardRaw")]
 pub struct Board {
-    pub pieces: Vec<Piece>,
+    pub bitboards: PieceBitboards, // the pieces on the board, one bitboard per kind and per color; see `get` and `set`
     pub width: u8,
     pub height: u8,
     pub l: i32, // its timeline
     pub t: isize, // its time coordinate
     pub king_w: Option<(u8, u8)>, // TODO: update if the king moves
     pub king_b: Option<(u8, u8)>,
-    pub castle_w: (bool, bool),
-    pub castle_b: (bool, bool),
+    pub moved: Bitboard, // the squares whose piece has moved (or travelled) since the start of the game
+    pub hash: u64, // zobrist hash of the pieces and of the moved flags, kept up to date by `set` and `set_moved`; see `zobrist::content_hash`
+    pub emerges_from: Option<i32>, // the timeline that this board branches off from, if it is the first board of a timeline created by a move; read and cleared by `Game::commit_moves` and `Game::make_moveset`
 }
 
 /// Represents the contents of a board's square
@@ -64,6 +86,9 @@ pub enum Piece {
     UnicornW,
     DragonW,
     PrincessW,
+    BrawnW,
+    CommonKingW,
+    RoyalQueenW,
 
     KingB,
     QueenB,
@@ -74,6 +99,9 @@ pub enum Piece {
     UnicornB,
     DragonB,
     PrincessB,
+    BrawnB,
+    CommonKingB,
+    RoyalQueenB,
 }
 
 impl Game {
@@ -88,7 +116,35 @@ impl Game {
                 active_player: true,
                 min_timeline: 0,
                 max_timeline: 0,
-            }
+            },
+            pawn_double_step: true,
+            castling: true,
+            promotions: vec![Piece::QueenW, Piece::KnightW, Piece::RookW, Piece::BishopW],
+            history: Vec::new(),
+        }
+    }
+}
+
+impl GameInfo {
+    /**
+        Returns whether or not the `l` timeline is active, ie. whether its last board counts towards the present.
+        A player's timelines are active as long as they have created at most one more timeline than their opponent; the initial timelines are always active.
+    **/
+    pub fn is_timeline_active(&self, l: i32) -> bool {
+        // Number of timelines created by each player
+        let created_w = self.max_timeline;
+        let created_b = if self.even_initial_timelines {
+            -self.min_timeline - 1
+        } else {
+            -self.min_timeline
+        };
+
+        if l >= 0 {
+            l <= created_b + 1
+        } else if self.even_initial_timelines {
+            -l - 1 <= created_w + 1
+        } else {
+            -l <= created_w + 1
         }
     }
 }
@@ -106,10 +162,12 @@ impl Timeline {
     }
 }
 
-impl From<usize> for Piece {
-    /// Converts 5dchess-notation piece indices into `Piece`s.
-    fn from(raw: usize) -> Self {
-        match raw {
+impl TryFrom<usize> for Piece {
+    type Error = Error;
+
+    /// Converts 5dchess-notation piece indices into `Piece`s; unknown indices yield `Error::InvalidPiece`
+    fn try_from(raw: usize) -> Result<Self, Error> {
+        Ok(match raw {
             0 => Piece::Blank,
             1 => Piece::PawnW,
             2 => Piece::KnightW,
@@ -120,6 +178,9 @@ impl From<usize> for Piece {
             7 => Piece::UnicornW,
             8 => Piece::DragonW,
             9 => Piece::PrincessW,
+            10 => Piece::BrawnW,
+            11 => Piece::CommonKingW,
+            12 => Piece::RoyalQueenW,
             33 => Piece::PawnB,
             34 => Piece::KnightB,
             35 => Piece::BishopB,
@@ -129,8 +190,11 @@ impl From<usize> for Piece {
             39 => Piece::UnicornB,
             40 => Piece::DragonB,
             41 => Piece::PrincessB,
-            _ => panic!("Invalid piece: {}", raw),
-        }
+            42 => Piece::BrawnB,
+            43 => Piece::CommonKingB,
+            44 => Piece::RoyalQueenB,
+            _ => return Err(Error::InvalidPiece(raw)),
+        })
     }
 }
 
@@ -148,6 +212,9 @@ impl From<Piece> for usize {
             Piece::UnicornW => 7,
             Piece::DragonW => 8,
             Piece::PrincessW => 9,
+            Piece::BrawnW => 10,
+            Piece::CommonKingW => 11,
+            Piece::RoyalQueenW => 12,
             Piece::PawnB => 33,
             Piece::KnightB => 34,
             Piece::BishopB => 35,
@@ -157,6 +224,9 @@ impl From<Piece> for usize {
             Piece::UnicornB => 39,
             Piece::DragonB => 40,
             Piece::PrincessB => 41,
+            Piece::BrawnB => 42,
+            Piece::CommonKingB => 43,
+            Piece::RoyalQueenB => 44,
         }
     }
 }
@@ -174,7 +244,48 @@ impl Piece {
             Piece::UnicornW | Piece::UnicornB => "U",
             Piece::DragonW | Piece::DragonB => "D",
             Piece::PrincessW | Piece::PrincessB => "S",
-        }
+            Piece::BrawnW | Piece::BrawnB => "W",
+            Piece::CommonKingW | Piece::CommonKingB => "C",
+            Piece::RoyalQueenW | Piece::RoyalQueenB => "Y",
+        }
+    }
+
+    /// Converts a letter as written by `Display` (uppercase for white, lowercase for black, `.` for blank) into a `Piece`, None if unknown
+    pub fn from_char(c: char) -> Option<Piece> {
+        Some(match c {
+            '.' => Piece::Blank,
+            'P' => Piece::PawnW,
+            'N' => Piece::KnightW,
+            'B' => Piece::BishopW,
+            'R' => Piece::RookW,
+            'Q' => Piece::QueenW,
+            'K' => Piece::KingW,
+            'U' => Piece::UnicornW,
+            'D' => Piece::DragonW,
+            'S' => Piece::PrincessW,
+            'W' => Piece::BrawnW,
+            'C' => Piece::CommonKingW,
+            'Y' => Piece::RoyalQueenW,
+            'p' => Piece::PawnB,
+            'n' => Piece::KnightB,
+            'b' => Piece::BishopB,
+            'r' => Piece::RookB,
+            'q' => Piece::QueenB,
+            'k' => Piece::KingB,
+            'u' => Piece::UnicornB,
+            'd' => Piece::DragonB,
+            's' => Piece::PrincessB,
+            'w' => Piece::BrawnB,
+            'c' => Piece::CommonKingB,
+            'y' => Piece::RoyalQueenB,
+            _ => return None,
+        })
+    }
+
+    /// Returns the same kind of piece, but of the given color (white if `white` is true); `Piece::Blank` stays blank
+    pub fn with_color(&self, white: bool) -> Piece {
+        let c = self.as_uppercase().chars().next().unwrap();
+        Piece::from_char(if white { c } else { c.to_ascii_lowercase() }).unwrap_or(Piece::Blank)
     }
 
     /// Returns whether or not that `Piece` is `Piece::Blank`
@@ -198,7 +309,10 @@ impl Piece {
             | Piece::KingW
             | Piece::UnicornW
             | Piece::DragonW
-            | Piece::PrincessW => true,
+            | Piece::PrincessW
+            | Piece::BrawnW
+            | Piece::CommonKingW
+            | Piece::RoyalQueenW => true,
             _ => false,
         }
     }
@@ -215,12 +329,15 @@ impl Piece {
             | Piece::KingB
             | Piece::UnicornB
             | Piece::DragonB
-            | Piece::PrincessB => true,
+            | Piece::PrincessB
+            | Piece::BrawnB
+            | Piece::CommonKingB
+            | Piece::RoyalQueenB => true,
             _ => false,
         }
     }
 
-    /// Returns whether or not that `Piece` is a `Piece::King*`
+    /// Returns whether or not that Piece is a `Piece::King*`; common kings aren't kings (see `is_common_king`), and `is_royal` tells royalty
     #[inline]
     pub fn is_king(&self) -> bool {
         match &self {
@@ -229,15 +346,30 @@ impl Piece {
         }
     }
 
-    /// Returns whether or not that Piece is a `Piece::Queen*`
+    /// Returns whether or not that Piece is a `Piece::CommonKing*`, which moves like a king but isn't royal
+    #[inline]
+    pub fn is_common_king(&self) -> bool {
+        match &self {
+            Piece::CommonKingW | Piece::CommonKingB => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether or not that Piece moves like a queen, ie. if it is a `Piece::Queen*` or a `Piece::RoyalQueen*`
     #[inline]
     pub fn is_queen(&self) -> bool {
         match &self {
-            Piece::QueenW | Piece::QueenB => true,
+            Piece::QueenW | Piece::QueenB | Piece::RoyalQueenW | Piece::RoyalQueenB => true,
             _ => false,
         }
     }
 
+    /// Returns whether or not that Piece is royal (see `PieceKind::royal`): a player whose royal pieces can be captured is in check
+    #[inline]
+    pub fn is_royal(&self) -> bool {
+        self.kind().map(|kind| kind.royal).unwrap_or(false)
+    }
+
     /// Returns whether or not that Piece is a `Piece::Pawn*`
     #[inline]
     pub fn is_pawn(&self) -> bool {
@@ -247,6 +379,15 @@ impl Piece {
         }
     }
 
+    /// Returns whether or not that Piece is a `Piece::Brawn*`
+    #[inline]
+    pub fn is_brawn(&self) -> bool {
+        match &self {
+            Piece::BrawnW | Piece::BrawnB => true,
+            _ => false,
+        }
+    }
+
     /// Returns whether or not that Piece is a `Piece::Knight*`
     #[inline]
     pub fn is_knight(&self) -> bool {
@@ -306,21 +447,7 @@ impl Piece {
     **/
     #[inline]
     pub fn slides(&self) -> bool {
-        match &self {
-            Piece::BishopW
-            | Piece::RookW
-            | Piece::QueenW
-            | Piece::UnicornW
-            | Piece::DragonW
-            | Piece::PrincessW
-            | Piece::BishopB
-            | Piece::RookB
-            | Piece::QueenB
-            | Piece::UnicornB
-            | Piece::DragonB
-            | Piece::PrincessB => true,
-            _ => false,
-        }
+        self.kind().map(|kind| kind.slides()).unwrap_or(false)
     }
 
     /// Whether or not the target piece belongs to the opponent
@@ -377,8 +504,17 @@ impl Game {
     }
 
     /// Returns the `(l, t)` board, panics if not found
+    #[deprecated(note = "panics if the board doesn't exist; use `try_get_board` or `get_board` instead")]
     pub fn get_board_unsafe<'a>(&'a self, l: i32, t: isize) -> &'a Board {
-        self.get_timeline(l).expect("Couldn't find timeline!").get_board_unsafe(t)
+        self.get_board(l, t).expect("Couldn't find board!")
+    }
+
+    /// Returns the `(l, t)` board, `Error::MissingTimeline` or `Error::MissingBoard` if not found
+    pub fn try_get_board<'a>(&'a self, l: i32, t: isize) -> Result<&'a Board, Error> {
+        self.get_timeline(l)
+            .ok_or(Error::MissingTimeline(l))?
+            .get_board(t)
+            .ok_or(Error::MissingBoard(l, t))
     }
 
     /// Returns a mutable reference to the `(l, t)` board, None if not found
@@ -389,10 +525,9 @@ impl Game {
     }
 
     /// Returns a mutable reference to the `(l, t)` board, panics if not found
+    #[deprecated(note = "panics if the board doesn't exist; use `get_board_mut` instead")]
     pub fn get_board_mut_unsafe<'a>(&'a mut self, l: i32, t: isize) -> &'a mut Board {
-        self.get_timeline_mut(l)
-            .expect("Couldn't find timeline!")
-            .get_board_mut_unsafe(t)
+        self.get_board_mut(l, t).expect("Couldn't find board!")
     }
 
     /// Returns the last board in the `l`-th timeline, None if not found
@@ -406,11 +541,13 @@ impl Game {
     }
 
     /// Returns the `(l, t, x, y)` piece, panics if not found
+    #[deprecated(note = "panics if the board doesn't exist; use `try_get_board` or `get` instead")]
     pub fn get_unsafe<'a>(&'a self, l: i32, t: isize, x: u8, y: u8) -> Piece {
-        self.timelines[&l].get_unsafe(t, x, y)
+        self.get_board(l, t).expect("Couldn't find board!").get_unsafe(x, y)
     }
 
     /** Appends a set of boards to the current game structure; currently only supports appending one board to every timeline.
+        Returns `Error::BoardCollision` and leaves the game untouched if one of the boards already exists or is given twice.
         This is usually used together with `Move::generate_vboards`:
 
         ```
@@ -419,37 +556,83 @@ impl Game {
 
         // Assign to `mv` and `game`
 
-        let boards = mv.generate_vboards(&game, &game.info, &vec![], &vec![]);
-        game.commit_moves(boards);
+        let (_info, boards) = mv.generate_vboards(&game, &game.info, &[])?;
+        game.commit_moves(boards)?;
         ```
     **/
-    pub fn commit_moves(&mut self, mut boards: Vec<Board>) {
+    pub fn commit_moves(&mut self, boards: Vec<Board>) -> Result<(), Error> {
+        self.push_boards(boards)?;
+        self.info.active_player = !self.info.active_player;
+        Ok(())
+    }
+
+    /**
+        Makes the moveset `moves` on the game: appends the `boards` that it created and replaces the game's information with `info`, as yielded by `legal_movesets` or `score_moveset`.
+        What is needed to unmake it is pushed onto `history` (see `unmake_moveset`).
+        Returns `Error::BoardCollision` and leaves the game untouched if one of the boards already exists or is given twice.
+    **/
+    pub fn make_moveset(&mut self, moves: Vec<Move>, boards: Vec<Board>, info: GameInfo) -> Result<(), Error> {
+        let coordinates: Vec<(i32, isize)> = boards.iter().map(|b| (b.l, b.t)).collect();
+        let new_timelines = self.push_boards(boards)?;
+        self.history.push(MadeMoveset {
+            moves,
+            boards: coordinates,
+            new_timelines,
+            previous_info: self.info,
+        });
+        self.info = info;
+        Ok(())
+    }
+
+    /**
+        Unmakes the last moveset made with `make_moveset`: removes the boards and the timelines that it added and restores the previous game information.
+        Returns that moveset, None if no moveset was made.
+    **/
+    pub fn unmake_moveset(&mut self) -> Option<MadeMoveset> {
+        let made = self.history.pop()?;
+        for l in made.new_timelines.iter() {
+            self.timelines.remove(l);
+        }
+        for (l, t) in made.boards.iter() {
+            if let Some(tl) = self.get_timeline_mut(*l) {
+                if tl.get_last_board().map(|b| b.t) == Some(*t) {
+                    tl.states.pop();
+                }
+            }
+        }
+        self.info = made.previous_info;
+        Some(made)
+    }
+
+    /// Appends `boards` to their timelines, creating the missing timelines, and returns the created timelines; nothing is appended if one of the boards already exists or is given twice
+    fn push_boards(&mut self, mut boards: Vec<Board>) -> Result<Vec<i32>, Error> {
+        let collision = boards.iter().enumerate().find(|(i, b)| {
+            self.get_board(b.l, b.t).is_some() || boards[..*i].iter().any(|other| other.l == b.l && other.t == b.t)
+        });
+        if let Some((_, b)) = collision {
+            return Err(Error::BoardCollision(b.l, b.t));
+        }
+
+        let mut new_timelines = Vec::new();
         boards.sort_by_key(|b| b.t);
         boards.reverse();
-        for b in boards.into_iter() {
+        for mut b in boards.into_iter() {
+            let emerges_from = b.emerges_from.take();
             if let Some(tl) = self.get_timeline_mut(b.l) {
-                if tl.get_board(b.t).is_none() {
-                    tl.states.push(b)
-                } else {
-                    for (i, b2) in tl.states.iter().enumerate() {
-                        println!("> {}+{}={} : {}", tl.begins_at, i, tl.begins_at + i as isize, b2.t);
-                    }
-                    println!("{:?}", tl.get_board(b.t));
-                    println!("{:?}", b);
-                    panic!("Board already there: {}/{}", b.l, b.t);
-                }
+                tl.states.push(b)
             } else {
+                new_timelines.push(b.l);
                 self.timelines.insert(b.l, Timeline {
                     index: b.l,
                     begins_at: b.t,
                     width: self.width,
                     height: self.height,
-                    emerges_from: None,
+                    emerges_from,
                     states: vec![b],
                 });
             }
         }
-        self.info.active_player = !self.info.active_player;
+        Ok(new_timelines)
     }
 }
 
@@ -469,6 +652,7 @@ impl Timeline {
     }
 
     /// Returns the board at `t` in this timeline; panics if the board does not exist
+    #[deprecated(note = "panics if the board doesn't exist; use `get_board` instead")]
     pub fn get_board_unsafe<'a>(&'a self, t: isize) -> &'a Board {
         &self.states[(t - self.begins_at) as usize]
     }
@@ -483,6 +667,7 @@ impl Timeline {
     }
 
     /// Returns a mutable reference to the board at `t` in this timeline, panics if the board does not exist
+    #[deprecated(note = "panics if the board doesn't exist; use `get_board_mut` instead")]
     pub fn get_board_mut_unsafe<'a>(&'a mut self, t: isize) -> &'a mut Board {
         &mut self.states[(t - self.begins_at) as usize]
     }
@@ -493,8 +678,9 @@ impl Timeline {
     }
 
     /// Returns the piece at `(t, x, y)` in this timeline, panics if the square does not exist. UB if that board's size is not equal to the timeline's own size
+    #[deprecated(note = "panics if the board doesn't exist; use `get` instead")]
     pub fn get_unsafe<'a>(&'a self, t: isize, x: u8, y: u8) -> Piece {
-        self.states[(t - self.begins_at) as usize].pieces[(x + self.width * y) as usize]
+        self.states[(t - self.begins_at) as usize].get_unsafe(x, y)
     }
 }
 
@@ -506,11 +692,12 @@ impl Board {
             l,
             width,
             height,
-            pieces: vec![Piece::Blank; (width as usize) * (height as usize)],
+            bitboards: PieceBitboards::new((width as usize) * (height as usize)),
             king_w: None,
             king_b: None,
-            castle_w: (false, false),
-            castle_b: (false, false),
+            moved: Bitboard::empty((width as usize) * (height as usize)),
+            hash: 0,
+            emerges_from: None,
         }
     }
 
@@ -519,28 +706,86 @@ impl Board {
         if x >= self.width || y >= self.height {
             None
         } else {
-            self.pieces.get((x + y * self.width) as usize).copied()
+            Some(self.get_unsafe(x, y))
         }
     }
 
     /// Returns the piece at `(x, y)`, panics if not found
     pub fn get_unsafe(&self, x: u8, y: u8) -> Piece {
-        self.pieces[(x + y * self.width) as usize]
+        self.bitboards.get(self.index(x, y))
+    }
+
+    /// Returns the index of the square `(x, y)` in the bitboards
+    #[inline]
+    pub fn index(&self, x: u8, y: u8) -> usize {
+        x as usize + y as usize * self.width as usize
     }
 
-    /// Sets the piece at `(x, y)`, returns `Ok` on success and `Err` if the square does not exist
-    pub fn set(&mut self, x: u8, y: u8, piece: Piece) -> Result<(), ()> {
+    /// Returns the number of squares of the board
+    pub fn n_squares(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Iterates over the pieces of the board, square by square (`x` first, then `y`)
+    pub fn pieces(&self) -> impl Iterator<Item = Piece> + '_ {
+        (0..self.n_squares()).map(move |index| self.bitboards.get(index))
+    }
+
+    /// Sets the piece at `(x, y)`, returns `Error::OutOfBounds` if the square does not exist
+    pub fn set(&mut self, x: u8, y: u8, piece: Piece) -> Result<(), Error> {
         if x >= self.width || y >= self.height {
-            Err(())
+            Err(Error::OutOfBounds(x, y))
         } else {
-            self.pieces[(x + y * self.width) as usize] = piece;
+            self.set_unsafe(x, y, piece);
             Ok(())
         }
     }
 
     /// Sets the piece at `(x, y)`, panics if the square does not exist
     pub fn set_unsafe(&mut self, x: u8, y: u8, piece: Piece) {
-        self.pieces[(x + y * self.width) as usize] = piece;
+        let index = self.index(x, y);
+        self.hash ^= piece_key(x, y, self.bitboards.get(index)) ^ piece_key(x, y, piece);
+        self.bitboards.set(index, piece);
+    }
+
+    /// Returns whether or not the piece at `(x, y)` has moved since the start of the game, panics if the square does not exist
+    pub fn has_moved(&self, x: u8, y: u8) -> bool {
+        self.moved.get(self.index(x, y))
+    }
+
+    /// Sets whether or not the piece at `(x, y)` has moved, panics if the square does not exist
+    pub fn set_moved(&mut self, x: u8, y: u8, moved: bool) {
+        let index = self.index(x, y);
+        if self.moved.get(index) != moved {
+            self.hash ^= moved_key(x, y);
+        }
+        self.moved.set(index, moved);
+    }
+
+    /**
+        Returns the position of `white`'s king and the `x` coordinate of the rook that it may castle with, on the long side (towards `x = 0`) if `long` is true and on the short side otherwise.
+        Returns None if there is no such king and rook or if either of them has moved; the squares between them and whether the king is attacked are not checked.
+    **/
+    pub fn castling_rook(&self, white: bool, long: bool) -> Option<((u8, u8), u8)> {
+        let (king, rook) = if white {
+            (Piece::KingW, Piece::RookW)
+        } else {
+            (Piece::KingB, Piece::RookB)
+        };
+        let index = self.bitboards.squares_of(king).and_not(&self.moved).first()?;
+        let (king_x, y) = ((index % self.width as usize) as u8, (index / self.width as usize) as u8);
+
+        let rook_x = if long {
+            (0..king_x).rev().find(|&x| self.get_unsafe(x, y) == rook)?
+        } else {
+            (king_x + 1..self.width).find(|&x| self.get_unsafe(x, y) == rook)?
+        };
+
+        if self.has_moved(rook_x, y) {
+            None
+        } else {
+            Some(((king_x, y), rook_x))
+        }
     }
 
     /// Returns whose player's turn it is on this board
@@ -550,21 +795,7 @@ impl Board {
 
     /// Returns whether or not this board must be played on (does not check if it is the last board in its timeline)
     pub fn is_active(&self, info: &GameInfo) -> bool {
-        if info.even_initial_timelines {
-            self.t <= info.present
-                && if self.l < 0 {
-                    self.l >= -info.max_timeline - 2
-                } else {
-                    self.l <= -info.min_timeline + 1
-                }
-        } else {
-            self.t <= info.present
-                && if self.l < 0 {
-                    self.l >= -info.max_timeline - 1
-                } else {
-                    self.l <= -info.min_timeline + 1
-                }
-        }
+        self.t <= info.present && info.is_timeline_active(self.l)
     }
 }
 
@@ -584,6 +815,9 @@ impl fmt::Display for Piece {
                 Piece::UnicornW => "U",
                 Piece::DragonW => "D",
                 Piece::PrincessW => "S",
+                Piece::BrawnW => "W",
+                Piece::CommonKingW => "C",
+                Piece::RoyalQueenW => "Y",
                 Piece::PawnB => "p",
                 Piece::KnightB => "n",
                 Piece::BishopB => "b",
@@ -593,6 +827,9 @@ impl fmt::Display for Piece {
                 Piece::UnicornB => "u",
                 Piece::DragonB => "d",
                 Piece::PrincessB => "s",
+                Piece::BrawnB => "w",
+                Piece::CommonKingB => "c",
+                Piece::RoyalQueenB => "y",
             }
         )
     }
@@ -602,7 +839,7 @@ impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for y in (0..self.height).rev() {
             for x in 0..self.width {
-                write!(f, "{}", self.pieces[(x + y * self.height) as usize])?;
+                write!(f, "{}", self.get_unsafe(x, y))?;
             }
             if y > 0 {
                 write!(f, "\n")?;
@@ -703,166 +940,77 @@ pub fn write_file(x: u8) -> char {
     ][x as usize]
 }
 
-/**
-    Populates the castling rights of every board in the game; does so by induction (uses the `bubble_down` function)
-**/
-pub fn populate_castling_rights(game: &mut Game) {
-    // I apologize to code readers for the visual density of this function
-
-    let width = game.width;
-    let height = game.height;
-    let timeline_indices: Vec<i32> = game
-        .timelines
-        .values()
-        .filter(|tl| tl.begins_at == 0)
-        .map(|tl| tl.index.clone())
-        .collect();
+/// Parses a timeline index as displayed in-game (`"+0"`, `"-0"`, `"1"`, `"+1"`, `"-2"`, ...), inverse of `write_timeline`
+pub fn parse_timeline(raw: &str, even_initial_timelines: bool) -> Option<i32> {
+    let (negative, digits) = if let Some(digits) = raw.strip_prefix('-') {
+        (true, digits)
+    } else if let Some(digits) = raw.strip_prefix('+') {
+        (false, digits)
+    } else {
+        (false, raw)
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let l: i32 = digits.parse().ok()?;
 
-    for l in timeline_indices {
-        // Extract white and black's position
-        let (king_w, king_b) = {
-            let board = game.get_board(l, 0).unwrap();
-            let kings_w: Vec<(usize, Piece)> = board
-                .pieces
-                .iter()
-                .copied()
-                .enumerate()
-                .filter(|(_i, p)| *p == Piece::KingW)
-                .collect();
-            let kings_b: Vec<(usize, Piece)> = board
-                .pieces
-                .iter()
-                .copied()
-                .enumerate()
-                .filter(|(_i, p)| *p == Piece::KingB)
-                .collect();
-            if kings_w.len() != 1 && kings_b.len() != 1 {
-                continue;
-            }
-            (
-                kings_w.get(0).map(|k| ((k.0 % width as usize) as u8, (k.0 / width as usize) as u8)),
-                kings_b.get(0).map(|k| ((k.0 % width as usize) as u8, (k.0 / width as usize) as u8)),
-            )
-        };
+    if even_initial_timelines && negative {
+        Some(-l - 1)
+    } else if negative {
+        Some(-l)
+    } else {
+        Some(l)
+    }
+}
 
-        // Get rook positions
-        let (rook_w1, rook_w2, rook_b1, rook_b2) = {
-            let board = game.get_board(l, 0).unwrap();
-
-            let rooks_w: Vec<(usize, Piece)> = board
-                .pieces
-                .iter()
-                .copied()
-                .enumerate()
-                .filter(|(i, p)| {
-                    *p == Piece::RookW && *i / width as usize == king_w.map(|k| k.1).unwrap_or(height) as usize
-                })
-                .collect();
-            let rooks_b: Vec<(usize, Piece)> = board
-                .pieces
-                .iter()
-                .copied()
-                .enumerate()
-                .filter(|(i, p)| {
-                    *p == Piece::RookB && *i / width as usize == king_b.map(|k| k.1).unwrap_or(height) as usize
-                })
-                .collect();
-
-            let rook_w_left = rooks_w
-                .iter()
-                .filter(|(i, _p)| i % (width as usize) < king_w.map(|k| k.0).unwrap_or(0) as usize)
-                .max_by_key(|(i, _p)| i % width as usize);
-            let rook_w_right = rooks_w
-                .iter()
-                .filter(|(i, _p)| i % (width as usize) > king_w.map(|k| k.0).unwrap_or(width) as usize)
-                .min_by_key(|(i, _p)| i % width as usize);
-            let rook_b_left = rooks_b
-                .iter()
-                .filter(|(i, _p)| i % (width as usize) < king_b.map(|k| k.0).unwrap_or(0) as usize)
-                .max_by_key(|(i, _p)| i % width as usize);
-            let rook_b_right = rooks_b
-                .iter()
-                .filter(|(i, _p)| i % (width as usize) > king_b.map(|k| k.0).unwrap_or(width) as usize)
-                .min_by_key(|(i, _p)| i % width as usize);
-
-            (
-                rook_w_left.map(|(i, _p)| ((i % width as usize) as u8, (i / width as usize) as u8)),
-                rook_w_right.map(|(i, _p)| ((i % width as usize) as u8, (i / width as usize) as u8)),
-                rook_b_left.map(|(i, _p)| ((i % width as usize) as u8, (i / width as usize) as u8)),
-                rook_b_right.map(|(i, _p)| ((i % width as usize) as u8, (i / width as usize) as u8)),
-            )
-        };
+/// Returns the `x` coordinate of a file as displayed in-game, inverse of `write_file`
+pub fn parse_file(file: char) -> Option<u8> {
+    if ('a'..='w').contains(&file) {
+        Some(file as u8 - b'a')
+    } else {
+        None
+    }
+}
 
-        // Bubble down!
-        bubble_down(
-            game,
-            l,
-            0,
-            |board, mut last_state| {
-                if last_state.0 {
-                    last_state.0 = rook_w1
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p == Piece::RookW)
-                        .unwrap();
-                }
-                if last_state.1 {
-                    last_state.1 = rook_w2
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p == Piece::RookW)
-                        .unwrap();
-                }
-                if last_state.2 {
-                    last_state.2 = rook_b1
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p == Piece::RookB)
-                        .unwrap();
-                }
-                if last_state.3 {
-                    last_state.3 = rook_b2
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p == Piece::RookB)
-                        .unwrap();
-                }
-                if last_state.0 || last_state.1 {
-                    if king_w
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p != Piece::KingW)
-                        .unwrap()
-                    {
-                        last_state.0 = false;
-                        last_state.1 = false;
+/**
+    Populates the moved flags of every board in the game from the previous board of its timeline (or of the timeline it emerges from):
+    a piece has moved if it wasn't on the same square of the previous board, or if it had already moved there.
+    Pieces on the first board of an initial timeline are considered as not having moved.
+**/
+pub fn populate_moved_flags(game: &mut Game) {
+    // Parent timelines begin before the timelines emerging from them
+    let mut timelines: Vec<(isize, i32)> = game.timelines.values().map(|tl| (tl.begins_at, tl.index)).collect();
+    timelines.sort();
+
+    for (begins_at, l) in timelines {
+        let emerges_from = game.timelines[&l].emerges_from;
+        let len = game.timelines[&l].states.len() as isize;
+
+        for t in begins_at..begins_at + len {
+            let previous = if t > begins_at {
+                game.get_board(l, t - 1)
+            } else {
+                emerges_from.and_then(|l2| game.get_board(l2, t - 1))
+            };
+            let moved = match previous {
+                Some(previous) => {
+                    let board = game.timelines[&l].get_board(t).unwrap();
+                    let mut moved = Bitboard::empty(board.n_squares());
+                    for (i, (piece, previous_piece)) in board.pieces().zip(previous.pieces()).enumerate() {
+                        moved.set(i, !piece.is_blank() && (piece != previous_piece || previous.moved.get(i)));
                     }
+                    moved
                 }
-                if last_state.2 || last_state.3 {
-                    if king_b
-                        .map(|(x, y)| board.get(x, y))
-                        .flatten()
-                        .map(|p| p != Piece::KingB)
-                        .unwrap()
-                    {
-                        last_state.2 = false;
-                        last_state.3 = false;
-                    }
+                None => Bitboard::empty((game.width as usize) * (game.height as usize)),
+            };
+            // Goes through `set_moved` to keep the board's hash up to date
+            let board = game.timelines.get_mut(&l).unwrap().get_board_mut(t).unwrap();
+            for y in 0..board.height {
+                for x in 0..board.width {
+                    let index = board.index(x, y);
+                    board.set_moved(x, y, moved.get(index));
                 }
-
-                board.king_w = king_w;
-                board.king_b = king_b;
-                board.castle_w = (last_state.0, last_state.1);
-                board.castle_b = (last_state.2, last_state.3);
-
-                (true, last_state)
-            },
-            (
-                rook_w1.is_some() && king_w.is_some(),
-                rook_w2.is_some() && king_w.is_some(),
-                rook_b1.is_some() && king_b.is_some(),
-                rook_b2.is_some() && king_b.is_some(),
-            ),
-        );
+            }
+        }
     }
 }
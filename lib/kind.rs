@@ -0,0 +1,266 @@
+/*
+    Data-driven description of how each kind of piece moves and whether it is royal.
+
+    Move generation (`moves::probable_moves_for`), check detection and the evaluation in `resolve` all work from these descriptions,
+    so that adding a fairy piece only requires a new `PieceKind` and its entry in `Piece::kind`.
+*/
+
+use super::game::Piece;
+
+/// The directions along which a piece moves, as `(l, t, x, y)` vectors; `t` is counted in turns, not in half-turns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vectors {
+    /// Every permutation of the `n`-th n-gonal group of `moves::PERMUTATIONS` (the knight's leaps are the `0`-th group, the rook's directions the `1`-st, and so on)
+    NGonal(usize),
+    /// A fixed list of vectors, as seen by white; the `l` and `y` components are negated for black, so that "forward" points towards the opponent
+    Forward(&'static [(isize, isize, isize, isize)]),
+}
+
+/// Whether a movement may capture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture {
+    /// The piece may move to blank squares and capture opponent pieces
+    Allowed,
+    /// The piece may only move to blank squares (eg. a pawn moving forward)
+    Never,
+    /// The piece may only capture (eg. a pawn moving diagonally)
+    Only,
+}
+
+/// A set of vectors along which a piece moves, and how far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Movement {
+    pub vectors: Vectors,
+    /// The maximum number of steps along each vector: `Some(1)` for leapers, `None` for riders that slide until they are blocked
+    pub range: Option<usize>,
+    pub capture: Capture,
+}
+
+/// Description of a kind of piece, regardless of its color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PieceKind {
+    pub name: &'static str,
+    pub movements: &'static [Movement],
+    /// Whether the player is in check if that piece can be captured
+    pub royal: bool,
+    /// Whether the piece is promoted when reaching the last rank (see `Game::promotions`)
+    pub promotes: bool,
+    /// Whether its non-capturing movements may go one step further if the piece hasn't moved yet (see `Game::pawn_double_step`)
+    pub double_step: bool,
+    /// Whether its capturing movements within a board may capture en passant
+    pub en_passant: bool,
+}
+
+impl PieceKind {
+    /// Returns whether or not that kind of piece can move by `n` tiles in some direction until it is blocked
+    pub fn slides(&self) -> bool {
+        self.movements.iter().any(|movement| movement.range.is_none())
+    }
+
+    /**
+        Returns the vectors along which a piece of that kind, of the given color, can capture without leaving its board, as `(dx, dy, slides)`.
+        Used to build the control maps of a board.
+    **/
+    pub fn board_vectors(&self, white: bool) -> Vec<(isize, isize, bool)> {
+        let mut res = Vec::new();
+        for movement in self.movements.iter().filter(|movement| movement.capture != Capture::Never) {
+            for (dl, dt, dx, dy) in movement.vectors.list(white) {
+                if dl == 0 && dt == 0 {
+                    res.push((dx, dy, movement.range.is_none()));
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Vectors {
+    /// Returns the list of `(l, t, x, y)` vectors, as seen by `white`'s pieces
+    pub fn list(&self, white: bool) -> Vec<(isize, isize, isize, isize)> {
+        match self {
+            Vectors::NGonal(n) => super::moves::PERMUTATIONS[*n].clone(),
+            Vectors::Forward(vectors) => vectors
+                .iter()
+                .map(|&(dl, dt, dx, dy)| if white { (dl, dt, dx, dy) } else { (-dl, dt, dx, -dy) })
+                .collect(),
+        }
+    }
+}
+
+const fn rider(n: usize) -> Movement {
+    Movement {
+        vectors: Vectors::NGonal(n),
+        range: None,
+        capture: Capture::Allowed,
+    }
+}
+
+const fn leaper(n: usize) -> Movement {
+    Movement {
+        vectors: Vectors::NGonal(n),
+        range: Some(1),
+        capture: Capture::Allowed,
+    }
+}
+
+const PAWN_MOVES: Movement = Movement {
+    vectors: Vectors::Forward(&[(0, 0, 0, 1)]),
+    range: Some(1),
+    capture: Capture::Never,
+};
+
+const KING_MOVES: [Movement; 4] = [leaper(1), leaper(2), leaper(3), leaper(4)];
+const QUEEN_MOVES: [Movement; 4] = [rider(1), rider(2), rider(3), rider(4)];
+
+pub static PAWN: PieceKind = PieceKind {
+    name: "pawn",
+    movements: &[
+        PAWN_MOVES,
+        Movement {
+            vectors: Vectors::Forward(&[(0, 0, 1, 1), (0, 0, -1, 1)]),
+            range: Some(1),
+            capture: Capture::Only,
+        },
+    ],
+    royal: false,
+    promotes: true,
+    double_step: true,
+    en_passant: true,
+};
+
+/// A pawn that may also capture by moving forward along `y` or `l` and sideways along `x` or `t`
+pub static BRAWN: PieceKind = PieceKind {
+    name: "brawn",
+    movements: &[
+        PAWN_MOVES,
+        Movement {
+            vectors: Vectors::Forward(&[
+                (0, 0, 1, 1),
+                (0, 0, -1, 1),
+                (0, 1, 0, 1),
+                (0, -1, 0, 1),
+                (-1, 0, 1, 0),
+                (-1, 0, -1, 0),
+                (-1, 1, 0, 0),
+                (-1, -1, 0, 0),
+            ]),
+            range: Some(1),
+            capture: Capture::Only,
+        },
+    ],
+    royal: false,
+    promotes: true,
+    double_step: true,
+    en_passant: false,
+};
+
+pub static KNIGHT: PieceKind = PieceKind {
+    name: "knight",
+    movements: &[leaper(0)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static ROOK: PieceKind = PieceKind {
+    name: "rook",
+    movements: &[rider(1)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static BISHOP: PieceKind = PieceKind {
+    name: "bishop",
+    movements: &[rider(2)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static UNICORN: PieceKind = PieceKind {
+    name: "unicorn",
+    movements: &[rider(3)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static DRAGON: PieceKind = PieceKind {
+    name: "dragon",
+    movements: &[rider(4)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static PRINCESS: PieceKind = PieceKind {
+    name: "princess",
+    movements: &[rider(1), rider(2)],
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static QUEEN: PieceKind = PieceKind {
+    name: "queen",
+    movements: &QUEEN_MOVES,
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static ROYAL_QUEEN: PieceKind = PieceKind {
+    name: "royal queen",
+    movements: &QUEEN_MOVES,
+    royal: true,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static KING: PieceKind = PieceKind {
+    name: "king",
+    movements: &KING_MOVES,
+    royal: true,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+pub static COMMON_KING: PieceKind = PieceKind {
+    name: "common king",
+    movements: &KING_MOVES,
+    royal: false,
+    promotes: false,
+    double_step: false,
+    en_passant: false,
+};
+
+impl Piece {
+    /// Returns the description of how that piece moves, None if it is blank
+    pub fn kind(&self) -> Option<&'static PieceKind> {
+        Some(match self {
+            Piece::Blank => return None,
+            Piece::PawnW | Piece::PawnB => &PAWN,
+            Piece::BrawnW | Piece::BrawnB => &BRAWN,
+            Piece::KnightW | Piece::KnightB => &KNIGHT,
+            Piece::RookW | Piece::RookB => &ROOK,
+            Piece::BishopW | Piece::BishopB => &BISHOP,
+            Piece::UnicornW | Piece::UnicornB => &UNICORN,
+            Piece::DragonW | Piece::DragonB => &DRAGON,
+            Piece::PrincessW | Piece::PrincessB => &PRINCESS,
+            Piece::QueenW | Piece::QueenB => &QUEEN,
+            Piece::RoyalQueenW | Piece::RoyalQueenB => &ROYAL_QUEEN,
+            Piece::KingW | Piece::KingB => &KING,
+            Piece::CommonKingW | Piece::CommonKingB => &COMMON_KING,
+        })
+    }
+}
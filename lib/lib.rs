@@ -6,11 +6,28 @@ extern crate permute;
 extern crate log;
 extern crate serde;
 extern crate serde_json;
+extern crate serde_path_to_error;
+extern crate ron;
 
+pub mod bitboard;
+pub mod check;
+pub mod error;
+pub mod fen;
 pub mod game;
+pub mod kind;
 pub mod moves;
+pub mod notation;
 pub mod moveset;
 pub mod resolve;
 pub mod tree;
+pub mod transposition;
 pub mod parse;
+pub mod perft;
+pub mod replay;
+pub mod schema;
+pub mod svg;
+pub mod variants;
 pub mod vboard;
+pub mod zobrist;
+
+pub use error::Error;
@@ -1,5 +1,6 @@
 // Handles moves
-use super::{game::*, moveset::*, resolve::*};
+use super::{bitboard::*, check::{checks, is_attacked, is_in_check, Check}, error::Error, game::*, kind::*, moveset::*, notation::write_move_raw, resolve::*, vboard::*};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 // Generate permutations for the basic, symmetric piece movements
@@ -69,7 +70,7 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Move {
     pub src: (i32, isize, u8, u8), // l, t, x, y
     pub dst: (i32, isize, u8, u8), // l, t, x, y
@@ -78,128 +79,36 @@ pub struct Move {
     pub en_passant: Option<(u8, u8)>,
     pub src_piece: Piece,
     pub dst_piece: Piece,
+    pub promotion: Option<Piece>, // the piece that a pawn turns into when reaching the last rank
     pub noop: bool,
 }
 
+impl fmt::Display for Move {
+    /**
+        Prints out a move in 5D notation (eg. `(0T1)Ng1f3`, `(0T2)Nb1>>(0T1)b3`).
+        As a move does not know about the game it is played in, every jump is written as branching (`>>`) and timelines are written as if the game had an odd number of initial timelines; use `notation::write_move` to get the exact notation.
+    **/
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", write_move_raw(self, false, true))
+    }
+}
+
 impl fmt::Debug for Move {
-    /// Prints out a move in semi-readable format; does not match any of the existing and known notations
+    /// Prints out a move in 5D notation, see `Display`
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.noop {
-            return write!(f, "_");
-        }
-        if self.castle {
-            if self.castle_long {
-                write!(
-                    f,
-                    "({}T{})O-O-O",
-                    (self.src.0).to_string(),
-                    self.src.1 / 2 + 1
-                )
-            } else {
-                write!(
-                    f,
-                    "({}T{})O-O",
-                    (self.src.0).to_string(),
-                    self.src.1 / 2 + 1
-                )
-            }
-        } else {
-            if self.src.0 == self.dst.0 && self.src.1 == self.dst.1 {
-                if self.src_piece.is_pawn() {
-                    if self.dst_piece.is_blank() {
-                        write!(
-                            f,
-                            "({}T{}){}{}",
-                            (self.src.0).to_string(),
-                            self.src.1 / 2 + 1,
-                            write_file(self.dst.2),
-                            (self.dst.3 + 1),
-                        )
-                    } else {
-                        write!(
-                            f,
-                            "({}T{}){}x{}{}",
-                            (self.src.0).to_string(),
-                            self.src.1 / 2 + 1,
-                            write_file(self.src.2),
-                            write_file(self.dst.2),
-                            (self.dst.3 + 1),
-                        )
-                    }
-                } else {
-                    if self.dst_piece.is_blank() {
-                        write!(
-                            f,
-                            "({}T{}){}{}{}{}{}",
-                            (self.src.0).to_string(),
-                            self.src.1 / 2 + 1,
-                            self.src_piece.as_uppercase(),
-                            write_file(self.src.2),
-                            (self.src.3 + 1),
-                            write_file(self.dst.2),
-                            (self.dst.3 + 1),
-                        )
-                    } else {
-                        write!(
-                            f,
-                            "({}T{}){}{}{}x{}{}",
-                            (self.src.0).to_string(),
-                            self.src.1 / 2 + 1,
-                            self.src_piece.as_uppercase(),
-                            write_file(self.src.2),
-                            (self.src.3 + 1),
-                            write_file(self.dst.2),
-                            (self.dst.3 + 1),
-                        )
-                    }
-                }
-            } else {
-                if self.dst_piece.is_blank() {
-                    write!(
-                        f,
-                        "({}T{}){}{}{}>>({}T{}){}{}{}",
-                        (self.src.0).to_string(),
-                        self.src.1 / 2 + 1,
-                        self.src_piece.as_uppercase(),
-                        write_file(self.src.2),
-                        (self.src.3 + 1),
-                        (self.dst.0).to_string(),
-                        self.dst.1 / 2 + 1,
-                        self.dst_piece,
-                        write_file(self.dst.2),
-                        (self.dst.3 + 1),
-                    )
-                } else {
-                    write!(
-                        f,
-                        "({}T{}){}{}{}>>x({}T{}){}{}{}",
-                        (self.src.0).to_string(),
-                        self.src.1 / 2 + 1,
-                        self.src_piece.as_uppercase(),
-                        write_file(self.src.2),
-                        (self.src.3 + 1),
-                        (self.dst.0).to_string(),
-                        self.dst.1 / 2 + 1,
-                        self.dst_piece,
-                        write_file(self.dst.2),
-                        (self.dst.3 + 1),
-                    )
-                }
-            }
-        }
+        fmt::Display::fmt(self, f)
     }
 }
 
 impl Move {
-    /// Creates a new normal move; extracts piece information from `game` and `virtual_boards`
-    pub fn new(
+    /// Creates a new normal move; extracts piece information from `virtual_boards`
+    pub fn new<T: VirtualBoardset>(
         src: (i32, isize, u8, u8),
         dst: (i32, isize, u8, u8),
-        game: &Game,
-        virtual_boards: &Vec<&Board>,
+        virtual_boards: &T,
     ) -> Option<Self> {
-        let src_piece = get(game, virtual_boards, src)?;
-        let dst_piece = get(game, virtual_boards, dst)?;
+        let src_piece = virtual_boards.get(src.0, src.1, src.2, src.3)?;
+        let dst_piece = virtual_boards.get(dst.0, dst.1, dst.2, dst.3)?;
         Some(Move {
             src,
             dst,
@@ -219,20 +128,20 @@ impl Move {
             },
             src_piece,
             dst_piece,
+            promotion: None,
             noop: false,
         })
     }
 
-    /// Creates a new normal move; extracts piece information from `game`, `board` `virtual_boards`. Does not need `board` to be within `game` or `virtual_boards`
-    fn new_with_board(
+    /// Creates a new normal move; extracts piece information from `board` and `virtual_boards`. Does not need `board` to be within `virtual_boards`
+    fn new_with_board<T: VirtualBoardset>(
         src: (i32, isize, u8, u8),
         dst: (i32, isize, u8, u8),
-        game: &Game,
         board: &Board,
-        virtual_boards: &Vec<&Board>,
+        virtual_boards: &T,
     ) -> Option<Self> {
-        let src_piece = get_with_board(game, board, virtual_boards, src)?;
-        let dst_piece = get_with_board(game, board, virtual_boards, dst)?;
+        let src_piece = get_with_board(board, virtual_boards, src)?;
+        let dst_piece = get_with_board(board, virtual_boards, dst)?;
         Some(Move {
             src,
             dst,
@@ -252,6 +161,7 @@ impl Move {
             },
             src_piece,
             dst_piece,
+            promotion: None,
             noop: false,
         })
     }
@@ -272,6 +182,7 @@ impl Move {
             en_passant: None,
             src_piece,
             dst_piece: if white { Piece::RookW } else { Piece::RookB },
+            promotion: None,
             noop: false,
         })
     }
@@ -286,92 +197,74 @@ impl Move {
             en_passant: None,
             src_piece: Piece::Blank,
             dst_piece: Piece::Blank,
+            promotion: None,
             noop: true,
         }
     }
 
-    /// Generate the boards that are created as a result of the move being played out. The target and source boards must be present in either `game`, `virtual_boards` or `already_generated`
-    pub fn generate_vboards(
+    /**
+        Generate the boards that are created as a result of the move being played out. The target and source boards must be present in either `virtual_boards` or `already_generated`.
+        Returns `Error::MissingBoard` if the source or target board can't be found and `Error::BoardCollision` if the source board was already played on.
+    **/
+    pub fn generate_vboards<T: VirtualBoardset>(
         &self,
-        game: &Game,
+        virtual_boards: &T,
         info: &GameInfo,
-        virtual_boards: &Vec<&Board>,
-        already_generated: &Vec<Board>,
-    ) -> Option<(GameInfo, Vec<Board>)> {
-        // TODO: properly handle Board::set's result
+        already_generated: &[Board],
+    ) -> Result<(GameInfo, Vec<Board>), Error> {
         if self.noop {
-            return Some((info.clone(), vec![]));
+            return Ok((info.clone(), vec![]));
         }
 
-        let mut new_board = get_board(game, virtual_boards, (self.src.0, self.src.1))?.clone();
+        let game = virtual_boards.game();
+        let mut new_board = virtual_boards
+            .get_board(self.src.0, self.src.1)
+            .ok_or(Error::MissingBoard(self.src.0, self.src.1))?
+            .clone();
 
-        if !is_last(game, virtual_boards, &new_board)
+        if !virtual_boards.is_last(&new_board)
             || already_generated
                 .iter()
                 .find(|b| b.l == new_board.l && b.t == new_board.t + 1)
                 .is_some()
         {
-            return None;
+            return Err(Error::BoardCollision(new_board.l, new_board.t + 1));
         }
 
         if self.castle {
+            let king_x = if self.castle_long { 2 } else { game.width - 2 };
+            let rook_x = if self.castle_long { 3 } else { game.width - 3 };
             new_board.t += 1;
-            new_board.set(self.src.2, self.src.3, Piece::Blank).unwrap();
-            new_board.set(self.dst.2, self.dst.3, Piece::Blank).unwrap();
-
-            new_board.set(
-                self.src.2,
-                if self.castle_long { 2 } else { game.width - 2 },
-                if new_board.active_player() {
-                    Piece::KingB
-                } else {
-                    Piece::KingW
-                },
-            ).unwrap();
-            new_board.set(
-                self.dst.2,
-                if self.castle_long { 3 } else { game.width - 3 },
-                if new_board.active_player() {
-                    Piece::RookB
-                } else {
-                    Piece::RookW
-                },
-            ).unwrap();
-            Some((info.clone(), vec![new_board]))
-        } else if self.en_passant.is_some() {
+            new_board.set(self.src.2, self.src.3, Piece::Blank)?;
+            new_board.set(self.dst.2, self.dst.3, Piece::Blank)?;
+            new_board.set_moved(self.src.2, self.src.3, false);
+            new_board.set_moved(self.dst.2, self.dst.3, false);
+
+            new_board.set(king_x, self.src.3, self.src_piece)?;
+            new_board.set(rook_x, self.dst.3, self.dst_piece)?;
+            new_board.set_moved(king_x, self.src.3, true);
+            new_board.set_moved(rook_x, self.dst.3, true);
+            Ok((info.clone(), vec![new_board]))
+        } else if let Some(en_passant) = self.en_passant {
             new_board.t += 1;
-            new_board.set(self.src.2, self.src.3, Piece::Blank).unwrap();
-            new_board.set(self.en_passant?.0, self.en_passant?.1, Piece::Blank).unwrap();
-            new_board.set(self.dst.2, self.dst.3, self.src_piece).unwrap();
-            Some((info.clone(), vec![new_board]))
+            new_board.set(self.src.2, self.src.3, Piece::Blank)?;
+            new_board.set(en_passant.0, en_passant.1, Piece::Blank)?;
+            new_board.set(self.dst.2, self.dst.3, self.src_piece)?;
+            new_board.set_moved(self.src.2, self.src.3, false);
+            new_board.set_moved(en_passant.0, en_passant.1, false);
+            new_board.set_moved(self.dst.2, self.dst.3, true);
+            Ok((info.clone(), vec![new_board]))
         } else {
             if self.src.0 == self.dst.0 && self.src.1 == self.dst.1 {
                 // Non-branching move
                 new_board.t += 1;
-                new_board.set(self.src.2, self.src.3, Piece::Blank).unwrap();
-                new_board.set(self.dst.2, self.dst.3, self.src_piece).unwrap();
+                new_board.set(self.src.2, self.src.3, Piece::Blank)?;
+                new_board.set(self.dst.2, self.dst.3, self.promotion.unwrap_or(self.src_piece))?;
+                new_board.set_moved(self.src.2, self.src.3, false);
+                new_board.set_moved(self.dst.2, self.dst.3, true);
 
                 let info = info.clone();
 
-                if self.src_piece.is_pawn()
-                    && self.dst.3
-                        == if self.src_piece.is_white() {
-                            new_board.height - 1
-                        } else {
-                            0
-                        }
-                {
-                    new_board.set(
-                        self.dst.2,
-                        self.dst.3,
-                        if self.src_piece.is_white() {
-                            Piece::QueenW
-                        } else {
-                            Piece::QueenB
-                        },
-                    ).unwrap();
-                }
-
                 // Impossible!
                 // for b in already_generated {
                 //     if b.t == new_board.t && b.l == new_board.l {
@@ -387,14 +280,16 @@ impl Move {
                 //     }
                 // }
 
-                Some((info, vec![new_board]))
+                Ok((info, vec![new_board]))
             } else {
                 let mut new_src_board = new_board;
-                let mut new_dst_board =
-                    get_board(game, virtual_boards, (self.dst.0, self.dst.1))?.clone();
+                let mut new_dst_board = virtual_boards
+                    .get_board(self.dst.0, self.dst.1)
+                    .ok_or(Error::MissingBoard(self.dst.0, self.dst.1))?
+                    .clone();
 
                 let mut new_info = info.clone();
-                if !is_last(game, virtual_boards, &new_dst_board)
+                if !virtual_boards.is_last(&new_dst_board)
                     || already_generated
                         .iter()
                         .find(|b| b.l == new_dst_board.l && b.t == new_dst_board.t + 1)
@@ -407,34 +302,44 @@ impl Move {
                         new_info.min_timeline = info.min_timeline - 1;
                         new_info.min_timeline
                     };
+                    new_dst_board.emerges_from = Some(self.dst.0);
                 }
 
                 new_src_board.t += 1;
                 new_dst_board.t += 1;
-                // TODO: timeline reactivation
-                if new_dst_board.t < new_info.present && new_dst_board.is_active(&new_info) {
-                    new_info.present = new_dst_board.t;
-                }
 
-                if if self.src_piece.is_white() {
-                    -info.min_timeline > info.max_timeline
-                } else {
-                    -info.min_timeline < info.max_timeline
-                } {
-                    new_info.present = find_present(game, virtual_boards, info);
-                }
+                new_src_board.set(self.src.2, self.src.3, Piece::Blank)?;
+                new_dst_board.set(self.dst.2, self.dst.3, self.promotion.unwrap_or(self.src_piece))?;
+                new_src_board.set_moved(self.src.2, self.src.3, false);
+                new_dst_board.set_moved(self.dst.2, self.dst.3, true);
 
-                new_src_board.set(self.src.2, self.src.3, Piece::Blank).unwrap();
-                new_dst_board.set(self.dst.2, self.dst.3, self.src_piece).unwrap();
+                if new_dst_board.l != self.dst.0 {
+                    // A new timeline may move the present back, be it by itself or by reactivating older timelines
+                    let merged_vboards = OverlayVirtualBoardset::new(
+                        virtual_boards,
+                        already_generated.iter().chain(vec![&new_src_board, &new_dst_board]).collect(),
+                    );
+                    new_info.present = find_present(&merged_vboards, &new_info);
+                }
 
-                Some((new_info, vec![new_src_board, new_dst_board]))
+                Ok((new_info, vec![new_src_board, new_dst_board]))
             }
         }
     }
 }
 
-/// Returns the set of moves that can be made from `board`; does not check for the legality of said move (ie. if it puts the player in check)
-pub fn probable_moves(game: &Game, board: &Board, virtual_boards: &Vec<&Board>) -> Vec<Move> {
+/**
+    Returns the set of moves that can be made from `board`; does not check for the legality of said move (ie. if it puts the player in check).
+    Returns None if a move lands on a square that can't be read from `virtual_boards`.
+**/
+pub fn probable_moves<T: VirtualBoardset>(virtual_boards: &T, board: &Board) -> Option<Vec<Move>> {
+    let mut res = probable_moves_without_castling(virtual_boards, board)?;
+    castling_moves(virtual_boards, board, &mut res);
+    Some(res)
+}
+
+/// Returns the moves of `probable_moves`, except for castling; since castling never captures, this is enough to look for attacks on a king
+pub fn probable_moves_without_castling<T: VirtualBoardset>(virtual_boards: &T, board: &Board) -> Option<Vec<Move>> {
     let mut res: Vec<Move> = Vec::new();
 
     for y in 0..board.height {
@@ -445,129 +350,69 @@ pub fn probable_moves(game: &Game, board: &Board, virtual_boards: &Vec<&Board>)
                 } else {
                     piece.is_black()
                 } {
-                    probable_moves_for(game, board, virtual_boards, &mut res, piece, x, y).unwrap();
+                    probable_moves_for(virtual_boards, board, &mut res, piece, x, y)?;
                 }
             }
         }
     }
 
-    if board.active_player() && board.width > 5 {
-        if board.castle_w.0 {
-            // TODO: check the b and c file
-            let king_w = board.king_w.unwrap();
-            let (mut x, y) = king_w;
-            if x != 0 {
-                x -= 1;
-                while let Some(piece) = board.get(x, y) {
-                    if let Piece::RookW = piece {
-                        res.push(
-                            Move::castle(true, (board.l, board.t, king_w.0, king_w.1), (x, y), true)
-                                .unwrap(),
-                        );
-                        break;
-                    } else if let Piece::Blank = piece {
-                        if x == 0 {
-                            break;
-                        }
-                        x -= 1;
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
-            }
-        }
-        if board.castle_w.1 {
-            // TODO: check the f and g file
-            let king_w = board.king_w.unwrap();
-            let (mut x, y) = king_w;
-            x += 1;
-            while let Some(piece) = board.get(x, y) {
-                if let Piece::RookW = piece {
-                    res.push(
-                        Move::castle(false, (board.l, board.t, king_w.0, king_w.1), (x, y), true)
-                            .unwrap(),
-                    );
-                    break;
-                } else if let Piece::Blank = piece {
-                    x += 1;
-                    continue;
-                } else {
-                    break;
-                }
-            }
-        }
+    Some(res)
+}
+
+/**
+    Pushes the castling moves of the active player on `board` onto `res`.
+    Neither the king nor the rook may have moved (see `Board::castling_rook`), the squares between them must be blank and the squares that they land on must be free.
+    The king may not castle out of, through or into check: none of the squares from its initial square to its destination may be attacked (see `check::is_attacked`).
+**/
+fn castling_moves<T: VirtualBoardset>(virtual_boards: &T, board: &Board, res: &mut Vec<Move>) {
+    if !virtual_boards.game().castling || board.width <= 5 {
+        return;
     }
-    if !board.active_player() && board.width > 5 {
-        if board.castle_b.0 {
-            // TODO: check the b and c file
-            let king_b = board.king_b.unwrap();
-            let (mut x, y) = king_b;
-            if x != 0 {
-                x -= 1;
-                while let Some(piece) = board.get(x, y) {
-                    if let Piece::RookB = piece {
-                        res.push(
-                            Move::castle(true, (board.l, board.t, king_b.0, king_b.1), (x, y), false)
-                                .unwrap(),
-                        );
-                        break;
-                    } else if let Piece::Blank = piece {
-                        if x == 0 {
-                            break;
-                        }
-                        x -= 1;
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
-            }
+
+    let white = board.active_player();
+    for long in [true, false] {
+        let ((king_x, y), rook_x) = match board.castling_rook(white, long) {
+            Some(squares) => squares,
+            None => continue,
+        };
+
+        if (king_x.min(rook_x) + 1..king_x.max(rook_x)).any(|x| !board.get_unsafe(x, y).is_blank()) {
+            continue;
         }
-        if board.castle_b.1 {
-            // TODO: check the f and g file
-            let king_b = board.king_b.unwrap();
-            let (mut x, y) = king_b;
-            x += 1;
-            while let Some(piece) = board.get(x, y) {
-                if let Piece::RookB = piece {
-                    res.push(
-                        Move::castle(false, (board.l, board.t, king_b.0, king_b.1), (x, y), false)
-                            .unwrap(),
-                    );
-                    break;
-                } else if let Piece::Blank = piece {
-                    x += 1;
-                    continue;
-                } else {
-                    break;
-                }
-            }
+
+        let king_dst = if long { 2 } else { board.width - 2 };
+        let rook_dst = if long { 3 } else { board.width - 3 };
+        let is_free = |x: u8| x == king_x || x == rook_x || board.get_unsafe(x, y).is_blank();
+        if !is_free(king_dst) || !is_free(rook_dst) {
+            continue;
         }
-    }
 
-    res
+        if (king_x.min(king_dst)..=king_x.max(king_dst)).any(|x| is_attacked(virtual_boards, board, x, y)) {
+            continue;
+        }
+
+        res.push(Move::castle(long, (board.l, board.t, king_x, y), (rook_x, y), white).unwrap());
+    }
 }
 
 /// Returns whether or not a moveset is legal (ie. if it doesn't put the player in check).
-pub fn is_moveset_legal<'a, U>(
-    game: &Game,
-    virtual_boards: &Vec<&Board>,
+pub fn is_moveset_legal<'a, T, U>(
+    virtual_boards: &T,
     info: &GameInfo,
     boards: U,
 ) -> bool
 where
+    T: VirtualBoardset,
     U: Iterator<Item = &'a Board>,
 {
     let opponent = !info.active_player;
 
     for board in boards {
-        if is_last(game, virtual_boards, board) {
+        if virtual_boards.is_last(board) {
             if board.active_player() == opponent {
-                for m in probable_moves(game, board, virtual_boards) {
-                    if m.dst_piece == (if opponent { Piece::KingB } else { Piece::KingW }) {
-                        return false;
-                    }
+                // Moves that can't be generated can't be ruled out either
+                if probable_moves_without_castling(virtual_boards, board).is_none_or(|moves| moves.iter().any(|m| m.dst_piece.is_royal())) {
+                    return false;
                 }
             } else {
                 if board.is_active(info) {
@@ -580,88 +425,149 @@ where
     true
 }
 
+/// Reason for which `validate_moveset` rejected a moveset
+#[derive(Debug, Clone, PartialEq)]
+pub enum MovesetError {
+    /// The moveset is empty
+    Empty,
+    /// The `(l, t)` board can't be played on: it doesn't exist, isn't the last board of its timeline or belongs to the opponent
+    NotPlayable((i32, isize)),
+    /// The `(l, t)` board was already played on by a previous move of the moveset
+    AlreadyPlayed((i32, isize)),
+    /// The move can't be made by its piece (it isn't part of `probable_moves`)
+    IllegalMove(Move),
+    /// The `(l, t)` board must be played on, but wasn't
+    BoardNotPlayed((i32, isize)),
+    /// The moveset leaves one of the player's kings in check
+    InCheck(Check),
+}
+
+/**
+    Checks that `moveset` can be played by `info.active_player`, like `score_moveset` does, but tells why it can't.
+    On success, returns the boards created by the moveset and the resulting `GameInfo`.
+**/
+pub fn validate_moveset<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    moveset: &[Move],
+) -> Result<(Vec<Board>, GameInfo), MovesetError> {
+    if moveset.is_empty() {
+        return Err(MovesetError::Empty);
+    }
+
+    let mut moveset_boards: Vec<Board> = Vec::new();
+    let mut new_info = *info;
+
+    for mv in moveset {
+        let src = (mv.src.0, mv.src.1);
+        if moveset_boards.iter().any(|b| b.l == src.0 && b.t == src.1 + 1) {
+            return Err(MovesetError::AlreadyPlayed(src));
+        }
+        let board = match virtual_boards.get_board(src.0, src.1) {
+            Some(board)
+                if board.active_player() == info.active_player
+                    && virtual_boards.is_last(board) =>
+            {
+                board
+            }
+            _ => return Err(MovesetError::NotPlayable(src)),
+        };
+        if !probable_moves(virtual_boards, board).is_some_and(|moves| moves.contains(mv)) {
+            return Err(MovesetError::IllegalMove(*mv));
+        }
+
+        let (info, mut new_vboards) = mv
+            .generate_vboards(virtual_boards, &new_info, &moveset_boards)
+            .map_err(|_| MovesetError::IllegalMove(*mv))?;
+        moveset_boards.append(&mut new_vboards);
+        new_info = info;
+    }
+
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, moveset_boards.iter().collect());
+    new_info.present = find_present(&merged_vboards, &new_info);
+
+    if let Some(board) = get_own_boards(&merged_vboards, &new_info)
+        .into_iter()
+        .find(|b| b.is_active(&new_info))
+    {
+        return Err(MovesetError::BoardNotPlayed((board.l, board.t)));
+    }
+    if let Some(check) = checks(&merged_vboards, &new_info).into_iter().next() {
+        return Err(MovesetError::InCheck(check));
+    }
+
+    new_info.active_player = !new_info.active_player;
+
+    Ok((moveset_boards, new_info))
+}
+
 /// Returns whether or not every mandatory boards were played on (accepts time travel escapes)
-pub fn all_boards_played(game: &Game, virtual_boards: &Vec<&Board>, info: &GameInfo) -> bool {
-    for board in get_own_boards(game, virtual_boards, info) {
-        if board.t <= info.present {
+pub fn all_boards_played<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> bool {
+    for board in get_own_boards(virtual_boards, info) {
+        if board.is_active(info) {
             return false;
         }
     }
     true
 }
 
-/// Returns the set of boards on which the opponent can make a move
-pub fn get_opponent_boards<'a>(
-    game: &'a Game,
-    virtual_boards: &'a Vec<&'a Board>,
-    info: &'a GameInfo,
-) -> Vec<&'a Board> {
-    let mut res: Vec<&Board> = game
+/// Returns the last board of every timeline, those of `virtual_boards` included
+pub fn last_boards<T: VirtualBoardset>(virtual_boards: &T) -> impl Iterator<Item = &Board> {
+    virtual_boards
+        .game()
         .timelines
         .values()
         .map(|tl| &tl.states[tl.states.len() - 1])
-        .filter(|b| b.active_player() != info.active_player && is_last(game, virtual_boards, b))
-        .collect();
-    for b in virtual_boards {
-        if b.active_player() != info.active_player && is_last(game, virtual_boards, b) {
-            res.push(b);
-        }
-    }
-    res
+        .chain(virtual_boards.virtual_boards())
+        .filter(move |b| virtual_boards.is_last(b))
+}
+
+/// Returns the set of boards on which the opponent can make a move
+pub fn get_opponent_boards<'a, T: VirtualBoardset>(virtual_boards: &'a T, info: &GameInfo) -> Vec<&'a Board> {
+    last_boards(virtual_boards)
+        .filter(|b| b.active_player() != info.active_player)
+        .collect()
 }
 
 /// Returns the set of board on which the active player can make a move
-pub fn get_own_boards<'a>(
-    game: &'a Game,
-    virtual_boards: &'a Vec<&'a Board>,
-    info: &'a GameInfo,
-) -> Vec<&'a Board> {
-    let mut res: Vec<&Board> = game
-        .timelines
-        .values()
-        .map(|tl| &tl.states[tl.states.len() - 1])
-        .filter(|b| b.active_player() == info.active_player && is_last(game, virtual_boards, b))
-        .collect();
-    for b in virtual_boards {
-        if b.active_player() == info.active_player && is_last(game, virtual_boards, b) {
-            res.push(b);
-        }
-    }
-    res
+pub fn get_own_boards<'a, T: VirtualBoardset>(virtual_boards: &'a T, info: &GameInfo) -> Vec<&'a Board> {
+    last_boards(virtual_boards)
+        .filter(|b| b.active_player() == info.active_player)
+        .collect()
 }
 
 /// Returns a lazy iterator over the legal movesets that the active player can make
-pub fn legal_movesets<'a>(
-    game: &'a Game,
+pub fn legal_movesets<'a, T: VirtualBoardset>(
+    virtual_boards: &'a T,
     info: &'a GameInfo,
-    virtual_boards: &'a Vec<&'a Board>,
     max_moves_considered: usize,
     max_movesets_considered: usize,
 ) -> impl Iterator<Item = (Vec<Move>, Vec<Board>, GameInfo, f32)> + 'a {
-    let ranked_moves = get_own_boards(&game, &virtual_boards, &info)
+    let opponent_boards = get_opponent_boards(virtual_boards, info);
+    let ranked_moves = get_own_boards(virtual_boards, info)
         .into_iter()
         .map(|board| {
             let lore = Lore::new(
-                game,
                 virtual_boards,
                 board,
-                get_opponent_boards(&game, &virtual_boards, &info).into_iter(),
+                opponent_boards.iter().copied(),
                 &info,
             );
-            let probables = probable_moves(&game, board, &virtual_boards)
+            let probables = probable_moves(virtual_boards, board)
+                .unwrap_or_default()
                 .into_iter()
-                .map(|mv| {
+                .filter_map(|mv| {
                     let (new_info, new_vboards) = mv
-                        .generate_vboards(&game, &info, &virtual_boards, &vec![])
-                        .unwrap();
-                    (mv, new_info, new_vboards)
+                        .generate_vboards(virtual_boards, &info, &[])
+                        .ok()?;
+                    Some((mv, new_info, new_vboards))
                 })
                 .collect::<Vec<_>>();
-            score_moves(&game, &virtual_boards, board, &lore, probables, &info)
+            score_moves(virtual_boards, board, &lore, probables, &info)
         })
         .collect::<Vec<_>>();
 
-    let mut iter = MovesetIter::new(&game, &virtual_boards, &info, ranked_moves);
+    let mut iter = MovesetIter::new(virtual_boards, &info, ranked_moves);
 
     iter.max_moves_considered = max_moves_considered;
     iter.max_movesets_considered = max_movesets_considered;
@@ -669,292 +575,151 @@ pub fn legal_movesets<'a>(
     iter.score()
 }
 
-/// Returns the `(l, t)` board within `game` or `virtual_boards`
-pub fn get_board<'a, 'b, 'd>(
-    game: &'a Game,
-    virtual_boards: &'b Vec<&'b Board>,
-    pos: (i32, isize),
-) -> Option<&'d Board>
-where
-    'a: 'd,
-    'b: 'd,
-{
-    for vboard in virtual_boards.iter() {
-        if pos.0 == vboard.l && pos.1 == vboard.t {
-            return Some(vboard);
-        }
-    }
-    game.get_board(pos.0, pos.1)
-}
-
-/// Returns the `(l, t, x, y)` square within `game` or `virtual_boards`
-fn get(
-    game: &Game,
-    virtual_boards: &Vec<&Board>,
-    pos: (i32, isize, u8, u8),
-) -> Option<Piece> {
-    get_board(game, virtual_boards, (pos.0, pos.1))
-        .map(|b| b.get(pos.2, pos.3))
-        .flatten()
+/**
+    Returns the exact number of distinct legal movesets that the active player can make; see `ExhaustiveMovesetIter`.
+    Unlike `legal_movesets`, no moveset is left out, so this can be relied upon to tell whether any legal moveset exists.
+**/
+pub fn count_legal_movesets<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> usize {
+    ExhaustiveMovesetIter::new(virtual_boards, info).count()
 }
 
-/// Returns the `(l, t, x, y)` square within either `game`, `virtual_boards` or `board`
-fn get_with_board(
-    game: &Game,
+/// Returns the `(l, t, x, y)` square within either `virtual_boards` or `board`
+fn get_with_board<T: VirtualBoardset>(
     board: &Board,
-    virtual_boards: &Vec<&Board>,
+    virtual_boards: &T,
     pos: (i32, isize, u8, u8),
 ) -> Option<Piece> {
     if pos.0 == board.l && pos.1 == board.t {
         board.get(pos.2, pos.3)
     } else {
-        get_board(game, virtual_boards, (pos.0, pos.1))
-            .map(|b| b.get(pos.2, pos.3))
-            .flatten()
+        virtual_boards.get(pos.0, pos.1, pos.2, pos.3)
     }
 }
 
-/// Returns whether or not `board` is the last board of its timeline (looks in `game` and `virtual_boards`)
-pub fn is_last(game: &Game, virtual_boards: &Vec<&Board>, board: &Board) -> bool {
-    if let Some(tl) = game.get_timeline(board.l) {
-        if (tl.states.len() as isize) + tl.begins_at - 1 > board.t {
-            return false;
-        }
-    }
-    for vboard in virtual_boards.iter() {
-        if vboard.l == board.l && vboard.t > board.t {
-            return false;
-        }
-    }
-    true
-}
-
-/// Returns the set of moves that `piece` can make (does not check the legality of that move)
-pub fn probable_moves_for(
-    game: &Game,
+/// Returns the set of moves that `piece` can make (does not check the legality of that move); the moves are described by `piece.kind()`
+pub fn probable_moves_for<T: VirtualBoardset>(
+    virtual_boards: &T,
     board: &Board,
-    virtual_boards: &Vec<&Board>,
     res: &mut Vec<Move>,
     piece: Piece,
     x: u8,
     y: u8,
 ) -> Option<()> {
+    let kind = match piece.kind() {
+        Some(kind) => kind,
+        None => return Some(()),
+    };
+    let game = virtual_boards.game();
     let src = (board.l, board.t, x, y);
     let active_player = board.active_player();
-    if piece.is_pawn() {
-        let dy: isize = if piece.is_white() { 1 } else { -1 };
-        let y1 = ((y as isize) + dy) as u8;
-        let y2 = ((y as isize) + 2 * dy) as u8;
-        if board.get(x, y1)? == Piece::Blank {
-            res.push(Move::new_with_board(
-                src,
-                (board.l, board.t, x, y1),
-                game,
-                board,
-                virtual_boards,
-            )?);
-            if if piece.is_white() {
-                y <= 1
-            } else {
-                y >= game.height - 2
-            } && board.get(x, y2)? == Piece::Blank
-            {
-                // TODO: handle 1-pawn better
-                res.push(Move::new_with_board(
-                    src,
-                    (board.l, board.t, x, y2),
-                    game,
-                    board,
-                    virtual_boards,
-                )?);
+    let white = piece.is_white();
+    let own = if active_player { &board.bitboards.white } else { &board.bitboards.black };
+    let tables = attack_tables(board.width, board.height);
+
+    for movement in kind.movements {
+        // Leapers and sliders within the board use the precomputed attacks
+        let in_board = match (movement.vectors, movement.range, movement.capture) {
+            (Vectors::NGonal(n), Some(1), Capture::Allowed) if n <= 2 => {
+                Some(tables.leaper(n, board.index(x, y)).clone())
+            }
+            (Vectors::NGonal(n), None, Capture::Allowed) if n == 1 || n == 2 => {
+                Some(tables.slider(n, board.index(x, y), &board.bitboards.occupied()))
+            }
+            _ => None,
+        };
+        if let Some(attacks) = &in_board {
+            for index in attacks.and_not(own).iter() {
+                let dst = (board.l, board.t, (index % board.width as usize) as u8, (index / board.width as usize) as u8);
+                res.push(Move::new_with_board(src, dst, board, virtual_boards)?);
             }
         }
-        // Try to take on x + 1
-        if x < game.width - 1
-            && (may_en_passant(game, board, virtual_boards, x + 1, y1)
-                || board.get(x + 1, y1)?
-                    .is_opponent_piece(active_player))
-        {
-            res.push(Move::new_with_board(
-                src,
-                (board.l, board.t, x + 1, y1),
-                game,
-                board,
-                virtual_boards,
-            )?);
-        }
-        // Try to take on x - 1
-        if x > 0
-            && (may_en_passant(game, board, virtual_boards, x - 1, y1)
-                || board.get(x - 1, y1)?
-                    .is_opponent_piece(active_player))
-        {
-            res.push(Move::new_with_board(
-                src,
-                (board.l, board.t, x - 1, y1),
-                game,
-                board,
-                virtual_boards,
-            )?);
-        }
-    } else if piece.is_king() {
-        for dl in -1isize..=1isize {
-            for dt in -1isize..=1isize {
-                for dy in -1isize..=1isize {
-                    for dx in -1isize..=1isize {
-                        if dx == 0 && dy == 0 && dl == 0 && dt == 0
-                            || x == 0 && dx < 0
-                            || x == game.width - 1 && dx > 0
-                            || y == 0 && dy < 0
-                            || y == game.height - 1 && dy > 0
-                            || board.t < 2 && dt < 0
-                        {
-                            continue;
-                        }
-                        let l1 = if dl == -1 {
-                            board.l - 1
-                        } else if dl == 1 {
-                            board.l + 1
-                        } else {
-                            board.l
-                        };
-                        let t1 = board.t + 2 * dt;
-                        let x1 = ((x as isize) + dx) as u8;
-                        let y1 = ((y as isize) + dy) as u8;
-                        if let Some(true) = get_with_board(game, board, virtual_boards, (l1, t1, x1, y1))
-                            .map(|p| p.is_takable_piece(active_player))
-                        {
-                            res.push(Move::new_with_board(
-                                src,
-                                (l1, t1, x1, y1),
-                                game,
-                                board,
-                                virtual_boards,
-                            )?);
-                        }
+
+        let range = match movement.range {
+            Some(range)
+                if kind.double_step
+                    && movement.capture == Capture::Never
+                    && game.pawn_double_step
+                    && !board.has_moved(x, y) =>
+            {
+                Some(range + 1)
+            }
+            range => range,
+        };
+        let vectors: &[(isize, isize, isize, isize)] = match movement.vectors {
+            Vectors::NGonal(n) => &PERMUTATIONS[n],
+            Vectors::Forward(vectors) => vectors,
+        };
+        let flip = matches!(movement.vectors, Vectors::Forward(_)) && !white;
+
+        for &(dl, dt, dx, dy) in vectors {
+            let (dl, dy) = if flip { (-dl, -dy) } else { (dl, dy) };
+            if in_board.is_some() && dl == 0 && dt == 0 {
+                continue;
+            }
+            let mut length: isize = 1;
+            while range.map(|range| length <= range as isize).unwrap_or(true) {
+                let x0 = x as isize + dx * length;
+                let y0 = y as isize + dy * length;
+                if x0 < 0 || x0 >= game.width as isize || y0 < 0 || y0 >= game.height as isize {
+                    break;
+                }
+                let dst = (board.l + (dl * length) as i32, board.t + 2 * dt * length, x0 as u8, y0 as u8);
+                let target = match get_with_board(board, virtual_boards, dst) {
+                    Some(target) => target,
+                    None => break,
+                };
+
+                let pushed = if target.is_blank() {
+                    movement.capture != Capture::Only
+                        || kind.en_passant
+                            && dl == 0
+                            && dt == 0
+                            && may_en_passant(virtual_boards, board, dst.2, dst.3)
+                } else {
+                    movement.capture != Capture::Never && target.is_opponent_piece(active_player)
+                };
+                if pushed {
+                    let mv = Move::new_with_board(src, dst, board, virtual_boards)?;
+                    if kind.promotes {
+                        push_pawn_move(game, res, mv);
+                    } else {
+                        res.push(mv);
                     }
                 }
+
+                if !target.is_blank() {
+                    break;
+                }
+                length += 1;
             }
         }
-    } else if piece.is_knight() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            0,
-            active_player,
-        )?;
-    } else if piece.is_rook() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            1,
-            active_player,
-        )?;
-    } else if piece.is_bishop() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            2,
-            active_player,
-        )?;
-    } else if piece.is_unicorn() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            3,
-            active_player,
-        )?;
-    } else if piece.is_dragon() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            4,
-            active_player,
-        )?;
-    } else if piece.is_queen() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            1,
-            active_player,
-        )?;
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            2,
-            active_player,
-        )?;
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            3,
-            active_player,
-        )?;
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            4,
-            active_player,
-        )?;
-    } else if piece.is_princess() {
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            1,
-            active_player,
-        )?;
-        n_gonal(
-            game,
-            board,
-            virtual_boards,
-            res,
-            (board.l, board.t, x, y),
-            2,
-            active_player,
-        )?;
     }
     Some(())
 }
 
+/// Pushes the move `mv` of a piece that promotes onto `res`; if it reaches the last rank, pushes it once for every piece of `Game::promotions` instead
+fn push_pawn_move(game: &Game, res: &mut Vec<Move>, mv: Move) {
+    let last_rank = if mv.src_piece.is_white() { game.height - 1 } else { 0 };
+    if mv.dst.3 == last_rank {
+        for piece in game.promotions.iter() {
+            res.push(Move {
+                promotion: Some(piece.with_color(mv.src_piece.is_white())),
+                ..mv
+            });
+        }
+    } else {
+        res.push(mv);
+    }
+}
+
 /// Returns if the `x, y` square in `board` can be taken as en-passant (`[ɑ̃ pasɑ̃]`)
-fn may_en_passant(
-    game: &Game,
+fn may_en_passant<T: VirtualBoardset>(
+    virtual_boards: &T,
     board: &Board,
-    virtual_boards: &Vec<&Board>,
     x: u8,
     y: u8,
 ) -> bool {
-    if board.t < 2 || y == 0 || y == game.height - 1 {
+    if board.t < 2 || y == 0 || y == virtual_boards.game().height - 1 {
         return false;
     }
     let active_player = board.active_player();
@@ -965,76 +730,26 @@ fn may_en_passant(
     } else {
         Piece::PawnW
     };
-    let a = get(game, virtual_boards, (board.l, board.t, x, dst_y)).map(|p| p == piece);
-    let b = get(game, virtual_boards, (board.l, board.t, x, src_y)).map(|p| p == Piece::Blank);
-    let c = get(game, virtual_boards, (board.l, board.t - 2, x, dst_y)).map(|p| p == Piece::Blank);
-    let d = get(game, virtual_boards, (board.l, board.t - 2, x, src_y)).map(|p| p == piece);
+    let a = virtual_boards.get(board.l, board.t, x, dst_y).map(|p| p == piece);
+    let b = virtual_boards.get(board.l, board.t, x, src_y).map(|p| p == Piece::Blank);
+    let c = virtual_boards.get(board.l, board.t - 2, x, dst_y).map(|p| p == Piece::Blank);
+    let d = virtual_boards.get(board.l, board.t - 2, x, src_y).map(|p| p == piece);
     match (a, b, c, d) {
         (Some(true), Some(true), Some(true), Some(true)) => true,
         _ => false,
     }
 }
 
-/// Generate the moves for n-gonals of pieces (knight's is the `0`-th n-gonal)
-fn n_gonal(
-    game: &Game,
-    board: &Board,
-    virtual_boards: &Vec<&Board>,
-    res: &mut Vec<Move>,
-    src: (i32, isize, u8, u8),
-    n: usize,
-    active_player: bool,
-) -> Option<()> {
-    for permutation in &PERMUTATIONS[n] {
-        let mut length: isize = 1;
-        loop {
-            let l0 = (src.0 as isize + permutation.0 * length) as i32;
-            let t0 = src.1 as isize + permutation.1 * length * 2;
-            let x0 = src.2 as isize + permutation.2 * length;
-            let y0 = src.3 as isize + permutation.3 * length;
-            if x0 < 0 || x0 >= game.width as isize || y0 < 0 || y0 >= game.height as isize
-            {
-                break;
-            }
-            let dst = (l0, t0, x0 as u8, y0 as u8);
-            let piece = get_with_board(game, board, virtual_boards, dst);
-
-            if let Some(true) = piece.map(|piece| piece.is_takable_piece(active_player)) {
-                res.push(Move::new_with_board(src, dst, game, board, virtual_boards)?);
-                if piece.unwrap().is_opponent_piece(active_player) {
-                    break;
-                }
-            } else {
-                break;
-            }
-            if n == 0 {
-                break;
-            }
-            length += 1;
-        }
-    }
-    Some(())
-}
-
-/// Re-calculate the present
-pub fn find_present(game: &Game, virtual_boards: &Vec<&Board>, info: &GameInfo) -> isize {
-    let mut min = info.present;
-    game.timelines
-        .values()
-        .map(|tl| &tl.states[tl.states.len() - 1])
-        .filter(|b| is_last(game, virtual_boards, b) && b.is_active(info))
-        .for_each(|b| {
-            if b.t < min {
-                min = b.t;
-            }
-        });
-    for b in virtual_boards {
-        if is_last(game, virtual_boards, b) && b.t < min && b.is_active(info) {
-            min = b.t;
-        }
-    }
-
-    min
+/**
+    Re-calculates the present: the time of the earliest last board among the active timelines (see `GameInfo::is_timeline_active`).
+    The timeline bounds of `info` are used, while its `present` is only returned if there is no active timeline.
+**/
+pub fn find_present<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> isize {
+    last_boards(virtual_boards)
+        .filter(|b| info.is_timeline_active(b.l))
+        .map(|b| b.t)
+        .min()
+        .unwrap_or(info.present)
 }
 
 // TODO: fix this
@@ -1050,10 +765,40 @@ pub fn is_optional(info: &GameInfo, mv: &Move) -> bool {
     }
 }
 
+/// Result of `is_checkmate`
+#[derive(Debug, Clone)]
+pub enum MateResult {
+    /// The active player can make a legal moveset; holds one of them (the witness), the boards that it creates and the resulting `GameInfo`
+    Playable(Vec<Move>, Vec<Board>, GameInfo),
+    /// The active player cannot make any legal moveset and is in check
+    Checkmate,
+    /// The active player cannot make any legal moveset but is not in check
+    Stalemate,
+}
+
+/**
+    Proves whether or not the active player can make a legal moveset, and returns a witness moveset if so.
+
+    The witness is looked for board by board with `find_legal_moveset`, which stops at the first legal moveset and prunes the movesets that can't be legal,
+    so a checkmate or stalemate is only declared once no legal moveset exists.
+    Unlike `is_draw`, this does not assume that no moveset can be made.
+**/
+pub fn is_checkmate<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> MateResult {
+    if let Some((moveset, boards, new_info)) = find_legal_moveset(virtual_boards, info) {
+        return MateResult::Playable(moveset, boards, new_info);
+    }
+
+    if is_in_check(virtual_boards, info) {
+        MateResult::Checkmate
+    } else {
+        MateResult::Stalemate
+    }
+}
+
 /// Returns whether or not the game is a draw; assumes that no move can be made
-pub fn is_draw(game: &Game, virtual_boards: &Vec<&Board>, info: &GameInfo) -> bool {
-    let opponent_boards = get_opponent_boards(game, virtual_boards, info).into_iter().filter(|b| b.is_active(info)).collect::<Vec<_>>();
-    let own_boards = get_own_boards(game, virtual_boards, info)
+pub fn is_draw<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> bool {
+    let opponent_boards = get_opponent_boards(virtual_boards, info).into_iter().filter(|b| b.is_active(info)).collect::<Vec<_>>();
+    let own_boards = get_own_boards(virtual_boards, info)
         .into_iter()
         .cloned()
         .filter(|b| b.is_active(info))
@@ -1063,29 +808,13 @@ pub fn is_draw(game: &Game, virtual_boards: &Vec<&Board>, info: &GameInfo) -> bo
         })
         .collect::<Vec<_>>();
 
-    let merged_vboards = virtual_boards
-        .iter()
-        .map(|x| *x)
-        .chain(opponent_boards.iter().map(|x| *x))
-        .chain(own_boards.iter())
-        .collect::<Vec<_>>();
-
-    // TODO: merge mutated own_boards with virtual_boards
-
-    for b in opponent_boards.into_iter() {
-        for mv in probable_moves(game, b, &merged_vboards) {
-            if mv.dst_piece.is_king() {
-                return false;
-            }
-        }
-    }
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, own_boards.iter().collect());
 
-    for b in own_boards.iter() {
-        for mv in probable_moves(game, &b, &merged_vboards) {
-            if mv.dst_piece.is_king() {
-                return false;
-            }
-        }
+    let allows_royal_capture = |b: &Board| {
+        probable_moves_without_castling(&merged_vboards, b).is_none_or(|moves| moves.iter().any(|mv| mv.dst_piece.is_royal()))
+    };
+    if opponent_boards.into_iter().any(allows_royal_capture) || own_boards.iter().any(allows_royal_capture) {
+        return false;
     }
 
     true
@@ -1,4 +1,5 @@
-use crate::{game::*, moves::*, resolve::*};
+use crate::{game::*, moves::*, resolve::*, vboard::*};
+use std::collections::HashSet;
 
 // TODO: optional boards
 
@@ -6,9 +7,8 @@ use crate::{game::*, moves::*, resolve::*};
     An iterator over movesets. Movesets are lazily yielded, based on the sorting done on `moves`.
 **/
 #[allow(dead_code)]
-pub struct MovesetIter<'a> {
-    game: &'a Game,
-    virtual_boards: &'a Vec<&'a Board>,
+pub struct MovesetIter<'a, T> {
+    virtual_boards: &'a T,
     info: GameInfo,
     /// List of moves per board, scored and sorted
     moves: Vec<Vec<(Move, Vec<Board>, GameInfo, i32)>>,
@@ -26,7 +26,7 @@ pub struct MovesetIter<'a> {
     pub movesets_considered: usize,
 }
 
-impl<'a> Iterator for MovesetIter<'a> {
+impl<'a, T: VirtualBoardset> Iterator for MovesetIter<'a, T> {
     type Item = Vec<Move>;
 
     /// Yields a moveset, if there are still any to yield
@@ -77,13 +77,12 @@ impl<'a> Iterator for MovesetIter<'a> {
     }
 }
 
-impl<'a> MovesetIter<'a> {
+impl<'a, T: VirtualBoardset> MovesetIter<'a, T> {
     /**
     Generates a new MovesetIter. Assumes that `moves` was already sorted.
     **/
     pub fn new(
-        game: &'a Game,
-        virtual_boards: &'a Vec<&'a Board>,
+        virtual_boards: &'a T,
         info: &'a GameInfo,
         moves: Vec<Vec<(Move, Vec<Board>, GameInfo, i32)>>,
     ) -> Self {
@@ -95,7 +94,6 @@ impl<'a> MovesetIter<'a> {
             })
             .collect::<Vec<_>>();
         MovesetIter {
-            game,
             virtual_boards,
             info: info.clone(),
             max_moves: moves.iter().map(|m| m.len()).max().unwrap_or(0) + 1,
@@ -270,17 +268,15 @@ impl<'a> MovesetIter<'a> {
     Lazily applies the `score_moveset` function to the movesets and filters out the illegal movesets
     **/
     pub fn score(self) -> impl Iterator<Item = (Vec<Move>, Vec<Board>, GameInfo, f32)> + 'a {
-        // NOTE: I still don't quite understand why this doesn't fail to compile
-        let game = self.game;
         let virtual_boards = self.virtual_boards;
         let info = self.info;
+        let opponent_boards = get_opponent_boards(virtual_boards, &info);
 
         self.map(move |ms| {
             score_moveset(
-                &game,
-                &virtual_boards,
+                virtual_boards,
                 &info,
-                get_opponent_boards(game, virtual_boards, &info).into_iter(),
+                opponent_boards.iter().copied(),
                 ms,
             )
         })
@@ -288,3 +284,573 @@ impl<'a> MovesetIter<'a> {
         .map(|x| x.unwrap())
     }
 }
+
+/**
+    An iterator over every distinct legal moveset, yielded along with the boards that it creates and the resulting `GameInfo`.
+
+    Unlike `MovesetIter`, no move is pruned nor ranked: every board that the active player can play on is either left untouched or played on with one of its `probable_moves`.
+    Non-jumping moves are played first and jumping moves are then played in every order, as the order of the latter decides which jumps branch and how the new timelines are numbered.
+    Movesets that lead to the same boards (compared by their `(l, t)` coordinates and zobrist hash) and to the same timeline numbering are only yielded once.
+
+    This makes the number of yielded movesets exact (see `count_legal_movesets`), at the cost of being much slower than `MovesetIter`.
+**/
+pub struct ExhaustiveMovesetIter<'a, T> {
+    virtual_boards: &'a T,
+    opponent_boards: Vec<&'a Board>,
+    /// Probable moves of each of the active player's boards, sorted by `(l, t)`
+    moves: Vec<Vec<Move>>,
+    /// Choice made for each board, along with the state preceding it; choice 0 leaves the board untouched and choice `i + 1` plays `moves[board][i]`
+    stack: Vec<ExhaustiveFrame>,
+    started: bool,
+    info: GameInfo,
+    /// The non-jumping moves chosen so far and the boards that they created
+    normal_moves: Vec<Move>,
+    normal_boards: Vec<Board>,
+    /// The jumping moves chosen so far; they are played once a choice was made for every board
+    jumping_moves: Vec<Move>,
+    /// Legal movesets found but not yet yielded
+    pending: Vec<(Vec<Move>, Vec<Board>, GameInfo)>,
+    /// Keys of the movesets found so far
+    seen: HashSet<(Vec<(i32, isize, u64)>, isize, i32, i32)>,
+}
+
+struct ExhaustiveFrame {
+    choice: usize,
+    info: GameInfo,
+    n_normal_moves: usize,
+    n_normal_boards: usize,
+    n_jumping_moves: usize,
+}
+
+impl<'a, T: VirtualBoardset> ExhaustiveMovesetIter<'a, T> {
+    /// Creates a new ExhaustiveMovesetIter, yielding the movesets that `info.active_player` can make
+    pub fn new(virtual_boards: &'a T, info: &GameInfo) -> Self {
+        let mut own_boards = get_own_boards(virtual_boards, info);
+        own_boards.sort_by_key(|b| (b.l, b.t));
+        let moves = own_boards
+            .into_iter()
+            .map(|board| probable_moves(virtual_boards, board).unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        ExhaustiveMovesetIter {
+            virtual_boards,
+            opponent_boards: get_opponent_boards(virtual_boards, info),
+            moves,
+            stack: Vec::new(),
+            started: false,
+            info: *info,
+            normal_moves: Vec::new(),
+            normal_boards: Vec::new(),
+            jumping_moves: Vec::new(),
+            pending: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Makes the `choice` for the next board (see `stack`), returns false if the move can't be played
+    fn push_choice(&mut self, choice: usize) -> bool {
+        let index = self.stack.len();
+        let frame = ExhaustiveFrame {
+            choice,
+            info: self.info,
+            n_normal_moves: self.normal_moves.len(),
+            n_normal_boards: self.normal_boards.len(),
+            n_jumping_moves: self.jumping_moves.len(),
+        };
+
+        if choice > 0 {
+            let mv = self.moves[index][choice - 1];
+            if mv.src.0 != mv.dst.0 || mv.src.1 != mv.dst.1 {
+                self.jumping_moves.push(mv);
+            } else {
+                match mv.generate_vboards(self.virtual_boards, &self.info, &self.normal_boards) {
+                    Ok((new_info, mut new_vboards)) => {
+                        self.info = new_info;
+                        self.normal_boards.append(&mut new_vboards);
+                        self.normal_moves.push(mv);
+                    }
+                    Err(_) => return false,
+                }
+            }
+        }
+
+        self.stack.push(frame);
+        true
+    }
+
+    /// Undoes the last choice and returns it
+    fn pop_choice(&mut self) -> Option<usize> {
+        let frame = self.stack.pop()?;
+        self.info = frame.info;
+        self.normal_moves.truncate(frame.n_normal_moves);
+        self.normal_boards.truncate(frame.n_normal_boards);
+        self.jumping_moves.truncate(frame.n_jumping_moves);
+        Some(frame.choice)
+    }
+
+    /// Moves on to the next set of moves with a choice for every board; returns false once every set was visited
+    fn next_combination(&mut self) -> bool {
+        if self.started {
+            loop {
+                let index = self.stack.len();
+                let choice = match self.pop_choice() {
+                    Some(choice) => choice,
+                    None => return false,
+                };
+                let index = index - 1;
+                if (choice + 1..=self.moves[index].len()).any(|c| self.push_choice(c)) {
+                    break;
+                }
+            }
+        } else {
+            self.started = true;
+        }
+
+        while self.stack.len() < self.moves.len() {
+            self.push_choice(0);
+        }
+        true
+    }
+
+    /// Plays the jumping moves of the current combination in every order and stores the legal, new movesets in `pending`
+    fn resolve_combination(&mut self) {
+        if self.normal_moves.is_empty() && self.jumping_moves.is_empty() {
+            return;
+        }
+
+        for permutation in permute::permutations_of(&self.jumping_moves) {
+            let played = play_jumps(
+                self.virtual_boards,
+                &self.info,
+                &self.opponent_boards,
+                &self.normal_moves,
+                &self.normal_boards,
+                permutation,
+            );
+            if let Some((moveset, moveset_boards, info)) = played {
+                let mut key: Vec<(i32, isize, u64)> = moveset_boards.iter().map(|b| (b.l, b.t, b.hash)).collect();
+                key.sort();
+                if self.seen.insert((key, info.present, info.min_timeline, info.max_timeline)) {
+                    self.pending.push((moveset, moveset_boards, info));
+                }
+            }
+        }
+    }
+}
+
+/**
+    Plays the jumping moves `jumps` (in that order) after the non-jumping moves `normal_moves`, which created `normal_boards`,
+    and returns the moveset, its boards and the resulting `GameInfo` (with the turn passed to the opponent) if the moveset is legal.
+**/
+fn play_jumps<'b, T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    opponent_boards: &[&Board],
+    normal_moves: &[Move],
+    normal_boards: &[Board],
+    jumps: impl Iterator<Item = &'b Move>,
+) -> Option<(Vec<Move>, Vec<Board>, GameInfo)> {
+    let mut info = *info;
+    let mut moveset = normal_moves.to_vec();
+    let mut moveset_boards = normal_boards.to_vec();
+
+    for mv in jumps {
+        let (new_info, mut new_vboards) = mv.generate_vboards(virtual_boards, &info, &moveset_boards).ok()?;
+        info = new_info;
+        moveset_boards.append(&mut new_vboards);
+        moveset.push(*mv);
+    }
+
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, moveset_boards.iter().collect());
+    info.present = find_present(&merged_vboards, &info);
+
+    if is_moveset_legal(&merged_vboards, &info, moveset_boards.iter())
+        && is_moveset_legal(&merged_vboards, &info, opponent_boards.iter().copied())
+        && all_boards_played(&merged_vboards, &info)
+    {
+        info.active_player = !info.active_player;
+        Some((moveset, moveset_boards, info))
+    } else {
+        None
+    }
+}
+
+impl<'a, T: VirtualBoardset> Iterator for ExhaustiveMovesetIter<'a, T> {
+    type Item = (Vec<Move>, Vec<Board>, GameInfo);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(moveset) = self.pending.pop() {
+                return Some(moveset);
+            }
+            if !self.next_combination() {
+                return None;
+            }
+            self.resolve_combination();
+        }
+    }
+}
+
+/**
+    Looks for a single legal moveset that the active player can make and returns it, along with the boards that it creates and the resulting `GameInfo`; returns None if there is none.
+
+    Movesets are built board by board and the search stops at the first legal one, so that a position with a legal moveset is usually decided after a handful of moves.
+    Proving that no moveset exists is what makes it expensive, so the search relies on the fact that adding boards never takes a royal capture away from the opponent:
+
+    - moves after which the opponent could capture a royal piece (from the board they leave behind, from the opponent's boards
+      or within the board that a jumping move lands on) are discarded upfront,
+      and so is any partial moveset whose boards already allow such a capture (the board that a jumping move leaves behind doesn't depend on the other moves,
+      nor does the board that it lands on once no other move may make it branch);
+    - if no jumping move exists, the present can't move, so the boards that must be played are played and the other ones are left untouched;
+    - a board that must be played is only left untouched if a jumping move that may move the present back can still be made:
+      one that lands on it, one that lands on an earlier board if the timeline that it creates would be active,
+      or one that may create a timeline if an inactive timeline that it may activate ends before that board;
+    - the boards with the fewest moves are tried first, except that a board left untouched is made up for by deciding first on a board that can jump onto it,
+      and jumping moves are only played (in every order) once a choice was made for every board.
+
+    Used by `is_checkmate`; `ExhaustiveMovesetIter` enumerates every moveset instead.
+**/
+pub fn find_legal_moveset<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+) -> Option<(Vec<Move>, Vec<Board>, GameInfo)> {
+    let opponent_boards = get_opponent_boards(virtual_boards, info);
+    // Whether the opponent can't capture a royal piece once `boards` are added, before any other board is
+    let is_safe = |boards: &[Board]| {
+        let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, boards.iter().collect());
+        !boards
+            .iter()
+            .chain(opponent_boards.iter().copied())
+            .any(|b| allows_royal_capture(&merged_vboards, b))
+    };
+
+    let mut boards: Vec<(&Board, Vec<Choice>, Vec<Choice>)> = Vec::new();
+    for board in get_own_boards(virtual_boards, info) {
+        let mut normal = Vec::new();
+        let mut jumping = Vec::new();
+        for mv in probable_moves(virtual_boards, board).unwrap_or_default() {
+            let generated = mv.generate_vboards(virtual_boards, info, &[]).ok();
+            if mv.src.0 == mv.dst.0 && mv.src.1 == mv.dst.1 {
+                if generated.filter(|(_info, new_vboards)| is_safe(new_vboards)).is_some() {
+                    normal.push(Choice::Play(mv));
+                }
+            } else {
+                let (left_behind, landed): (Vec<Board>, Vec<Board>) = generated
+                    .map(|(_info, new_vboards)| new_vboards)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .partition(|b| b.l == mv.src.0 && b.t == mv.src.1 + 1);
+                // The board that the piece lands on doesn't depend on the other moves, but its timeline might
+                let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, landed.iter().collect());
+                if landed.iter().any(|b| allows_royal_capture_within(&merged_vboards, b)) {
+                    continue;
+                }
+                match left_behind.into_iter().next() {
+                    Some(left_behind) if !is_safe(std::slice::from_ref(&left_behind)) => {}
+                    left_behind => jumping.push(Choice::Jump(mv, left_behind, landed.into_iter().next())),
+                }
+            }
+        }
+        boards.push((board, normal, jumping));
+    }
+
+    let can_jump = boards.iter().any(|(_, _, jumping)| !jumping.is_empty());
+    let mut choices: Vec<BoardChoices> = Vec::new();
+    for (board, normal, jumping) in boards.into_iter() {
+        let active = board.is_active(info);
+        let jump_targets = jumping
+            .iter()
+            .filter_map(|choice| match choice {
+                Choice::Jump(mv, _, _) => Some((mv.dst.0, mv.dst.1)),
+                _ => None,
+            })
+            .collect();
+        let moves = normal.into_iter().chain(jumping);
+        let choices_of_board: Vec<Choice> = match (active, can_jump) {
+            (true, true) => moves.chain(std::iter::once(Choice::Pass)).collect(),
+            (true, false) => moves.collect(),
+            (false, true) => std::iter::once(Choice::Pass).chain(moves).collect(),
+            (false, false) => vec![Choice::Pass],
+        };
+        if choices_of_board.is_empty() {
+            return None;
+        }
+        choices.push(BoardChoices {
+            l: board.l,
+            t: board.t,
+            active,
+            choices: choices_of_board,
+            jump_targets,
+        });
+    }
+    choices.sort_by_key(|c| (!c.active, c.choices.len()));
+    // The timelines created by white may activate those of black (l < 0) and vice versa
+    let inactive_end = last_boards(virtual_boards)
+        .filter(|b| !info.is_timeline_active(b.l) && (b.l < 0) == info.active_player)
+        .map(|b| b.t)
+        .min()
+        .unwrap_or(isize::MAX);
+
+    let mut branched_info = *info;
+    let new_timeline = if info.active_player {
+        branched_info.max_timeline += 1;
+        branched_info.max_timeline
+    } else {
+        branched_info.min_timeline -= 1;
+        branched_info.min_timeline
+    };
+
+    let search = LegalMovesetSearch {
+        virtual_boards,
+        opponent_boards,
+        boards: choices,
+        inactive_end,
+        new_timeline_active: branched_info.is_timeline_active(new_timeline),
+    };
+    let mut state = LegalMovesetState {
+        remaining: (0..search.boards.len()).collect(),
+        ..Default::default()
+    };
+    search.search(*info, &mut state)
+}
+
+/// Returns whether the opponent can capture a royal piece from `board`; assumes so if the opponent's moves can't be generated
+fn allows_royal_capture<T: VirtualBoardset>(virtual_boards: &T, board: &Board) -> bool {
+    probable_moves_without_castling(virtual_boards, board)
+        .is_none_or(|moves| moves.iter().any(|mv| mv.dst_piece.is_royal()))
+}
+
+/// Returns whether the opponent can capture a royal piece of `board` without leaving it; assumes so if the opponent's moves can't be generated
+fn allows_royal_capture_within<T: VirtualBoardset>(virtual_boards: &T, board: &Board) -> bool {
+    probable_moves_without_castling(virtual_boards, board)
+        .is_none_or(|moves| moves.iter().any(|mv| mv.dst_piece.is_royal() && mv.dst.0 == board.l && mv.dst.1 == board.t))
+}
+
+/// A choice that `find_legal_moveset` can make for one of the active player's boards
+enum Choice {
+    /// Leaves the board untouched
+    Pass,
+    /// Plays a non-jumping move
+    Play(Move),
+    /// Plays a jumping move, which leaves the first board behind and lands on the second one, if it doesn't branch (None if it can't be played alone)
+    Jump(Move, Option<Board>, Option<Board>),
+}
+
+/// The choices that `find_legal_moveset` can make for one of the active player's boards
+struct BoardChoices {
+    l: i32,
+    t: isize,
+    /// Whether the board must be played on
+    active: bool,
+    choices: Vec<Choice>,
+    /// The `(l, t)` boards that the board's jumping moves land on
+    jump_targets: HashSet<(i32, isize)>,
+}
+
+/// The moves chosen so far by `find_legal_moveset`'s search
+#[derive(Default)]
+struct LegalMovesetState {
+    normal_moves: Vec<Move>,
+    /// The boards created by `normal_moves`
+    normal_boards: Vec<Board>,
+    jumping_moves: Vec<Move>,
+    /// The boards left behind by `jumping_moves`
+    left_boards: Vec<Board>,
+    /// The boards that `jumping_moves` land on, if they don't branch
+    landed_boards: Vec<Option<Board>>,
+    /// The boards that had to be played on but were left untouched
+    passed: Vec<(i32, isize)>,
+    /// The boards that were played on
+    played: Vec<(i32, isize)>,
+    /// The indices in `LegalMovesetSearch::boards` of the boards that are yet to be decided on
+    remaining: Vec<usize>,
+}
+
+/// State of `find_legal_moveset`'s search
+struct LegalMovesetSearch<'a, T> {
+    virtual_boards: &'a T,
+    opponent_boards: Vec<&'a Board>,
+    boards: Vec<BoardChoices>,
+    /// The time of the earliest last board of an inactive timeline that a new timeline may activate
+    inactive_end: isize,
+    /// Whether the first timeline created by the active player would be active
+    new_timeline_active: bool,
+}
+
+impl<'a, T: VirtualBoardset> LegalMovesetSearch<'a, T> {
+    /// Makes a choice for the remaining boards, returns the first legal moveset found
+    fn search(&self, info: GameInfo, state: &mut LegalMovesetState) -> Option<(Vec<Move>, Vec<Board>, GameInfo)> {
+        let index = {
+            // A jump that may create a timeline if it lands on a board that is or may be played on
+            let may_branch = |dst: (i32, isize)| {
+                self.virtual_boards.get_board(dst.0, dst.1 + 1).is_some()
+                    || state.played.contains(&dst)
+                    || state.remaining.iter().any(|&i| (self.boards[i].l, self.boards[i].t) == dst)
+            };
+            // Only a jump landing on a passed board can make up for it, or one landing on an earlier board if the new timeline is active,
+            // or one that creates a timeline if that may activate a timeline that ends earlier
+            let makes_up = |(l, t): (i32, isize), dst: (i32, isize), branch: bool| {
+                dst == (l, t) || self.new_timeline_active && dst.1 < t || self.inactive_end < t && branch
+            };
+            let can_make_up = |passed: (i32, isize), i: usize| {
+                self.boards[i].jump_targets.iter().any(|&dst| makes_up(passed, dst, true))
+            };
+            let pending: Vec<(i32, isize)> = state
+                .passed
+                .iter()
+                .copied()
+                .filter(|&passed| {
+                    !state.jumping_moves.iter().any(|mv| {
+                        let dst = (mv.dst.0, mv.dst.1);
+                        makes_up(passed, dst, may_branch(dst))
+                    })
+                })
+                .collect();
+            if !pending.iter().all(|&passed| state.remaining.iter().any(|&i| can_make_up(passed, i))) {
+                return None;
+            }
+            // Decides first on a board that can make up for a passed board
+            match pending.first() {
+                Some(&passed) => state.remaining.iter().position(|&i| can_make_up(passed, i)),
+                None if state.remaining.is_empty() => None,
+                None => Some(0),
+            }
+        };
+
+        let index = match index {
+            Some(index) => index,
+            None => {
+                if state.normal_moves.is_empty() && state.jumping_moves.is_empty() {
+                    return None;
+                }
+                return permute::permutations_of(&state.jumping_moves).find_map(|permutation| {
+                    play_jumps(
+                        self.virtual_boards,
+                        &info,
+                        &self.opponent_boards,
+                        &state.normal_moves,
+                        &state.normal_boards,
+                        permutation,
+                    )
+                });
+            }
+        };
+
+        let i = state.remaining.remove(index);
+        let res = self.search_board(&self.boards[i], info, state);
+        state.remaining.insert(index, i);
+        res
+    }
+
+    /// Makes a choice for `board`, then for the remaining boards
+    fn search_board(
+        &self,
+        board: &BoardChoices,
+        info: GameInfo,
+        state: &mut LegalMovesetState,
+    ) -> Option<(Vec<Move>, Vec<Board>, GameInfo)> {
+        for choice in board.choices.iter() {
+            let res = match choice {
+                Choice::Pass => {
+                    if board.active {
+                        state.passed.push((board.l, board.t));
+                    }
+                    // A jumping move that lands on the board might now be known not to branch
+                    let lands_on_board = state.jumping_moves.iter().any(|mv| mv.dst.0 == board.l && mv.dst.1 == board.t);
+                    let res = if lands_on_board && self.allows_royal_capture(state) {
+                        None
+                    } else {
+                        self.search(info, state)
+                    };
+                    if board.active {
+                        state.passed.pop();
+                    }
+                    res
+                }
+                Choice::Jump(mv, left_behind, landed) => {
+                    state.played.push((board.l, board.t));
+                    state.jumping_moves.push(*mv);
+                    state.landed_boards.push(landed.clone());
+                    let n_left_boards = state.left_boards.len();
+                    state.left_boards.extend(left_behind.iter().cloned());
+                    let res = if self.allows_royal_capture(state) {
+                        None
+                    } else {
+                        self.search(info, state)
+                    };
+                    state.left_boards.truncate(n_left_boards);
+                    state.landed_boards.pop();
+                    state.jumping_moves.pop();
+                    state.played.pop();
+                    res
+                }
+                Choice::Play(mv) => {
+                    let (new_info, mut new_vboards) =
+                        match mv.generate_vboards(self.virtual_boards, &info, &state.normal_boards) {
+                            Ok(generated) => generated,
+                            Err(_) => continue,
+                        };
+                    state.played.push((board.l, board.t));
+                    state.normal_moves.push(*mv);
+                    let n_normal_boards = state.normal_boards.len();
+                    state.normal_boards.append(&mut new_vboards);
+                    let res = if self.allows_royal_capture(state) {
+                        None
+                    } else {
+                        self.search(new_info, state)
+                    };
+                    state.normal_boards.truncate(n_normal_boards);
+                    state.normal_moves.pop();
+                    state.played.pop();
+                    res
+                }
+            };
+            if res.is_some() {
+                return res;
+            }
+        }
+        None
+    }
+
+    /**
+        Returns the boards that the jumping moves chosen so far land on, for those that can't branch anymore:
+        no other move is or may be played on the board that they land on, and no other jumping move may land on it.
+    **/
+    fn fixed_landed_boards<'b>(&self, state: &'b LegalMovesetState) -> impl Iterator<Item = &'b Board> + 'b {
+        let lands_on = |dst: (i32, isize)| state.jumping_moves.iter().filter(move |mv| (mv.dst.0, mv.dst.1) == dst).count();
+        let fixed: Vec<bool> = state
+            .jumping_moves
+            .iter()
+            .map(|mv| {
+                let dst = (mv.dst.0, mv.dst.1);
+                self.virtual_boards.get_board(dst.0, dst.1 + 1).is_none()
+                    && !state.played.contains(&dst)
+                    && lands_on(dst) == 1
+                    && state.remaining.iter().all(|&i| {
+                        let board = &self.boards[i];
+                        (board.l, board.t) != dst && !board.jump_targets.contains(&dst)
+                    })
+            })
+            .collect();
+        state
+            .landed_boards
+            .iter()
+            .zip(fixed)
+            .filter_map(|(landed, fixed)| landed.as_ref().filter(|_| fixed))
+    }
+
+    /// Returns whether the opponent can already capture a royal piece from one of the boards created so far or from their own boards; it will still be able to once every move is made
+    fn allows_royal_capture(&self, state: &LegalMovesetState) -> bool {
+        let boards: Vec<&Board> = state
+            .normal_boards
+            .iter()
+            .chain(state.left_boards.iter())
+            .chain(self.fixed_landed_boards(state))
+            .collect();
+        let merged_vboards = OverlayVirtualBoardset::new(self.virtual_boards, boards.clone());
+        boards
+            .into_iter()
+            .chain(self.opponent_boards.iter().copied())
+            .any(|b| allows_royal_capture(&merged_vboards, b))
+    }
+}
@@ -0,0 +1,265 @@
+/*
+    Reading and writing moves in the 5D chess notation, as written by players
+    (eg. `(0T1)Ng1f3`, `(0T2)Nb1>>(0T1)b3`, `(-1T5)Qd1>>x(0T3)d7`, `(0T6)O-O`, `(+0T9)e8=Q`).
+*/
+
+use super::{game::*, moves::*, vboard::*};
+
+/**
+    Writes out `mv` in 5D notation. `virtual_boards` is used to tell branching (`>>`) and non-branching (`>`) jumps apart,
+    and to write the labels of even timelines (`+0`/`-0`).
+    The move must not have been committed to `virtual_boards` yet.
+**/
+pub fn write_move<T: VirtualBoardset>(virtual_boards: &T, mv: &Move) -> String {
+    let branching = virtual_boards
+        .get_board(mv.dst.0, mv.dst.1)
+        .map(|b| !virtual_boards.is_last(b))
+        .unwrap_or(true);
+
+    write_move_raw(mv, virtual_boards.game().info.even_initial_timelines, branching)
+}
+
+/**
+    Writes out a moveset in 5D notation, as a whitespace-separated list of moves (see `write_move`).
+    Moves are played one after the other, so that a jump onto a board that was already played on in this moveset is written as branching.
+    Returns None if the moveset can't be played.
+**/
+pub fn write_moveset<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    moveset: &[Move],
+) -> Option<String> {
+    let mut res: Vec<String> = Vec::with_capacity(moveset.len());
+    let mut info = *info;
+    let mut moveset_boards: Vec<Board> = Vec::new();
+
+    for mv in moveset {
+        let branching = virtual_boards
+            .get_board(mv.dst.0, mv.dst.1)
+            .map(|b| !virtual_boards.is_last(b))
+            .unwrap_or(true)
+            || moveset_boards.iter().any(|b| b.l == mv.dst.0 && b.t == mv.dst.1 + 1);
+        res.push(write_move_raw(mv, virtual_boards.game().info.even_initial_timelines, branching));
+
+        let (new_info, mut new_vboards) =
+            mv.generate_vboards(virtual_boards, &info, &moveset_boards).ok()?;
+        info = new_info;
+        moveset_boards.append(&mut new_vboards);
+    }
+
+    Some(res.join(" "))
+}
+
+/**
+    Writes out `mv` in 5D notation, given the information that `Move` does not hold by itself.
+    Non-pawn pieces are written with their source square; pawns are written with their source file when they capture.
+**/
+pub(crate) fn write_move_raw(
+    mv: &Move,
+    even_initial_timelines: bool,
+    branching: bool,
+) -> String {
+    if mv.noop {
+        return String::from("_");
+    }
+
+    let mut res = write_board(mv.src.0, mv.src.1, even_initial_timelines);
+
+    if mv.castle {
+        res.push_str(if mv.castle_long { "O-O-O" } else { "O-O" });
+        return res;
+    }
+
+    let piece = if mv.src_piece.is_pawn() { "" } else { mv.src_piece.as_uppercase() };
+
+    if mv.src.0 == mv.dst.0 && mv.src.1 == mv.dst.1 {
+        let capture = !mv.dst_piece.is_blank() || mv.src_piece.is_pawn() && mv.src.2 != mv.dst.2;
+        res.push_str(piece);
+        if mv.src_piece.is_pawn() {
+            if capture {
+                res.push(write_file(mv.src.2));
+            }
+        } else {
+            res.push_str(&write_square(mv.src.2, mv.src.3));
+        }
+        if capture {
+            res.push('x');
+        }
+        res.push_str(&write_square(mv.dst.2, mv.dst.3));
+    } else {
+        res.push_str(piece);
+        res.push_str(&write_square(mv.src.2, mv.src.3));
+        res.push_str(if branching { ">>" } else { ">" });
+        if !mv.dst_piece.is_blank() {
+            res.push('x');
+        }
+        res.push_str(&write_board(mv.dst.0, mv.dst.1, even_initial_timelines));
+        res.push_str(&write_square(mv.dst.2, mv.dst.3));
+    }
+
+    if let Some(promotion) = mv.promotion {
+        res.push('=');
+        res.push_str(promotion.as_uppercase());
+    }
+
+    res
+}
+
+/// Writes the `(LTt)` prefix of the `(l, t)` board
+fn write_board(l: i32, t: isize, even_initial_timelines: bool) -> String {
+    format!("({}T{})", write_timeline(l, even_initial_timelines), t.div_euclid(2) + 1)
+}
+
+/// Writes the `(x, y)` square, eg. `c3`
+fn write_square(x: u8, y: u8) -> String {
+    format!("{}{}", write_file(x), y + 1)
+}
+
+/**
+    Parses a move written in 5D notation, played by `info.active_player`.
+    The source and destination pieces are read from `virtual_boards`; short and disambiguated forms
+    (`(0T1)Nc3`, `(0T1)Nbd2`, `(0T1)exd5`, `Nf3` if only one board can be played on) are resolved by looking at the moves that can be made.
+
+    Returns `None` if the move couldn't be read, if it is ambiguous or if no piece can make that move.
+    Promotions are written as `=N`; if left out, the pawn promotes to the first piece of `Game::promotions`.
+    `+`, `#`, `~`, `!` and `?` annotations are ignored; `>` and `>>` are treated alike.
+**/
+pub fn parse_move<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    raw: &str,
+) -> Option<Move> {
+    let game = virtual_boards.game();
+    let raw = raw.trim().trim_end_matches(|c| "+#~!?".contains(c));
+    let even = info.even_initial_timelines;
+    let parity: isize = if info.active_player { 0 } else { 1 };
+
+    let (src_board, rest) = if raw.starts_with('(') {
+        let (l, t, rest) = parse_board(raw, even)?;
+        (virtual_boards.get_board(l, t * 2 + parity)?, rest)
+    } else {
+        // No board given: only accepted if there is a single board to play on
+        let own_boards = get_own_boards(virtual_boards, info);
+        if own_boards.len() != 1 {
+            return None;
+        }
+        (own_boards[0], raw)
+    };
+
+    let candidates = probable_moves(virtual_boards, src_board)?;
+
+    if rest == "O-O" || rest == "0-0" || rest == "O-O-O" || rest == "0-0-0" {
+        let long = rest.len() == 5;
+        return unique(candidates.into_iter().filter(|mv| mv.castle && mv.castle_long == long));
+    }
+
+    let (src, dst, dst_board) = match rest.find('>') {
+        Some(index) => {
+            let dst = rest[index..].trim_start_matches('>').trim_start_matches('x');
+            let (l, t, dst) = parse_board(dst, even)?;
+            (&rest[..index], dst, Some((l, t * 2 + parity)))
+        }
+        None => ("", rest, None),
+    };
+
+    // Destination square and promotion
+    let (dst, promotion) = match dst.find('=') {
+        Some(index) => (&dst[..index], Some(&dst[index + 1..])),
+        None => (dst, None),
+    };
+    let (prefix, dst_x, dst_y) = parse_square_suffix(dst)?;
+    let (dst_l, dst_t) = dst_board.unwrap_or((src_board.l, src_board.t));
+
+    // Piece and disambiguation
+    let src = if dst_board.is_some() { src } else { prefix.trim_end_matches('x') };
+    let (piece, src) = match src.chars().next() {
+        Some(c) if c.is_ascii_uppercase() => (&src[..1], &src[1..]),
+        _ => ("P", src),
+    };
+    let src_x = match src.chars().next() {
+        Some(c) if c.is_ascii_lowercase() => Some(parse_file(c)?),
+        _ => None,
+    };
+    let src_y = src.trim_start_matches(|c: char| c.is_ascii_lowercase());
+    let src_y = if src_y.is_empty() {
+        None
+    } else {
+        Some(src_y.parse::<u8>().ok()?.checked_sub(1)?)
+    };
+
+    unique(candidates.into_iter().filter(|mv| {
+        !mv.castle
+            && mv.src_piece.as_uppercase() == piece
+            && mv.dst == (dst_l, dst_t, dst_x, dst_y)
+            && src_x.map(|x| x == mv.src.2).unwrap_or(true)
+            && src_y.map(|y| y == mv.src.3).unwrap_or(true)
+            && match (mv.promotion, promotion) {
+                (Some(piece), Some(promotion)) => piece.as_uppercase() == promotion,
+                (Some(piece), None) => game.promotions.first() == Some(&piece.with_color(true)),
+                (None, promotion) => promotion.is_none(),
+            }
+    }))
+}
+
+/**
+    Parses a whitespace-separated list of moves, as played in a single turn by `info.active_player`.
+    Moves are read from the position before the moveset, as they would be when generating movesets.
+**/
+pub fn parse_moveset<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    raw: &str,
+) -> Option<Vec<Move>> {
+    let mut res: Vec<Move> = Vec::new();
+    let mut info = *info;
+    let mut moveset_boards: Vec<Board> = Vec::new();
+
+    for raw_move in raw.split_whitespace() {
+        let mv = parse_move(virtual_boards, &info, raw_move)?;
+        let (new_info, mut new_vboards) =
+            mv.generate_vboards(virtual_boards, &info, &moveset_boards).ok()?;
+        info = new_info;
+        moveset_boards.append(&mut new_vboards);
+        res.push(mv);
+    }
+
+    Some(res)
+}
+
+/// Parses a `(LTt)` prefix, returns the timeline, the turn (starting at 0, or -1 for the "Turn Zero" variant) and what follows
+fn parse_board(raw: &str, even_initial_timelines: bool) -> Option<(i32, isize, &str)> {
+    let raw = raw.strip_prefix('(')?;
+    let end = raw.find(')')?;
+    let mut split = raw[..end].splitn(2, 'T');
+    let l = parse_timeline(split.next()?, even_initial_timelines)?;
+    let t = split.next()?.parse::<isize>().ok()?.checked_sub(1)?;
+    if t < -1 {
+        return None;
+    }
+    Some((l, t, &raw[end + 1..]))
+}
+
+/// Parses the trailing square of `raw` (eg. the `c3` of `Nbc3`); returns what precedes it and the square's coordinates
+fn parse_square_suffix(raw: &str) -> Option<(&str, u8, u8)> {
+    let digits = raw.len() - raw.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 || digits + 1 > raw.len() {
+        return None;
+    }
+    let file_index = raw.len() - digits - 1;
+    if !raw.is_char_boundary(file_index) {
+        return None;
+    }
+    let x = parse_file(raw[file_index..].chars().next()?)?;
+    let y = raw[file_index + 1..].parse::<u8>().ok()?.checked_sub(1)?;
+    Some((&raw[..file_index], x, y))
+}
+
+/// Returns the only element of `iter`, None if it is empty or has more than one element
+fn unique<T, I: Iterator<Item = T>>(mut iter: I) -> Option<T> {
+    let res = iter.next()?;
+    if iter.next().is_some() {
+        None
+    } else {
+        Some(res)
+    }
+}
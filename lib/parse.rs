@@ -1,7 +1,8 @@
-use super::game;
-use serde::Deserialize;
+use super::{error::Error, game, moves::find_present};
+use std::convert::TryFrom;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct GameRaw {
     timelines: Vec<TimelineRaw>,
     width: u8,
@@ -10,7 +11,7 @@ struct GameRaw {
 }
 
 /// Represents an in-game timeline
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct TimelineRaw {
     index: f32,
     states: Vec<Vec<usize>>,
@@ -20,34 +21,29 @@ struct TimelineRaw {
     emerges_from: Option<f32>,
 }
 
-pub fn parse(raw: &str) -> Option<game::Game> {
-    let game_raw: GameRaw = serde_json::from_str(raw).ok()?;
+/**
+    Parses a game from the JSON outputted by [5dchess-notation](https://github.com/adri326/5dchess-notation/).
+    Returns `Error::Parse`, along with the path of the offending value, if the JSON couldn't be read, and `Error::InvalidPiece` if a board contains an unknown piece.
+**/
+pub fn parse(raw: &str) -> Result<game::Game, Error> {
+    let mut deserializer = serde_json::Deserializer::from_str(raw);
+    let game_raw: GameRaw = serde_path_to_error::deserialize(&mut deserializer)?;
+    deserializer.end().map_err(|err| Error::parse("", err.to_string()))?;
 
     let even_initial_timelines = game_raw
         .timelines
         .iter()
         .any(|tl| tl.index == -0.5 || tl.index == 0.5);
 
-    let min_timeline = game_raw.timelines
-        .iter()
-        .map(|tl| tl.index)
-        .min_by_key(|x| (*x) as isize)?;
-    let max_timeline = game_raw.timelines
-        .iter()
-        .map(|tl| tl.index)
-        .max_by_key(|x| (*x) as isize)?;
-
-    let timeline_width = ((-min_timeline).min(max_timeline) + 1.0).round();
-    let active_timelines = game_raw.timelines
-        .iter()
-        .filter(|tl| tl.index.abs() <= timeline_width);
-    let present = active_timelines
-        .map(|tl| tl.begins_at + (tl.states.len() as isize) - 1)
-        .min()?;
+    if game_raw.timelines.is_empty() {
+        return Err(Error::parse("timelines", "expected at least one timeline"));
+    }
+    // Compared as floats, so that `-0.5` and `0.5` don't compare as equal
+    let min_timeline = game_raw.timelines.iter().map(|tl| tl.index).fold(f32::INFINITY, f32::min);
+    let max_timeline = game_raw.timelines.iter().map(|tl| tl.index).fold(f32::NEG_INFINITY, f32::max);
 
     let mut res = game::Game::new(game_raw.width, game_raw.height);
 
-    res.info.present = present;
     res.info.min_timeline = de_l(min_timeline, even_initial_timelines);
     res.info.max_timeline = de_l(max_timeline, even_initial_timelines);
     res.info.active_player = game_raw.active_player;
@@ -56,20 +52,25 @@ pub fn parse(raw: &str) -> Option<game::Game> {
     for tl in game_raw.timelines.into_iter() {
         res.timelines.insert(
             de_l(tl.index, even_initial_timelines),
-            de_timeline(tl, even_initial_timelines),
+            de_timeline(tl, even_initial_timelines)?,
         );
     }
 
-    Some(res)
+    if !res.timelines.keys().any(|&l| res.info.is_timeline_active(l)) {
+        return Err(Error::parse("timelines", "expected at least one active timeline"));
+    }
+    res.info.present = find_present(&res, &res.info);
+    game::populate_moved_flags(&mut res);
+
+    Ok(res)
 }
 
-fn de_board(raw: Vec<usize>, t: isize, l: i32, width: u8, height: u8) -> game::Board {
+fn de_board(raw: Vec<usize>, t: isize, l: i32, width: u8, height: u8) -> Result<game::Board, Error> {
     let mut res = game::Board::new(t, l, width, height);
-    res.pieces = raw
-        .into_iter()
-        .map(|x| game::Piece::from(x))
-        .collect();
-    res
+    for (index, x) in raw.into_iter().enumerate().take(res.n_squares()) {
+        res.set_unsafe((index % width as usize) as u8, (index / width as usize) as u8, game::Piece::try_from(x)?);
+    }
+    Ok(res)
 }
 
 fn de_l(raw: f32, even: bool) -> i32 {
@@ -80,7 +81,7 @@ fn de_l(raw: f32, even: bool) -> i32 {
     }
 }
 
-fn de_timeline(raw: TimelineRaw, even: bool) -> game::Timeline {
+fn de_timeline(raw: TimelineRaw, even: bool) -> Result<game::Timeline, Error> {
     let mut res = game::Timeline::new(
         de_l(raw.index, even),
         raw.width,
@@ -99,7 +100,58 @@ fn de_timeline(raw: TimelineRaw, even: bool) -> game::Timeline {
         .into_iter()
         .enumerate()
         .map(|(i, b)| de_board(b, begins_at + i as isize, index, width, height))
-        .collect();
+        .collect::<Result<_, _>>()?;
+
+    Ok(res)
+}
+
+/// Exports a game into the JSON format read by `parse`; `parse(&export(&game))` yields back `game`
+pub fn export(game: &game::Game) -> String {
+    let even_initial_timelines = game.info.even_initial_timelines;
+    let mut timelines: Vec<&game::Timeline> = game.timelines.values().collect();
+    timelines.sort_by_key(|tl| tl.index);
+
+    let game_raw = GameRaw {
+        timelines: timelines
+            .into_iter()
+            .map(|tl| en_timeline(tl, even_initial_timelines))
+            .collect(),
+        width: game.width,
+        height: game.height,
+        active_player: game.info.active_player,
+    };
+
+    serde_json::to_string(&game_raw).expect("Couldn't serialize game")
+}
+
+fn en_board(board: &game::Board) -> Vec<usize> {
+    board.pieces().map(usize::from).collect()
+}
 
-    res
+/// Inverse of `de_l`: `+0` and `-0` are respectively written as `0.5` and `-0.5`
+fn en_l(l: i32, even: bool) -> f32 {
+    if even {
+        if l == 0 {
+            0.5
+        } else if l == -1 {
+            -0.5
+        } else if l < 0 {
+            (l + 1) as f32
+        } else {
+            l as f32
+        }
+    } else {
+        l as f32
+    }
+}
+
+fn en_timeline(tl: &game::Timeline, even: bool) -> TimelineRaw {
+    TimelineRaw {
+        index: en_l(tl.index, even),
+        states: tl.states.iter().map(en_board).collect(),
+        width: tl.width,
+        height: tl.height,
+        begins_at: tl.begins_at,
+        emerges_from: tl.emerges_from.map(|x| en_l(x, even)),
+    }
 }
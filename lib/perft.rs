@@ -0,0 +1,54 @@
+/*
+    Performance test ("perft"): exhaustively counts the legal movesets up to a given depth, to validate the move generation against known numbers.
+
+    Unlike `legal_movesets`, this does not go through the heuristic `MovesetIter`, which prunes and yields duplicates:
+    movesets are enumerated by `ExhaustiveMovesetIter`, which yields every distinct legal moveset exactly once.
+*/
+
+use super::{game::*, moves::*, moveset::ExhaustiveMovesetIter, vboard::*};
+
+/**
+    Returns the number of legal sequences of `depth` movesets that can be played from `virtual_boards` and `info`.
+    `perft(vboards, info, 0)` is 1.
+**/
+pub fn perft<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo, depth: usize) -> u64 {
+    match depth {
+        0 => 1,
+        1 => count_legal_movesets(virtual_boards, info) as u64,
+        _ => perft_shared(&shared(virtual_boards), info, depth),
+    }
+}
+
+/**
+    Returns, for every legal moveset that can be played from `virtual_boards` and `info`, the number of legal sequences of `depth - 1` movesets that follow it.
+    The sum of these numbers is `perft(virtual_boards, info, depth)`; `depth` must be at least 1.
+**/
+pub fn divide<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    depth: usize,
+) -> Vec<(Vec<Move>, u64)> {
+    let root = shared(virtual_boards);
+    ExhaustiveMovesetIter::new(virtual_boards, info)
+        .map(|(moveset, boards, new_info)| {
+            let count = perft_shared(&root.push(moveset.clone(), boards), &new_info, depth.saturating_sub(1));
+            (moveset, count)
+        })
+        .collect()
+}
+
+/// Recursive bit of `perft`; the boards of each moveset are pushed onto a `SharedVirtualBoardset` instead of being copied
+fn perft_shared(virtual_boards: &SharedVirtualBoardset, info: &GameInfo, depth: usize) -> u64 {
+    if depth <= 1 {
+        return perft(virtual_boards, info, depth);
+    }
+
+    ExhaustiveMovesetIter::new(virtual_boards, info)
+        .map(|(moveset, boards, new_info)| perft_shared(&virtual_boards.push(moveset, boards), &new_info, depth - 1))
+        .sum()
+}
+
+/// Copies the virtual boards of `virtual_boards` into a `SharedVirtualBoardset`
+fn shared<T: VirtualBoardset>(virtual_boards: &T) -> SharedVirtualBoardset<'_> {
+    SharedVirtualBoardset::new(virtual_boards.game(), virtual_boards.virtual_boards().cloned().collect())
+}
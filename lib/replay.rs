@@ -0,0 +1,69 @@
+/*
+    Replaying a game from its list of movesets
+*/
+
+use crate::{error::Error, game::*, moves::*, notation::*};
+
+/// A replayed moveset: its moves, the boards that it created and the game's information once it was played
+pub type ReplayStep = (Vec<Move>, Vec<Board>, GameInfo);
+
+/**
+    Replays `movesets` on top of `game`, one turn per moveset. Each moveset is checked for legality before being committed.
+
+    Returns every step on success. If a moveset is illegal, returns `Error::IllegalMove` with its index and the reason given by `play_moveset`; `game` is then left in the position preceding that moveset.
+**/
+pub fn replay(game: &mut Game, movesets: Vec<Vec<Move>>) -> Result<Vec<ReplayStep>, Error> {
+    let mut res: Vec<ReplayStep> = Vec::with_capacity(movesets.len());
+
+    for (index, moveset) in movesets.into_iter().enumerate() {
+        let step = play_moveset(game, &moveset).map_err(|reason| Error::IllegalMove {
+            index,
+            moveset: format!("{:?}", moveset),
+            reason,
+        })?;
+        res.push(step);
+    }
+
+    Ok(res)
+}
+
+/**
+    Replays movesets written in 5D notation on top of `game`, one turn per string (eg. `"(0T1)Nf3"`, `"(0T4)Nf3>>(0T3)f5"`, `"(0T5)Qd8xd5 (1T4)e6"`).
+    See `replay`; returns `Error::InvalidNotation` if a moveset couldn't be parsed.
+**/
+pub fn replay_notation<'b, I>(game: &mut Game, movesets: I) -> Result<Vec<ReplayStep>, Error>
+where
+    I: IntoIterator<Item = &'b str>,
+{
+    let mut res: Vec<ReplayStep> = Vec::new();
+
+    for (index, raw) in movesets.into_iter().enumerate() {
+        let moveset = parse_moveset(&*game, &game.info, raw).ok_or_else(|| Error::InvalidNotation {
+            index,
+            moveset: String::from(raw),
+        })?;
+        res.push(play_moveset(game, &moveset).map_err(|reason| Error::IllegalMove {
+            index,
+            moveset: String::from(raw),
+            reason,
+        })?);
+    }
+
+    Ok(res)
+}
+
+/**
+    Checks that `moveset` is legal with `validate_moveset` and makes it on `game` (see `Game::make_moveset`).
+    Returns why the moveset is illegal and leaves `game` untouched if it is.
+**/
+pub fn play_moveset(game: &mut Game, moveset: &[Move]) -> Result<ReplayStep, MovesetError> {
+    let (boards, info) = validate_moveset(&*game, &game.info, moveset)?;
+
+    // A board can only collide with an existing one if its predecessor was already played on
+    game.make_moveset(moveset.to_vec(), boards.clone(), info).map_err(|err| match err {
+        Error::BoardCollision(l, t) => MovesetError::AlreadyPlayed((l, t - 1)),
+        _ => MovesetError::NotPlayable((moveset[0].src.0, moveset[0].src.1)),
+    })?;
+
+    Ok((moveset.to_vec(), boards, info))
+}
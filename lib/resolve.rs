@@ -1,6 +1,6 @@
 // Functions around scoring states and moves
 
-use crate::{game::*, moves::*};
+use crate::{bitboard::*, game::*, moves::*, vboard::*};
 
 pub const JUMP_COST: i32 = -4;
 pub const JUMP_INACTIVE_COST: i32 = -24;
@@ -54,38 +54,36 @@ impl<'a> Lore<'a> {
     /**
         Generates a board's "Lore" (danger map and target pieces)
     **/
-    pub fn new<'b, T: Iterator<Item = &'b Board>>(
-        game: &Game,
-        virtual_boards: &Vec<&Board>,
+    pub fn new<'b, T: VirtualBoardset, I: Iterator<Item = &'b Board>>(
+        virtual_boards: &T,
         board: &'a Board,
-        opponent_boards: T,
+        opponent_boards: I,
         _info: &GameInfo,
     ) -> Lore<'a> {
         let mut res = Lore {
             board,
-            danger: vec![0; board.pieces.len()],
+            danger: vec![0; board.n_squares()],
             enemies: Vec::new(),
         };
 
         let mut noop_board = board.clone();
         noop_board.t += 1;
 
-        let mut n_virtual_boards = virtual_boards.clone();
-        n_virtual_boards.push(&noop_board);
+        let n_virtual_boards = OverlayVirtualBoardset::new(virtual_boards, vec![&noop_board]);
 
         for b in opponent_boards {
-            let probables = probable_moves(game, b, &n_virtual_boards);
+            let probables = probable_moves(&n_virtual_boards, b).unwrap_or_default();
             for mv in probables {
-                if mv.dst_piece.is_king() {
+                if mv.dst_piece.is_royal() {
                     res.register_enemy(&mv);
                 }
                 res.register_danger(&mv);
             }
         }
 
-        let probables = probable_moves(game, &noop_board, &n_virtual_boards);
+        let probables = probable_moves(&n_virtual_boards, &noop_board).unwrap_or_default();
         for mv in probables {
-            if mv.dst_piece.is_king() {
+            if mv.dst_piece.is_royal() {
                 res.register_enemy(&mv);
             }
             res.register_danger(&mv);
@@ -113,9 +111,8 @@ impl<'a> Lore<'a> {
     Gives each move in a set of moves (all of which happen on one board) a score and sorts them.
 **/
 #[allow(unused_variables)]
-pub fn score_moves<'a>(
-    game: &Game,
-    virtual_boards: &Vec<&Board>,
+pub fn score_moves<'a, T: VirtualBoardset>(
+    virtual_boards: &T,
     board: &'a Board,
     lore: &Lore<'a>,
     moves: Vec<(Move, GameInfo, Vec<Board>)>,
@@ -126,13 +123,8 @@ pub fn score_moves<'a>(
         .map(|(mv, info, boards)| {
             let mut score: i32 = 0;
 
-            if (mv.src.0 != mv.dst.0 || mv.src.1 != mv.dst.1)
-                && !is_last(
-                    game,
-                    virtual_boards,
-                    get_board(game, virtual_boards, (mv.dst.0, mv.dst.1)).unwrap(),
-                )
-            {
+            let dst_board = virtual_boards.get_board(mv.dst.0, mv.dst.1).unwrap();
+            if (mv.src.0 != mv.dst.0 || mv.src.1 != mv.dst.1) && !virtual_boards.is_last(dst_board) {
                 if if info.active_player {
                     info.max_timeline >= -info.min_timeline + 1
                 } else {
@@ -174,9 +166,8 @@ pub fn score_moves<'a>(
             let mut moves: Vec<Move> = Vec::new();
 
             probable_moves_for(
-                game,
-                get_board(game, virtual_boards, (mv.dst.0, mv.dst.1)).unwrap(),
                 virtual_boards,
+                dst_board,
                 &mut moves,
                 mv.src_piece,
                 mv.dst.2,
@@ -184,7 +175,7 @@ pub fn score_moves<'a>(
             );
 
             for mv in moves {
-                if mv.dst_piece.is_king() {
+                if mv.dst_piece.is_royal() {
                     if mv.src_piece.is_knight() {
                         score += CHECK_KNIGHT_REWARD;
                     } else if mv.src_piece.is_rook() {
@@ -215,9 +206,9 @@ pub fn score_moves<'a>(
 
             for b in &boards {
                 let mut n_kings: usize = 0;
-                for (index, piece) in b.pieces.iter().enumerate() {
-                    if *piece != Piece::Blank && piece.is_white() == board.active_player() {
-                        if piece.is_king() {
+                for (index, piece) in b.pieces().enumerate() {
+                    if piece != Piece::Blank && piece.is_white() == board.active_player() {
+                        if piece.is_royal() {
                             n_kings += 1;
                             score += (lore.danger[index] as i32) * KING_DANGER_COST;
                             if n_kings > 1 {
@@ -249,7 +240,7 @@ pub fn score_moves<'a>(
             (mv, boards, info, score)
         })
         .filter(|(_mv, boards, info, _score)| {
-            is_moveset_legal(game, virtual_boards, info, boards.iter())
+            is_moveset_legal(virtual_boards, info, boards.iter())
         })
         .collect::<Vec<_>>();
     res.sort_unstable_by_key(|(_mv, _boards, _info, score)| -(*score as i32));
@@ -289,11 +280,10 @@ pub const CONTROLLED_SQUARE_SCORE: f32 = 0.025;
 /**
     Checks that `moveset` is legal and gives it a score. The `GameInfo` returned will correspond to that of the submitted move.
 **/
-pub fn score_moveset<'a, T: Iterator<Item = &'a Board>>(
-    game: &Game,
-    virtual_boards: &Vec<&Board>,
+pub fn score_moveset<'a, T: VirtualBoardset, I: Iterator<Item = &'a Board>>(
+    virtual_boards: &T,
     info: &GameInfo,
-    opponent_boards: T,
+    opponent_boards: I,
     moveset: Vec<Move>,
 ) -> Option<(Vec<Move>, Vec<Board>, GameInfo, f32)> {
     let mut moveset_boards: Vec<Board> = Vec::new();
@@ -302,22 +292,18 @@ pub fn score_moveset<'a, T: Iterator<Item = &'a Board>>(
 
     for mv in &moveset {
         let (new_info, mut new_vboards) =
-            mv.generate_vboards(game, &info, &virtual_boards, &moveset_boards)?;
+            mv.generate_vboards(virtual_boards, &info, &moveset_boards).ok()?;
         moveset_boards.append(&mut new_vboards);
         info = new_info;
     }
 
-    let merged_vboards: Vec<&Board> = virtual_boards
-        .iter()
-        .map(|x| *x)
-        .chain(moveset_boards.iter())
-        .collect();
+    let merged_vboards = OverlayVirtualBoardset::new(virtual_boards, moveset_boards.iter().collect());
+    info.present = find_present(&merged_vboards, &info);
 
-    if is_moveset_legal(game, &merged_vboards, &info, moveset_boards.iter())
-        && is_moveset_legal(game, &merged_vboards, &info, opponent_boards)
-        && all_boards_played(game, &merged_vboards, &info)
+    if is_moveset_legal(&merged_vboards, &info, moveset_boards.iter())
+        && is_moveset_legal(&merged_vboards, &info, opponent_boards)
+        && all_boards_played(&merged_vboards, &info)
     {
-        info.present += 1;
         info.active_player = !info.active_player;
 
         let mut score: f32 = 0.0;
@@ -342,21 +328,17 @@ pub fn score_moveset<'a, T: Iterator<Item = &'a Board>>(
             let mut w_kings: usize = 0;
             let mut b_kings: usize = 0;
 
-            let mut controlled_squares_w: Vec<bool> = Vec::with_capacity((board.width * board.height) as usize);
-            let mut controlled_squares_b: Vec<bool> = Vec::with_capacity((board.width * board.height) as usize);
-            for _ in 0..(board.width * board.height) {
-                controlled_squares_w.push(false);
-                controlled_squares_b.push(false);
-            }
+            let mut controlled_squares_w = Bitboard::empty(board.n_squares());
+            let mut controlled_squares_b = Bitboard::empty(board.n_squares());
 
-            for (index, piece) in board.pieces.iter().enumerate() {
+            for (index, piece) in board.pieces().enumerate() {
                 let x = (index % board.width as usize) as u8;
                 let y = (index / board.width as usize) as u8;
                 if piece.is_blank() {
                     continue;
                 }
                 let mult: f32 = if piece.is_white() { 1.0 } else { -1.0 };
-                if piece.is_king() {
+                if piece.is_royal() {
                     if piece.is_white() {
                         w_kings += 1;
                         if w_kings > 1 {
@@ -408,86 +390,22 @@ pub fn score_moveset<'a, T: Iterator<Item = &'a Board>>(
                     score += UNICORN_VALUE * mult * board_mult;
                 } else if piece.is_dragon() {
                     score += DRAGON_VALUE * mult * board_mult;
-                } else if piece.is_pawn() {
+                } else if piece.is_pawn() || piece.is_brawn() {
                     score += PAWN_VALUE * mult * board_mult;
                 } else if piece.is_princess() {
                     score += PRINCESS_VALUE * mult * board_mult;
                 }
 
-                // Maybe replace with bitboard operations
-                // Or just dedupe that horror
+                let attacks = board_attacks(board, piece, x, y);
                 if piece.is_white() {
-                    if piece.is_pawn() {
-                        set_controlled_square(&mut controlled_squares_w, index, 1, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, 1, -1, board.width, board.height);
-                    } else if piece.is_knight() {
-                        set_controlled_square(&mut controlled_squares_w, index, 2, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, 2, -1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, -2, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, -2, -1, board.width, board.height);
-
-                        set_controlled_square(&mut controlled_squares_w, index, 1, 2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, 1, -2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, -1, 2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_w, index, -1, -2, board.width, board.height);
-                    }
-
-                    if piece.is_bishop() || piece.is_queen() || piece.is_princess() {
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, 1, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, -1, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, 1, -1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, -1, -1, board.width, board.height, white);
-                    }
-
-                    if piece.is_rook() || piece.is_queen() || piece.is_princess() {
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, 0, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, 0, -1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, 1, 0, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_w, index, -1, 0, board.width, board.height, white);
-                    }
-                } else if piece.is_black() {
-                    if piece.is_pawn() {
-                        set_controlled_square(&mut controlled_squares_b, index, -1, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, -1, -1, board.width, board.height);
-                    } else if piece.is_knight() {
-                        set_controlled_square(&mut controlled_squares_b, index, 2, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, 2, -1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, -2, 1, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, -2, -1, board.width, board.height);
-
-                        set_controlled_square(&mut controlled_squares_b, index, 1, 2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, 1, -2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, -1, 2, board.width, board.height);
-                        set_controlled_square(&mut controlled_squares_b, index, -1, -2, board.width, board.height);
-                    }
-
-                    if piece.is_bishop() || piece.is_queen() || piece.is_princess() {
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, 1, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, -1, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, 1, -1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, -1, -1, board.width, board.height, white);
-                    }
-
-                    if piece.is_rook() || piece.is_queen() || piece.is_princess() {
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, 0, 1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, 0, -1, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, 1, 0, board.width, board.height, white);
-                        set_controlled_square_slide(board, &mut controlled_squares_b, index, -1, 0, board.width, board.height, white);
-                    }
-                }
-            }
-
-            for controlled_square in controlled_squares_w {
-                if controlled_square {
-                    score += CONTROLLED_SQUARE_SCORE;
+                    controlled_squares_w.or_assign(&attacks.and_not(&board.bitboards.white));
+                } else {
+                    controlled_squares_b.or_assign(&attacks.and_not(&board.bitboards.black));
                 }
             }
 
-            for controlled_square in controlled_squares_b {
-                if controlled_square {
-                    score -= CONTROLLED_SQUARE_SCORE;
-                }
-            }
+            score += CONTROLLED_SQUARE_SCORE * controlled_squares_w.count() as f32;
+            score -= CONTROLLED_SQUARE_SCORE * controlled_squares_b.count() as f32;
 
         }
 
@@ -512,35 +430,3 @@ pub fn score_moveset<'a, T: Iterator<Item = &'a Board>>(
         None
     }
 }
-
-fn set_controlled_square(controlled_squares: &mut Vec<bool>, index: usize, dx: isize, dy: isize, width: u8, height: u8) {
-    if
-        ((index % width as usize) as isize) + dx < 0
-        || ((index % width as usize) as isize) + dx >= width as isize
-        || ((index / width as usize) as isize) + dy < 0
-        || ((index / width as usize) as isize) + dy >= height as isize
-    {
-        return;
-    }
-
-    controlled_squares[((index as isize) + dx + (width as isize) * dy) as usize] = true;
-}
-
-fn set_controlled_square_slide(board: &Board, controlled_squares: &mut Vec<bool>, index: usize, dx: isize, dy: isize, width: u8, height: u8, white: bool) {
-    let mut length = 1;
-
-    while
-        ((index % width as usize) as isize) + length * dx >= 0
-        && ((index % width as usize) as isize) + length * dx < width as isize
-        && ((index / width as usize) as isize) + length * dy >= 0
-        && ((index / width as usize) as isize) + length * dy < height as isize
-    {
-        let n_index = ((index as isize) + length * (dx + (width as isize) * dy)) as usize;
-        if board.pieces[n_index].is_takable_piece(white) {
-            controlled_squares[n_index] = true;
-        } else {
-            break;
-        }
-        length += 1;
-    }
-}
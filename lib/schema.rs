@@ -0,0 +1,149 @@
+/*
+    Serialized form of the core types, as written and read through serde (eg. with `serde_json`).
+    This schema is meant to be stable: fields may be added (with a default value), but existing ones won't be renamed or change meaning.
+
+    - `Piece`: a one-character string, as written by its `Display` implementation: `"."` for a blank square, uppercase letters for white pieces and lowercase letters for black pieces (`"K"`, `"n"`, ...).
+    - `Board`: `{"l", "t", "width", "height", "pieces", "moved", "king_w", "king_b"}`, where `pieces` lists the rows of the board in the format of `variants::board_to_rows`
+      (eg. `"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"`, from `y = height - 1` down to `y = 0`), `moved` lists the `[x, y]` squares whose piece has moved
+      and `king_w`/`king_b` are `[x, y]` or `null`. The zobrist hash isn't written; it is recomputed when reading.
+    - `Timeline`: `{"index", "states", "width", "height", "begins_at", "emerges_from"}`, `states` being its boards from `t = begins_at` onwards.
+    - `GameInfo`: `{"present", "active_player", "min_timeline", "max_timeline", "even_initial_timelines"}`.
+    - `Game`: `{"timelines", "width", "height", "info", "pawn_double_step", "castling", "promotions", "history"}`, `timelines` being an object whose keys are the timelines' indices (`"-1"`, `"0"`, ...);
+      `history` (the `MadeMoveset`s `{"moves", "boards", "new_timelines", "previous_info"}`) may be omitted.
+    - `Move`: `{"src", "dst", "castle", "castle_long", "en_passant", "src_piece", "dst_piece", "promotion", "noop"}`, `src` and `dst` being `[l, t, x, y]` and `en_passant` being `[x, y]` or `null`.
+    - `SearchNode`: `{"moves", "boards", "info", "score"}`, the serialized form of a search result (`tree::Node`); a non-finite score is written as `"inf"`, `"-inf"` or `"nan"`.
+
+    Internal coordinates are used throughout: `l` is the timeline's internal index (see `game::write_timeline`) and `t` counts half-turns, even values being white's boards.
+*/
+
+use super::{error::Error, game::*, moves::Move, variants::*};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        let mut chars = raw.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Piece::from_char(c),
+            _ => None,
+        }
+        .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&raw), &"a piece letter"))
+    }
+}
+
+/// Serialized form of a `Board`; see the module's documentation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BoardRaw {
+    l: i32,
+    t: isize,
+    width: u8,
+    height: u8,
+    pieces: String,
+    #[serde(default)]
+    moved: Vec<(u8, u8)>,
+    #[serde(default)]
+    king_w: Option<(u8, u8)>,
+    #[serde(default)]
+    king_b: Option<(u8, u8)>,
+}
+
+impl From<Board> for BoardRaw {
+    fn from(board: Board) -> Self {
+        let width = board.width as usize;
+        BoardRaw {
+            l: board.l,
+            t: board.t,
+            width: board.width,
+            height: board.height,
+            pieces: board_to_rows(&board),
+            moved: board.moved.iter().map(|i| ((i % width) as u8, (i / width) as u8)).collect(),
+            king_w: board.king_w,
+            king_b: board.king_b,
+        }
+    }
+}
+
+impl TryFrom<BoardRaw> for Board {
+    type Error = Error;
+
+    fn try_from(raw: BoardRaw) -> Result<Self, Error> {
+        let mut board = board_from_rows(&raw.pieces, raw.l, raw.t, raw.width, raw.height)
+            .ok_or_else(|| Error::parse("pieces", format!("invalid board: {}", raw.pieces)))?;
+        for (x, y) in raw.moved {
+            if x >= raw.width || y >= raw.height {
+                return Err(Error::OutOfBounds(x, y));
+            }
+            board.set_moved(x, y, true);
+        }
+        board.king_w = raw.king_w;
+        board.king_b = raw.king_b;
+        Ok(board)
+    }
+}
+
+/// A search result (`tree::Node`: the moveset, the boards it created, the resulting game information and its score), in a form that can be serialized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchNode {
+    pub moves: Vec<Move>,
+    pub boards: Vec<Board>,
+    pub info: GameInfo,
+    #[serde(with = "score")]
+    pub score: f32,
+}
+
+impl From<(Vec<Move>, Vec<Board>, GameInfo, f32)> for SearchNode {
+    fn from((moves, boards, info, score): (Vec<Move>, Vec<Board>, GameInfo, f32)) -> Self {
+        SearchNode {
+            moves,
+            boards,
+            info,
+            score,
+        }
+    }
+}
+
+impl From<SearchNode> for (Vec<Move>, Vec<Board>, GameInfo, f32) {
+    fn from(node: SearchNode) -> Self {
+        (node.moves, node.boards, node.info, node.score)
+    }
+}
+
+/// Writes scores as numbers, or as `"inf"`, `"-inf"` and `"nan"` when they aren't finite (which JSON can't represent)
+mod score {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum ScoreRaw {
+        Finite(f32),
+        Special(String),
+    }
+
+    pub fn serialize<S: Serializer>(score: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        if score.is_finite() {
+            ScoreRaw::Finite(*score)
+        } else {
+            ScoreRaw::Special(score.to_string().to_lowercase())
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        match ScoreRaw::deserialize(deserializer)? {
+            ScoreRaw::Finite(score) => Ok(score),
+            ScoreRaw::Special(raw) => match raw.as_str() {
+                "inf" => Ok(f32::INFINITY),
+                "-inf" => Ok(f32::NEG_INFINITY),
+                "nan" => Ok(f32::NAN),
+                _ => Err(de::Error::invalid_value(de::Unexpected::Str(&raw), &"a number, \"inf\", \"-inf\" or \"nan\"")),
+            },
+        }
+    }
+}
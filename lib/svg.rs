@@ -0,0 +1,252 @@
+/*
+    Rendering of the multiverse as an SVG image, for reports and bug tickets.
+
+    Timelines are laid out as rows (from the highest timeline at the top to the lowest one at the bottom) and half-turns as columns;
+    each board is framed by the color of the player to move on it, inactive timelines are greyed out and the present is drawn as a vertical line.
+    A moveset can be drawn on top of it as arrows, jumps between boards included.
+*/
+
+use super::{game::*, moves::*, vboard::*};
+use std::fmt::Write;
+
+/// Sizes and colors used by `render_svg_with`; `SvgStyle::default()` is used by `render_svg`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgStyle {
+    /// Size of a square, in pixels
+    pub square_size: u32,
+    /// Width of the frame around each board, which shows whose turn it is on it
+    pub frame_size: u32,
+    /// Space between two boards
+    pub gap: u32,
+    pub light_square: &'static str,
+    pub dark_square: &'static str,
+    pub white_frame: &'static str,
+    pub black_frame: &'static str,
+    pub present: &'static str,
+    pub arrow: &'static str,
+    /// Opacity of the boards of inactive timelines
+    pub inactive_opacity: f32,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle {
+            square_size: 16,
+            frame_size: 4,
+            gap: 24,
+            light_square: "#eeeed2",
+            dark_square: "#769656",
+            white_frame: "#f8f8f8",
+            black_frame: "#404040",
+            present: "#a040c0",
+            arrow: "#e06010",
+            inactive_opacity: 0.4,
+        }
+    }
+}
+
+/// Width of the column holding the timelines' labels
+const LABEL_WIDTH: u32 = 48;
+/// Height of the row holding the turns' labels
+const LABEL_HEIGHT: u32 = 24;
+
+/**
+    Renders every board of `virtual_boards` (those of its game included) as an SVG image, with `moveset` drawn as arrows; see the module's documentation for the layout.
+    `info` gives the present and which timelines are active (usually `game.info`, or the information yielded along with `virtual_boards`).
+**/
+pub fn render_svg<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo, moveset: &[Move]) -> String {
+    render_svg_with(virtual_boards, info, moveset, &SvgStyle::default())
+}
+
+/// Renders the multiverse as an SVG image using the sizes and colors of `style`; see `render_svg`
+pub fn render_svg_with<T: VirtualBoardset>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    moveset: &[Move],
+    style: &SvgStyle,
+) -> String {
+    let game = virtual_boards.game();
+    let boards: Vec<&Board> = game
+        .timelines
+        .values()
+        .flat_map(|tl| tl.states.iter())
+        .chain(virtual_boards.virtual_boards())
+        .collect();
+    let min_l = boards.iter().map(|b| b.l).min().unwrap_or(0);
+    let max_l = boards.iter().map(|b| b.l).max().unwrap_or(0);
+    let min_t = boards.iter().map(|b| b.t).min().unwrap_or(0);
+    let max_t = boards.iter().map(|b| b.t).max().unwrap_or(0);
+
+    let layout = Layout {
+        style,
+        width: game.width,
+        height: game.height,
+        max_l,
+        min_t,
+    };
+    let svg_width = layout.board_x(max_t + 1);
+    let svg_height = layout.board_y(min_l - 1);
+
+    let mut res = String::new();
+    writeln!(
+        res,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif">"#,
+        w = svg_width,
+        h = svg_height
+    )
+    .unwrap();
+    writeln!(
+        res,
+        r#"<defs><marker id="arrowhead" markerWidth="6" markerHeight="6" refX="3" refY="3" orient="auto"><path d="M0,0 L6,3 L0,6 z" fill="{}"/></marker></defs>"#,
+        style.arrow
+    )
+    .unwrap();
+
+    // Labels
+    for l in min_l..=max_l {
+        writeln!(
+            res,
+            r#"<text x="{}" y="{}" font-size="12" text-anchor="end" dominant-baseline="middle">L{}</text>"#,
+            LABEL_WIDTH - 8,
+            layout.board_y(l) + layout.board_height() / 2,
+            write_timeline(l, info.even_initial_timelines)
+        )
+        .unwrap();
+    }
+    for t in min_t..=max_t {
+        writeln!(
+            res,
+            r#"<text x="{}" y="{}" font-size="12" text-anchor="middle">T{}{}</text>"#,
+            layout.board_x(t) + layout.board_width() / 2,
+            LABEL_HEIGHT - 8,
+            t / 2 + 1,
+            if t % 2 == 0 { "w" } else { "b" }
+        )
+        .unwrap();
+    }
+
+    for board in boards.iter() {
+        render_board(&mut res, &layout, board, info.is_timeline_active(board.l));
+    }
+
+    // Present
+    let present_x = layout.board_x(info.present) + layout.board_width() / 2;
+    writeln!(
+        res,
+        r#"<line x1="{x}" y1="{}" x2="{x}" y2="{}" stroke="{}" stroke-width="3" stroke-dasharray="8,4" opacity="0.6"/>"#,
+        LABEL_HEIGHT,
+        svg_height,
+        style.present,
+        x = present_x
+    )
+    .unwrap();
+
+    for mv in moveset.iter().filter(|mv| !mv.noop) {
+        let (x1, y1) = layout.square_center(mv.src);
+        let (x2, y2) = layout.square_center(mv.dst);
+        writeln!(
+            res,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="3" stroke-linecap="round" marker-end="url(#arrowhead)"/>"#,
+            x1, y1, x2, y2, style.arrow
+        )
+        .unwrap();
+    }
+
+    res.push_str("</svg>\n");
+    res
+}
+
+/// Position of the boards and squares within the image
+struct Layout<'a> {
+    style: &'a SvgStyle,
+    width: u8,
+    height: u8,
+    max_l: i32,
+    min_t: isize,
+}
+
+impl<'a> Layout<'a> {
+    fn board_width(&self) -> u32 {
+        self.width as u32 * self.style.square_size + 2 * self.style.frame_size
+    }
+
+    fn board_height(&self) -> u32 {
+        self.height as u32 * self.style.square_size + 2 * self.style.frame_size
+    }
+
+    /// Left edge of the boards at `t`
+    fn board_x(&self, t: isize) -> u32 {
+        LABEL_WIDTH + (t - self.min_t).max(0) as u32 * (self.board_width() + self.style.gap)
+    }
+
+    /// Top edge of the boards of timeline `l`
+    fn board_y(&self, l: i32) -> u32 {
+        LABEL_HEIGHT + (self.max_l - l).max(0) as u32 * (self.board_height() + self.style.gap)
+    }
+
+    /// Top-left corner of the square `(x, y)` of the `(l, t)` board; `y = 0` is at the bottom of the board
+    fn square(&self, l: i32, t: isize, x: u8, y: u8) -> (u32, u32) {
+        (
+            self.board_x(t) + self.style.frame_size + x as u32 * self.style.square_size,
+            self.board_y(l) + self.style.frame_size + (self.height - 1 - y) as u32 * self.style.square_size,
+        )
+    }
+
+    fn square_center(&self, (l, t, x, y): (i32, isize, u8, u8)) -> (u32, u32) {
+        let (x0, y0) = self.square(l, t, x, y);
+        (x0 + self.style.square_size / 2, y0 + self.style.square_size / 2)
+    }
+}
+
+/// Writes a board, its frame and its pieces
+fn render_board(res: &mut String, layout: &Layout, board: &Board, active: bool) {
+    let style = layout.style;
+    let white = board.active_player();
+
+    if active {
+        res.push_str("<g>\n");
+    } else {
+        writeln!(res, r#"<g opacity="{}" style="filter: grayscale(1)">"#, style.inactive_opacity).unwrap();
+    }
+    writeln!(
+        res,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="#808080"/>"##,
+        layout.board_x(board.t),
+        layout.board_y(board.l),
+        layout.board_width(),
+        layout.board_height(),
+        if white { style.white_frame } else { style.black_frame }
+    )
+    .unwrap();
+
+    for y in 0..board.height {
+        for x in 0..board.width {
+            let (x0, y0) = layout.square(board.l, board.t, x, y);
+            writeln!(
+                res,
+                r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}"/>"#,
+                x0,
+                y0,
+                if (x + y) % 2 == 0 { style.dark_square } else { style.light_square },
+                s = style.square_size
+            )
+            .unwrap();
+
+            let piece = board.get_unsafe(x, y);
+            if !piece.is_blank() {
+                writeln!(
+                    res,
+                    r#"<text x="{}" y="{}" font-size="{}" font-weight="bold" text-anchor="middle" dominant-baseline="central" fill="{}" stroke="{}" stroke-width="0.5">{}</text>"#,
+                    x0 + style.square_size / 2,
+                    y0 + style.square_size / 2,
+                    style.square_size * 3 / 4,
+                    if piece.is_white() { "#ffffff" } else { "#000000" },
+                    if piece.is_white() { "#000000" } else { "#ffffff" },
+                    piece.to_string().to_uppercase()
+                )
+                .unwrap();
+            }
+        }
+    }
+    res.push_str("</g>\n");
+}
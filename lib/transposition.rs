@@ -0,0 +1,120 @@
+/*
+    Transposition table shared by the tree searches (see `tree`).
+    The same multiverse state can be reached through many permutations of a moveset; the table lets searchers reuse the results of the positions that they already looked at.
+*/
+
+use super::moves::Move;
+use std::mem::size_of;
+use std::sync::Mutex;
+
+/// Depth of the entries of positions in which no moveset can be made (checkmate or draw); their score is exact at any depth
+pub const TERMINAL_DEPTH: usize = usize::MAX;
+
+/// Number of moves assumed for the best moveset of an entry when estimating its size; most movesets only move on one or two boards
+const ESTIMATED_MOVESET_LEN: usize = 2;
+
+/// How the stored score relates to the actual score of a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The actual score is greater than or equal to the stored score (the search failed high)
+    Lower,
+    /// The actual score is lower than or equal to the stored score (the search failed low)
+    Upper,
+}
+
+/// An entry of the transposition table
+#[derive(Debug, Clone)]
+pub struct TTEntry {
+    /// Position hash, as returned by `zobrist::position_hash`
+    pub hash: u64,
+    /// The depth to which the position was searched
+    pub depth: usize,
+    pub bound: Bound,
+    pub score: f32,
+    /// The best moveset found from that position, if any
+    pub best: Option<Vec<Move>>,
+}
+
+/**
+    A thread-safe, fixed-size transposition table. Each slot holds one entry and is locked independently,
+    so that threads working on different positions don't wait on one another.
+
+    When two positions share a slot, the entry searched to the greatest depth is kept (a newer entry for the same position always replaces the older one).
+**/
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Mutex<Option<TTEntry>>>,
+}
+
+impl TranspositionTable {
+    /// Creates a new transposition table that will use roughly `memory` bytes, as estimated by `entry_size` (it will hold at least one entry)
+    pub fn new(memory: usize) -> Self {
+        let n_slots = (memory / Self::entry_size()).max(1);
+
+        let mut slots = Vec::with_capacity(n_slots);
+        slots.resize_with(n_slots, || Mutex::new(None));
+
+        TranspositionTable { slots }
+    }
+
+    /**
+        Returns the estimated size of a filled slot, in bytes: the slot itself, which holds the `Vec<Move>` header of the best moveset inline,
+        plus the moves of that moveset, which live on the heap (assuming a moveset of `ESTIMATED_MOVESET_LEN` moves and no allocator overhead).
+    **/
+    pub fn entry_size() -> usize {
+        size_of::<Mutex<Option<TTEntry>>>() + ESTIMATED_MOVESET_LEN * size_of::<Move>()
+    }
+
+    /// Returns the number of entries that the table can hold
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the entry stored for `hash`, if any
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        match self.slot(hash).lock() {
+            Ok(slot) => slot.as_ref().filter(|entry| entry.hash == hash).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Stores an entry, unless its slot holds an entry of another position searched to a greater depth
+    pub fn store(&self, entry: TTEntry) {
+        if let Ok(mut slot) = self.slot(entry.hash).lock() {
+            let replace = match &*slot {
+                Some(old) => old.hash == entry.hash || old.depth <= entry.depth,
+                None => true,
+            };
+            if replace {
+                *slot = Some(entry);
+            }
+        }
+    }
+
+    /// Empties the table
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            if let Ok(mut slot) = slot.lock() {
+                *slot = None;
+            }
+        }
+    }
+
+    #[inline]
+    fn slot(&self, hash: u64) -> &Mutex<Option<TTEntry>> {
+        &self.slots[(hash % self.slots.len() as u64) as usize]
+    }
+}
+
+/// Returns the bound type of a score found by an αβ search within the `(alpha, beta)` window
+pub fn bound_of(score: f32, alpha: f32, beta: f32) -> Bound {
+    if score <= alpha {
+        Bound::Upper
+    } else if score >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
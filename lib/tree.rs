@@ -1,5 +1,6 @@
-use crate::{game::*, moves::*};
+use crate::{game::*, moves::*, resolve::*, transposition::*, vboard::*, zobrist::*};
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
@@ -9,7 +10,8 @@ use scoped_threadpool::Pool;
 
 // Tree search algorithms
 
-type Node = (Vec<Move>, Vec<Board>, GameInfo, f32);
+/// A search result: the moveset, the boards that it created, the resulting game information and its score; see `schema::SearchNode` for its serialized form
+pub type Node = (Vec<Move>, Vec<Board>, GameInfo, f32);
 
 pub mod dfs {
     use super::*;
@@ -17,10 +19,11 @@ pub mod dfs {
         b-limited αβ-pruned depth-first search
 
         - `depth` is the depth to which the algorithm will look (`d`)
-        - `max_ms` corresponds to the maximum number of probable movesets to consider before admitting that no moveset can be made. Set to 0 for ∞ (not recommended!)
+        - `max_ms` corresponds to the maximum number of probable movesets to consider before falling back to `is_checkmate` to find a legal moveset, if any. Set to 0 for ∞ (not recommended!)
         - `bucket_size` correspond to the number of movesets to score and sort; ignored if `<= max_bf`
         - `max_bf` corresponds to the maximum number of movesets (branching factor, or `b`) to consider per tree node; note that αβ-pruning has a time complexity of `O(b^(d/2))`
         - `n_threads` is the number of threads to run concurrently; they will work on different starting moves to recursively rate them
+        - `table` is the transposition table shared by the threads; it may be reused between searches on the same game
     **/
     pub fn dfs<'a>(
         game: &'a Game,
@@ -29,9 +32,10 @@ pub mod dfs {
         bucket_size: usize,
         max_bf: usize,
         n_threads: u32,
+        table: &TranspositionTable,
     ) -> Option<(Node, f32)> {
-        let virtual_boards: Vec<&Board> = Vec::new();
-        let initial_iter = legal_movesets(&game, &game.info, &virtual_boards, 0, 0).take(max_bf);
+        let root_hash = position_hash(game, &game.info);
+        let initial_iter = legal_movesets(game, &game.info, 0, 0).take(max_bf);
         let mut pool = Pool::new(n_threads);
 
         let res_data = Arc::new(Mutex::new((
@@ -45,7 +49,6 @@ pub mod dfs {
 
         pool.scoped(|scope| {
             for node in initial_iter {
-                let virtual_boards: Vec<&Board> = Vec::new();
                 let info = game.info.clone();
                 let depth = depth;
                 let res_data = Arc::clone(&res_data);
@@ -68,9 +71,9 @@ pub mod dfs {
 
                     if depth > 0 {
                         let (best_branch, new_value) = dfs_rec(
-                            &game,
-                            &virtual_boards,
+                            &SharedVirtualBoardset::new(game, vec![]),
                             node.clone(),
+                            update_position_hash(root_hash, &info, &node.2, &node.1),
                             depth - 1,
                             std::f32::NEG_INFINITY,
                             std::f32::INFINITY,
@@ -78,6 +81,7 @@ pub mod dfs {
                             max_ms,
                             bucket_size,
                             max_bf,
+                            table,
                         );
                         if let Some(best_branch) = best_branch {
                             let mut res: String = format!("1. {:?} -> {}\n", node.0, new_value);
@@ -133,11 +137,11 @@ pub mod dfs {
         }
     }
 
-    /// Recursive bit of `dfs(...)`, see the documentation about `dfs` for more information!
+    /// Recursive bit of `dfs(...)`, see the documentation about `dfs` for more information! `hash` is the position hash of `node`
     fn dfs_rec(
-        game: &Game,
-        virtual_boards: &Vec<&Board>,
+        virtual_boards: &SharedVirtualBoardset,
         node: Node,
+        hash: u64,
         depth: usize,
         mut alpha: f32,
         mut beta: f32,
@@ -145,35 +149,76 @@ pub mod dfs {
         max_ms: usize,
         bucket_size: usize,
         max_bf: usize,
+        table: &TranspositionTable,
     ) -> (Option<Vec<Node>>, f32) {
         // TODO: merge white's and black's code?
         if depth == 0 {
             let s = node.3;
             (None, s)
         } else {
+            let (alpha_orig, beta_orig) = (alpha, beta);
+            let tt_best = match table.probe(hash) {
+                Some(entry) => {
+                    if entry.depth >= depth {
+                        match entry.bound {
+                            Bound::Exact => return (None, entry.score),
+                            Bound::Lower => alpha = alpha.max(entry.score),
+                            Bound::Upper => beta = beta.min(entry.score),
+                        }
+                        if alpha >= beta {
+                            return (None, entry.score);
+                        }
+                    }
+                    entry.best
+                }
+                None => None,
+            };
+
+            let game = virtual_boards.game;
             let mut info = node.2.clone();
             info.active_player = white;
-            let merged_vboards: Vec<&Board> = virtual_boards
-                .iter()
-                .map(|x| *x)
-                .chain(node.1.iter())
-                .collect::<Vec<&Board>>();
-            let movesets = legal_movesets(game, &info, &merged_vboards, 0, max_ms);
-
-            if white { // White:
+            let merged_vboards = virtual_boards.push(node.0.clone(), node.1.clone());
+            let movesets = or_witness(
+                &merged_vboards,
+                &info,
+                tt_first(
+                    &merged_vboards,
+                    &info,
+                    tt_best,
+                    opt_apply_bucket(
+                        bucket_size,
+                        max_bf,
+                        white,
+                        legal_movesets(&merged_vboards, &info, 0, max_ms),
+                    ),
+                ),
+            );
+            let movesets = match movesets {
+                Ok(movesets) => movesets,
+                Err(score) => {
+                    table.store(TTEntry {
+                        hash,
+                        depth: TERMINAL_DEPTH,
+                        bound: Bound::Exact,
+                        score,
+                        best: None,
+                    });
+                    return (None, score);
+                }
+            };
+
+            let (best_move, value) = if white { // White:
                 let mut value = std::f32::NEG_INFINITY;
-                let mut yielded_move = false;
                 let mut best_move: Option<Vec<Node>> = None;
-                for ms in opt_apply_bucket(bucket_size, max_bf, white, movesets) {
+                for ms in movesets {
                     if ms.0.len() > game.timelines.len() * 20 {
                         info!("Abnormally high number of dimensions: {}", ms.0.len());
                         info!("{:?}", ms.0);
                     }
-                    yielded_move = true;
                     let (best_branch, n_value) = dfs_rec(
-                        game,
                         &merged_vboards,
                         ms.clone(),
+                        update_position_hash(hash, &node.2, &ms.2, &ms.1),
                         depth - 1,
                         alpha,
                         beta,
@@ -181,6 +226,7 @@ pub mod dfs {
                         max_ms,
                         bucket_size,
                         max_bf,
+                        table,
                     );
                     if n_value > value {
                         if let Some(mut best_branch) = best_branch {
@@ -199,28 +245,19 @@ pub mod dfs {
                         break;
                     }
                 }
-                if !yielded_move {
-                    // Look for a draw
-                    if is_draw(game, &merged_vboards, &info) {
-                        value = 0.0;
-                    }
-                }
-
                 (best_move, value)
             } else { // Black:
                 let mut value = std::f32::INFINITY;
-                let mut yielded_move = false;
                 let mut best_move: Option<Vec<Node>> = None;
-                for ms in opt_apply_bucket(bucket_size, max_bf, white, movesets) {
+                for ms in movesets {
                     if ms.0.len() > game.timelines.len() * 20 {
                         info!("Abnormally high number of dimensions: {}", ms.0.len());
                         info!("{:?}", ms.0);
                     }
-                    yielded_move = true;
                     let (best_branch, n_value) = dfs_rec(
-                        game,
                         &merged_vboards,
                         ms.clone(),
+                        update_position_hash(hash, &node.2, &ms.2, &ms.1),
                         depth - 1,
                         alpha,
                         beta,
@@ -228,6 +265,7 @@ pub mod dfs {
                         max_ms,
                         bucket_size,
                         max_bf,
+                        table,
                     );
                     if n_value < value {
                         if let Some(mut best_branch) = best_branch {
@@ -246,15 +284,18 @@ pub mod dfs {
                         break;
                     }
                 }
-                if !yielded_move {
-                    // Look for a draw
-                    if is_draw(game, &merged_vboards, &info) {
-                        value = 0.0;
-                    }
-                }
-
                 (best_move, value)
-            }
+            };
+
+            table.store(TTEntry {
+                hash,
+                depth,
+                bound: bound_of(value, alpha_orig, beta_orig),
+                score: value,
+                best: best_move.as_ref().map(|b| b[0].0.clone()),
+            });
+
+            (best_move, value)
         }
     }
 }
@@ -282,6 +323,10 @@ pub mod bfs {
         - `tolerance` is the maximum score difference from the best scoring node that there can be for a branch to not be pruned. If `0`, only the best scoring branches will be kept; they might turn out to not score as well deeper down the tree.
         - `tolerance_mult` is the multiplier for that score difference that will be applied to it should there be more than one consecutive pruning step; it must be lower than 1 (or else this algorithm will loop forever).
         - The `pool_size` option can also be increased to reduce the number of times that the pruning has to be ran. Doing so will, however, increase the memory usage of the program.
+
+        Nodes leading to the same position as one of their siblings are skipped, and `table` is used to remember which positions have no legal moveset.
+        Entries of `table` that were searched deeper than the deepest branch (eg. by `dfs` or `iddfs_bfs`) replace the score of a branch if they are exact, instead of expanding it,
+        or bound it otherwise; the best moveset of an entry is expanded first.
     **/
     pub fn bfs<'a>(
         game: &'a Game,
@@ -294,19 +339,21 @@ pub mod bfs {
         tolerance_mult: f32,
         n_threads: u32,
         max_duration: Duration,
+        table: &TranspositionTable,
     ) -> Option<(Node, f32)> {
         let mut pool = Pool::new(n_threads);
         let mut res = pool.scoped(|scope| {
-            let initial_virtual_boards: Vec<&Board> = Vec::new();
+            let root_hash = position_hash(game, &game.info);
             let results = Arc::new(Mutex::new(Vec::new()));
             for initial_node in
-                legal_movesets(game, &game.info, &initial_virtual_boards, 0, 0).take(initial_movesets)
+                legal_movesets(game, &game.info, 0, 0).take(initial_movesets)
             {
                 let results = Arc::clone(&results);
                 scope.execute(move || {
                     let res = bfs_sub(
                         game,
                         initial_node.clone(),
+                        update_position_hash(root_hash, &game.info, &initial_node.2, &initial_node.1),
                         max_ms,
                         bucket_size,
                         bucket_downsize,
@@ -314,6 +361,7 @@ pub mod bfs {
                         tolerance,
                         tolerance_mult,
                         max_duration,
+                        table,
                     );
                     results.lock().unwrap().push((initial_node, res));
                 });
@@ -339,46 +387,40 @@ pub mod bfs {
     **/
     #[derive(Clone, Debug)]
     pub struct BFSBranch {
-        pub boards: Vec<Board>,
-        pub moves: Vec<Vec<Move>>,
+        /// The movesets played since the root and the boards they created, shared with the parent branch
+        pub history: Multiverse,
         pub info: GameInfo,
         pub depth: usize,
         pub score: f32,
         pub tree: RBFSTree,
+        /// Position hash of the branch (see `zobrist::position_hash`)
+        pub hash: u64,
     }
 
     impl From<(Node, &BFSBranch, RBFSTree)> for BFSBranch {
         fn from(raw: (Node, &BFSBranch, RBFSTree)) -> Self {
+            let hash = update_position_hash(raw.1.hash, &raw.1.info, &(raw.0).2, &(raw.0).1);
             BFSBranch {
-                boards: (raw.1)
-                    .boards
-                    .clone()
-                    .into_iter()
-                    .chain(((raw.0).1).into_iter())
-                    .collect(),
-                moves: (raw.1)
-                    .moves
-                    .clone()
-                    .into_iter()
-                    .chain(vec![(raw.0).0].into_iter())
-                    .collect(),
+                history: (raw.1).history.push((raw.0).0, (raw.0).1),
                 info: (raw.0).2,
                 depth: raw.1.depth + 1,
                 score: (raw.0).3,
                 tree: raw.2,
+                hash,
             }
         }
     }
 
+    /// Converts the branch into the node of its last moveset, whose boards go on top of `history.parent()`
     impl Into<Node> for BFSBranch {
         fn into(self) -> Node {
-            (self.moves.last().unwrap_or(&vec![]).clone(), self.boards, self.info, self.score)
+            (self.history.last_moveset().cloned().unwrap_or_default(), self.history.last_boards().cloned().collect(), self.info, self.score)
         }
     }
 
     impl Into<Node> for &BFSBranch {
         fn into(self) -> Node {
-            (self.moves.last().unwrap_or(&vec![]).clone(), self.boards.clone(), self.info.clone(), self.score)
+            (self.history.last_moveset().cloned().unwrap_or_default(), self.history.last_boards().cloned().collect(), self.info.clone(), self.score)
         }
     }
 
@@ -418,6 +460,7 @@ pub mod bfs {
     fn bfs_sub<'a>(
         game: &'a Game,
         initial_node: Node,
+        initial_hash: u64,
         max_ms: usize,
         bucket_size: usize,
         bucket_downsize: usize,
@@ -425,6 +468,7 @@ pub mod bfs {
         mut tolerance: f32,
         tolerance_mult: f32,
         max_duration: Duration,
+        table: &TranspositionTable,
     ) -> f32 {
         let mut pool: VecDeque<BFSBranch> = VecDeque::with_capacity(pool_size * 2);
         let initial_tree = Rc::new(RefCell::new(BFSTree {
@@ -439,17 +483,20 @@ pub mod bfs {
             pruned: false,
         }));
         pool.push_back(BFSBranch {
-            moves: vec![initial_node.0],
-            boards: initial_node.1,
+            history: Multiverse::new().push(initial_node.0, initial_node.1),
             info: initial_node.2,
             score: initial_node.3,
             depth: 0,
             tree: initial_tree.clone(),
+            hash: initial_hash,
         });
         let begin = Instant::now();
 
         let mut consecutive_prunes: usize = 0;
         let mut n_nodes: usize = 1;
+        // Depth of the deepest branch so far, and number of branches in a row that the transposition table cut off
+        let mut frontier: usize = 0;
+        let mut cut_offs: usize = 0;
 
         while begin.elapsed() < max_duration {
             if pool.len() > pool_size {
@@ -465,10 +512,58 @@ pub mod bfs {
                         pool.push_back(branch);
                         continue;
                     }
-                    let virtual_boards = branch.boards.iter().collect::<Vec<_>>();
-                    let mut movesets = legal_movesets(game, &branch.info, &virtual_boards, 0, max_ms)
-                        .take(bucket_size)
-                        .collect::<Vec<_>>();
+                    // An entry searched past the frontier gives a better score than expanding the branch would;
+                    // the branch is put back, as it will be expanded once the frontier goes past the entry's depth
+                    let tt_best = match table.probe(branch.hash) {
+                        Some(entry) => {
+                            if entry.depth == TERMINAL_DEPTH || entry.depth > 0 && branch.depth + entry.depth > frontier {
+                                match entry.bound {
+                                    Bound::Exact => {
+                                        branch.score = entry.score;
+                                        branch.tree.borrow_mut().score = branch.score;
+                                        pool.push_back(branch);
+                                        cut_offs += 1;
+                                        if pool.len() == 1 || cut_offs >= pool.len() {
+                                            break;
+                                        }
+                                        continue;
+                                    }
+                                    Bound::Lower => branch.score = branch.score.max(entry.score),
+                                    Bound::Upper => branch.score = branch.score.min(entry.score),
+                                }
+                                branch.tree.borrow_mut().score = branch.score;
+                            }
+                            entry.best
+                        }
+                        None => None,
+                    };
+                    cut_offs = 0;
+                    let virtual_boards = SharedVirtualBoardset { game, multiverse: branch.history.clone() };
+                    let info = branch.info;
+                    let movesets = or_witness(
+                        &virtual_boards,
+                        &info,
+                        legal_movesets(&virtual_boards, &info, 0, max_ms).take(bucket_size),
+                    );
+                    let mut movesets = match movesets {
+                        Ok(movesets) => movesets.collect::<Vec<_>>(),
+                        Err(score) => {
+                            branch.score = score;
+                            branch.tree.borrow_mut().score = score;
+                            table.store(TTEntry {
+                                hash: branch.hash,
+                                depth: TERMINAL_DEPTH,
+                                bound: Bound::Exact,
+                                score,
+                                best: None,
+                            });
+                            pool.push_back(branch);
+                            if pool.len() == 1 {
+                                break;
+                            }
+                            continue;
+                        }
+                    };
                     movesets.sort_by(|a, b| {
                         if branch.info.active_player {
                             b.3.partial_cmp(&a.3).unwrap()
@@ -477,30 +572,17 @@ pub mod bfs {
                         }
                     });
 
-                    if movesets.len() > 0 {
-                        for node in movesets.into_iter().take(bucket_downsize) {
-                            n_nodes += 1;
-                            if pool.len() < pool_size * 2 {
-                                if let Some(new_tree) = BFSTree::after(&branch.tree, node.3) {
-                                    pool.push_back(BFSBranch::from((node, &branch, new_tree)));
-                                }
-                            }
+                    let mut seen: HashSet<u64> = HashSet::new();
+                    for node in tt_first(&virtual_boards, &info, tt_best, movesets.into_iter()).take(bucket_downsize) {
+                        if !seen.insert(update_position_hash(branch.hash, &branch.info, &node.2, &node.1)) {
+                            continue;
                         }
-                    } else {
-                        if is_draw(game, &virtual_boards, &branch.info) {
-                            branch.score = 0.0;
-                            branch.tree.borrow_mut().score = branch.score;
-                        } else {
-                            branch.score = if branch.info.active_player {
-                                std::f32::NEG_INFINITY
-                            } else {
-                                std::f32::INFINITY
-                            };
-                            branch.tree.borrow_mut().score = branch.score;
-                        }
-                        pool.push_back(branch);
-                        if pool.len() == 1 {
-                            break;
+                        n_nodes += 1;
+                        if pool.len() < pool_size * 2 {
+                            if let Some(new_tree) = BFSTree::after(&branch.tree, node.3) {
+                                frontier = frontier.max(branch.depth + 1);
+                                pool.push_back(BFSBranch::from((node, &branch, new_tree)));
+                            }
                         }
                     }
                 } else {
@@ -519,8 +601,9 @@ pub mod bfs {
         for (k, mv) in pool
             .pop_front()
             .expect("Expected pool to contain at least one item!")
-            .moves
-            .iter()
+            .history
+            .movesets()
+            .into_iter()
             .enumerate()
         {
             res_str.push_str(format!("{}: {:?}\n", k + 1, mv).as_str());
@@ -631,6 +714,7 @@ pub mod iddfs {
     - `pool_size` is the desired number of tasks to have. The actual number of tasks might exceed that number and is subject to change should some lines be worse than others. Tasks will be properly scheduled among the different threads
     - `n_threads` is the number of threads to run this with
     - `max_duration` is the maximum duration that this algorithm may take; once that maximum duration is reached, the process is stopped as soon as possible and early results, if available, are returned
+    - `table` is the transposition table shared by the DFS searches; it is kept across iterations, which lets deeper iterations try the best moveset of the previous ones first
     **/
     pub fn iddfs_bfs<'a>(
         game: &'a Game,
@@ -639,6 +723,7 @@ pub mod iddfs {
         pool_size: usize,
         n_threads: u32,
         max_duration: Duration,
+        table: &TranspositionTable,
     ) -> Option<(Node, f32)> {
         let queue_fail_threshold = 4;
         let begin = Instant::now();
@@ -652,12 +737,12 @@ pub mod iddfs {
         }));
         let mut initial_nodes: Vec<(Node, Rc<RefCell<BFSTree>>)> = Vec::new();
         queue.push_back(BFSBranch {
-            moves: vec![],
-            boards: vec![],
+            history: Multiverse::new(),
             info: game.info.clone(),
             depth: 0,
             score: 0.0,
             tree: root.clone(),
+            hash: position_hash(game, &game.info),
         });
         let mut depth = 0;
         let mut pool = Pool::new(n_threads);
@@ -668,35 +753,44 @@ pub mod iddfs {
             let mut queue_fails = 0;
             while queue.len() < pool_size {
                 if let Some(mut branch) = queue.pop_front() {
-                    let virtual_boards = branch.boards.iter().collect::<Vec<_>>();
-                    let mut has_looped = false;
-                    for moveset in legal_movesets(game, &branch.info, &virtual_boards, 0, max_ms)
-                        .take(bucket_size)
-                    {
-                        has_looped = true;
-                        let new_tree = BFSTree::after(&branch.tree, moveset.3).unwrap();
-                        if branch.depth == 0 {
-                            initial_nodes.push((moveset.clone(), new_tree.clone()));
-                        }
-                        queue.push_back(BFSBranch::from((moveset, &branch, new_tree)));
-                    }
-                    if !has_looped {
-                        if is_draw(game, &branch.boards.iter().collect(), &branch.info) {
-                            branch.score = 0.0;
-                            branch.tree.borrow_mut().score = 0.0;
-                        } else {
-                            if branch.info.active_player {
-                                branch.score = std::f32::NEG_INFINITY;
-                                branch.tree.borrow_mut().score = std::f32::NEG_INFINITY;
-                            } else {
-                                branch.score = std::f32::INFINITY;
-                                branch.tree.borrow_mut().score = std::f32::INFINITY;
+                    let virtual_boards = SharedVirtualBoardset { game, multiverse: branch.history.clone() };
+                    let info = branch.info;
+                    let movesets = match table.probe(branch.hash).filter(|e| e.depth == TERMINAL_DEPTH) {
+                        Some(entry) => Err(entry.score),
+                        None => or_witness(
+                            &virtual_boards,
+                            &info,
+                            legal_movesets(&virtual_boards, &info, 0, max_ms).take(bucket_size),
+                        ),
+                    };
+                    match movesets {
+                        Ok(movesets) => {
+                            let mut seen: HashSet<u64> = HashSet::new();
+                            for moveset in movesets {
+                                if !seen.insert(update_position_hash(branch.hash, &branch.info, &moveset.2, &moveset.1)) {
+                                    continue;
+                                }
+                                let new_tree = BFSTree::after(&branch.tree, moveset.3).unwrap();
+                                if branch.depth == 0 {
+                                    initial_nodes.push((moveset.clone(), new_tree.clone()));
+                                }
+                                queue.push_back(BFSBranch::from((moveset, &branch, new_tree)));
                             }
+                            queue_fails = 0;
+                        }
+                        Err(score) => {
+                            branch.score = score;
+                            branch.tree.borrow_mut().score = score;
+                            table.store(TTEntry {
+                                hash: branch.hash,
+                                depth: TERMINAL_DEPTH,
+                                bound: Bound::Exact,
+                                score,
+                                best: None,
+                            });
+                            queue_fails += 1;
+                            queue.push_back(branch);
                         }
-                        queue_fails += 1;
-                        queue.push_back(branch);
-                    } else {
-                        queue_fails = 0;
                     }
                 }
                 // println!("> {}", queue.len());
@@ -715,12 +809,14 @@ pub mod iddfs {
                     if node.depth <= depth {
                         let depth = depth - node.depth;
                         let results = Arc::clone(&results);
+                        let hash = node.hash;
+                        let parent = SharedVirtualBoardset { game, multiverse: node.history.parent() };
                         let node: Node = node.into();
                         scope.execute(move || {
                             let res = iddfs_bfs_sub(
-                                game,
-                                &vec![],
+                                &parent,
                                 node,
+                                hash,
                                 max_ms,
                                 bucket_size,
                                 depth,
@@ -728,6 +824,7 @@ pub mod iddfs {
                                 std::f32::INFINITY,
                                 begin,
                                 max_duration,
+                                table,
                             );
                             results.lock().unwrap().push((id, res));
                         });
@@ -781,11 +878,11 @@ pub mod iddfs {
         None
     }
 
-    /// Recursive DFS search with time verification
-    fn iddfs_bfs_sub<'a>(
-        game: &'a Game,
-        virtual_boards: &Vec<&Board>,
+    /// Recursive DFS search with time verification; `hash` is the position hash of `node`
+    fn iddfs_bfs_sub(
+        virtual_boards: &SharedVirtualBoardset,
         node: Node,
+        hash: u64,
         max_ms: usize,
         bucket_size: usize,
         depth: usize,
@@ -793,31 +890,71 @@ pub mod iddfs {
         mut beta: f32,
         begin: Instant,
         max_duration: Duration,
+        table: &TranspositionTable,
     ) -> Option<(Vec<Node>, f32)> {
         if begin.elapsed() >= max_duration {
             return None;
         } else if depth == 0 {
             Some((vec![node.clone()], node.3))
         } else {
-            let merged_vboards: Vec<&Board> = virtual_boards
-                .iter()
-                .map(|x| *x)
-                .chain(node.1.iter())
-                .collect::<Vec<&Board>>();
+            let (alpha_orig, beta_orig) = (alpha, beta);
+            let tt_best = match table.probe(hash) {
+                Some(entry) => {
+                    if entry.depth >= depth {
+                        match entry.bound {
+                            Bound::Exact => return Some((vec![node], entry.score)),
+                            Bound::Lower => alpha = alpha.max(entry.score),
+                            Bound::Upper => beta = beta.min(entry.score),
+                        }
+                        if alpha >= beta {
+                            return Some((vec![node], entry.score));
+                        }
+                    }
+                    entry.best
+                }
+                None => None,
+            };
+
+            let merged_vboards = virtual_boards.push(node.0.clone(), node.1.clone());
             let mut best = (vec![], if node.2.active_player {std::f32::NEG_INFINITY} else {std::f32::INFINITY});
+            let info = node.2;
+            let movesets = or_witness(
+                &merged_vboards,
+                &info,
+                tt_first(
+                    &merged_vboards,
+                    &info,
+                    tt_best,
+                    legal_movesets(&merged_vboards, &info, 0, max_ms).take(bucket_size),
+                ),
+            );
+            let movesets = match movesets {
+                Ok(movesets) => movesets,
+                Err(score) => {
+                    table.store(TTEntry {
+                        hash,
+                        depth: TERMINAL_DEPTH,
+                        bound: Bound::Exact,
+                        score,
+                        best: None,
+                    });
+                    return Some((vec![node], score));
+                }
+            };
             // Loop over the child nodes
-            for moveset in legal_movesets(game, &node.2, &merged_vboards, 0, max_ms).take(bucket_size) {
+            for moveset in movesets {
                 let res = iddfs_bfs_sub(
-                    game,
                     &merged_vboards,
                     moveset.clone(),
+                    update_position_hash(hash, &node.2, &moveset.2, &moveset.1),
                     max_ms,
                     bucket_size,
                     depth - 1,
                     alpha,
                     beta,
                     begin,
-                    max_duration
+                    max_duration,
+                    table,
                 );
 
                 if let None = res {
@@ -843,17 +980,16 @@ pub mod iddfs {
                 }
             }
 
-            if best.0.len() != 0 {
-                let mut v = vec![node];
-                v.append(&mut best.0);
-                Some((v, best.1))
-            } else {
-                if is_draw(game, &merged_vboards, &node.2) {
-                    Some((vec![node.clone()], 0.0))
-                } else {
-                    Some((vec![node.clone()], if node.2.active_player {std::f32::NEG_INFINITY} else {std::f32::INFINITY}))
-                }
-            }
+            table.store(TTEntry {
+                hash,
+                depth,
+                bound: bound_of(best.1, alpha_orig, beta_orig),
+                score: best.1,
+                best: best.0.first().map(|first| first.0.clone()),
+            });
+            let mut v = vec![node];
+            v.append(&mut best.0);
+            Some((v, best.1))
         }
     }
 
@@ -916,6 +1052,67 @@ pub mod iddfs {
     }
 }
 
+/// Yields the best moveset found in the transposition table (if it is still legal) before the movesets of `iter`
+fn tt_first<'a, T: VirtualBoardset, I: Iterator<Item = Node> + 'a>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    tt_best: Option<Vec<Move>>,
+    iter: I,
+) -> Box<dyn Iterator<Item = Node> + 'a> {
+    let first = tt_best
+        .map(|ms| {
+            score_moveset(
+                virtual_boards,
+                info,
+                get_opponent_boards(virtual_boards, info).into_iter(),
+                ms,
+            )
+        })
+        .flatten();
+
+    match first {
+        Some(first) => {
+            let first_moves = first.0.clone();
+            Box::new(std::iter::once(first).chain(iter.filter(move |ms| ms.0 != first_moves)))
+        }
+        None => Box::new(iter),
+    }
+}
+
+/**
+    Yields the movesets of `iter`, or the witness of `is_checkmate` if `iter` is empty: `legal_movesets` only considers a limited number of movesets, so it not yielding any doesn't mean that no legal moveset exists.
+    Returns the exact score of the position instead if the active player is checkmated (`±∞`) or stalemated (`0`).
+**/
+fn or_witness<'a, T: VirtualBoardset, I: Iterator<Item = Node> + 'a>(
+    virtual_boards: &T,
+    info: &GameInfo,
+    iter: I,
+) -> Result<Box<dyn Iterator<Item = Node> + 'a>, f32> {
+    let mut iter = iter.peekable();
+    if iter.peek().is_some() {
+        return Ok(Box::new(iter));
+    }
+
+    match is_checkmate(virtual_boards, info) {
+        MateResult::Playable(moveset, _boards, _info) => {
+            let witness = score_moveset(
+                virtual_boards,
+                info,
+                get_opponent_boards(virtual_boards, info).into_iter(),
+                moveset,
+            )
+            .expect("Expected the witness of is_checkmate to be legal!");
+            Ok(Box::new(std::iter::once(witness)))
+        }
+        MateResult::Checkmate => Err(if info.active_player {
+            std::f32::NEG_INFINITY
+        } else {
+            std::f32::INFINITY
+        }),
+        MateResult::Stalemate => Err(0.0),
+    }
+}
+
 /// Optionally applies the `bucket_size` option to the legal movesets iterator; `bucket_size` will be ignored if it is less than or equal to `max_bf`
 fn opt_apply_bucket<'a, T: Iterator<Item = Node> + 'a>(
     bucket_size: usize,
@@ -0,0 +1,343 @@
+/*
+    Starting positions of the official variants and of custom variants, so that games can be created without a JSON export of a game.
+*/
+
+use super::{error::Error, game::*};
+use serde::{Deserialize, Serialize};
+
+/// The official variants whose starting position can be built by `Game::variant`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Standard,
+    /// Standard, with the queens replaced by princesses
+    Princess,
+    /// Standard, starting with an additional black board at turn 0, which white can travel back to
+    TurnZero,
+    /// Standard, with two initial timelines (`-0` and `+0`)
+    TwoTimelines,
+    /// Standard, with the queen and the queen's knight swapped, so that every pawn is defended
+    DefendedPawn,
+    /// Standard, with black's king and queen swapped
+    HalfReflected,
+    /// 4x6 board
+    Small,
+    /// 4x6 board, without pawns
+    SmallOpen,
+    /// 4x4 board
+    VerySmall,
+    /// 4x4 board, without pawns
+    VerySmallOpen,
+}
+
+impl Variant {
+    /// Returns the board size as `(width, height)`
+    pub fn size(&self) -> (u8, u8) {
+        match self {
+            Variant::Small | Variant::SmallOpen => (4, 6),
+            Variant::VerySmall | Variant::VerySmallOpen => (4, 4),
+            _ => (8, 8),
+        }
+    }
+
+    /// Returns the initial position, with the rows separated by `/` and listed from black's side to white's side (see `board_from_rows`)
+    pub fn rows(&self) -> &'static str {
+        match self {
+            Variant::Standard | Variant::TurnZero | Variant::TwoTimelines => {
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"
+            }
+            Variant::Princess => "rnbskbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBSKBNR",
+            Variant::DefendedPawn => "rqbnkbnr/pppppppp/8/8/8/8/PPPPPPPP/RQBNKBNR",
+            Variant::HalfReflected => "rnbkqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+            Variant::Small => "nbrk/pppp/4/4/PPPP/KRBN",
+            Variant::SmallOpen => "nbrk/4/4/4/4/KRBN",
+            Variant::VerySmall => "nbrk/3p/P3/KRBN",
+            Variant::VerySmallOpen => "nbrk/4/4/KRBN",
+        }
+    }
+
+    /// Returns the description of this variant, which `VariantDescription::build` turns into a `Game`
+    pub fn description(&self) -> VariantDescription {
+        let (width, height) = self.size();
+        let rows = String::from(self.rows());
+
+        let timelines = match self {
+            Variant::TwoTimelines => vec![
+                TimelineDescription {
+                    index: String::from("-0"),
+                    begins_at: 0,
+                    boards: vec![rows.clone()],
+                },
+                TimelineDescription {
+                    index: String::from("+0"),
+                    begins_at: 0,
+                    boards: vec![rows],
+                },
+            ],
+            Variant::TurnZero => vec![TimelineDescription {
+                index: String::from("0"),
+                begins_at: -1,
+                boards: vec![rows.clone(), rows],
+            }],
+            _ => vec![TimelineDescription {
+                index: String::from("0"),
+                begins_at: 0,
+                boards: vec![rows],
+            }],
+        };
+
+        VariantDescription {
+            width,
+            height,
+            timelines,
+            castling: true,
+            pawn_double_step: true,
+            promotions: String::from(match self {
+                Variant::Princess => "QSNRB",
+                _ => "QNRB",
+            }),
+        }
+    }
+}
+
+/**
+    Description of a variant's starting position, as read from a variant file. For instance, in JSON:
+
+    ```json
+    {
+        "width": 4,
+        "height": 4,
+        "timelines": [
+            {"index": "-0", "boards": ["nbrk/3p/P3/KRBN"]},
+            {"index": "+0", "boards": ["nbrk/3p/P3/KRBN"]}
+        ],
+        "castling": false
+    }
+    ```
+
+    The same description can be written in RON (see `from_ron`):
+
+    ```ron
+    (
+        width: 4,
+        height: 4,
+        timelines: [
+            (index: "-0", boards: ["nbrk/3p/P3/KRBN"]),
+            (index: "+0", boards: ["nbrk/3p/P3/KRBN"]),
+        ],
+        castling: false,
+    )
+    ```
+**/
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VariantDescription {
+    pub width: u8,
+    pub height: u8,
+    pub timelines: Vec<TimelineDescription>,
+    /// Whether kings may castle with the rooks on their rank; defaults to `true`
+    #[serde(default = "default_true")]
+    pub castling: bool,
+    /// Whether pawns may move two squares forward from their initial rank; defaults to `true`
+    #[serde(default = "default_true")]
+    pub pawn_double_step: bool,
+    /// The pieces that pawns may promote to, as uppercase letters (eg. `"QNRBSUD"`); royal pieces, kings, pawns and brawns are not allowed. Defaults to `"QNRB"`
+    #[serde(default = "default_promotions")]
+    pub promotions: String,
+}
+
+/// Description of one of the initial timelines of a variant
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TimelineDescription {
+    /// The timeline's index, as displayed in-game (`"0"`, `"-1"`, `"+1"`, ...); `"-0"` and `"+0"` denote even initial timelines
+    pub index: String,
+    /// The time coordinate of the first board; defaults to `0`
+    #[serde(default)]
+    pub begins_at: isize,
+    /// The layout of each board of the timeline, in the format read by `board_from_rows`
+    pub boards: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_promotions() -> String {
+    String::from("QNRB")
+}
+
+impl VariantDescription {
+    /// Reads a variant description from JSON; returns `Error::Parse`, along with the path of the offending value, if it couldn't be read
+    pub fn from_json(raw: &str) -> Result<Self, Error> {
+        let mut deserializer = serde_json::Deserializer::from_str(raw);
+        let res = serde_path_to_error::deserialize(&mut deserializer)?;
+        deserializer.end().map_err(|err| Error::parse("", err.to_string()))?;
+        Ok(res)
+    }
+
+    /// Writes this variant description as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Reads a variant description from RON; returns `Error::Parse`, along with the path of the offending value, if it couldn't be read
+    pub fn from_ron(raw: &str) -> Result<Self, Error> {
+        let mut deserializer = ron::Deserializer::from_str(raw).map_err(|err| Error::parse("", err.to_string()))?;
+        let res = serde_path_to_error::deserialize(&mut deserializer)?;
+        deserializer.end().map_err(|err| Error::parse("", err.to_string()))?;
+        Ok(res)
+    }
+
+    /// Writes this variant description as RON
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string(self).unwrap()
+    }
+
+    /**
+        Builds the game described by this variant, with the moved flags of its pieces populated and the present set to the earliest last board.
+        Returns `Error::Parse`, along with the path of the offending value, if there are no timelines, if a timeline is empty or given twice, if a board's layout is invalid or if a promotion piece is unknown.
+    **/
+    pub fn build(&self) -> Result<Game, Error> {
+        let mut game = Game::new(self.width, self.height);
+        game.pawn_double_step = self.pawn_double_step;
+        game.promotions = parse_promotions(&self.promotions)
+            .ok_or_else(|| Error::parse("promotions", format!("invalid promotion pieces: {}", self.promotions)))?;
+        game.info.even_initial_timelines = self.timelines.iter().any(|tl| tl.index == "-0" || tl.index == "+0");
+
+        for (index, description) in self.timelines.iter().enumerate() {
+            let path = format!("timelines[{}]", index);
+            let l = parse_timeline(&description.index, game.info.even_initial_timelines)
+                .ok_or_else(|| Error::parse(format!("{}.index", path), format!("invalid timeline: {}", description.index)))?;
+            if description.boards.is_empty() {
+                return Err(Error::parse(format!("{}.boards", path), "expected at least one board"));
+            }
+            if game.timelines.contains_key(&l) {
+                return Err(Error::parse(format!("{}.index", path), format!("timeline {} is given twice", description.index)));
+            }
+
+            let mut timeline = Timeline::new(l, self.width, self.height, description.begins_at, None);
+            for (i, rows) in description.boards.iter().enumerate() {
+                let t = description.begins_at + i as isize;
+                timeline.states.push(
+                    board_from_rows(rows, l, t, self.width, self.height)
+                        .ok_or_else(|| Error::parse(format!("{}.boards[{}]", path, i), format!("invalid board: {}", rows)))?,
+                );
+            }
+            game.timelines.insert(l, timeline);
+        }
+
+        let no_timeline = || Error::parse("timelines", "expected at least one timeline");
+        game.info.min_timeline = *game.timelines.keys().min().ok_or_else(no_timeline)?;
+        game.info.max_timeline = *game.timelines.keys().max().ok_or_else(no_timeline)?;
+        game.info.present = game
+            .timelines
+            .values()
+            .map(|tl| tl.begins_at + tl.states.len() as isize - 1)
+            .min()
+            .ok_or_else(no_timeline)?;
+        game.info.active_player = game.info.present.rem_euclid(2) == 0;
+
+        game.castling = self.castling;
+        populate_moved_flags(&mut game);
+
+        Ok(game)
+    }
+}
+
+/// Reads a list of promotion pieces as uppercase letters (eg. `"QNRB"`); returns None if one of them is unknown, royal, a king, a pawn or a brawn
+pub(crate) fn parse_promotions(raw: &str) -> Option<Vec<Piece>> {
+    raw.chars()
+        .map(|c| Piece::from_char(c).filter(|p| p.is_white() && !p.is_pawn() && !p.is_brawn() && !p.is_king() && !p.is_common_king() && !p.is_royal()))
+        .collect()
+}
+
+/// Reads a variant description from JSON and builds its game; see `VariantDescription`
+pub fn load_variant(raw: &str) -> Result<Game, Error> {
+    VariantDescription::from_json(raw)?.build()
+}
+
+/// Reads a variant description from RON and builds its game; see `VariantDescription`
+pub fn load_variant_ron(raw: &str) -> Result<Game, Error> {
+    VariantDescription::from_ron(raw)?.build()
+}
+
+impl Game {
+    /**
+        Creates a new game, set up with the starting position of `variant`. White is to move on every initial board.
+
+        ```
+        let game = Game::variant(Variant::Standard);
+        assert_eq!(game.get(0, 0, 4, 0), Some(Piece::KingW));
+        ```
+    **/
+    pub fn variant(variant: Variant) -> Game {
+        variant.description().build().unwrap()
+    }
+}
+
+/**
+    Builds the `(l, t)` board from a list of rows, separated by `/` and going from `y = height - 1` down to `y = 0`.
+    Pieces are written as by `Piece`'s `Display` implementation and numbers stand for that many blank squares (eg. `"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"`).
+
+    Returns None if a row doesn't have `width` squares (including when its numbers overflow), if there aren't `height` rows or if a piece is unknown.
+**/
+pub fn board_from_rows(rows: &str, l: i32, t: isize, width: u8, height: u8) -> Option<Board> {
+    let mut board = Board::new(t, l, width, height);
+    let rows: Vec<&str> = rows.split('/').collect();
+    if rows.len() != height as usize {
+        return None;
+    }
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let y = height - 1 - index as u8;
+        let mut x: usize = 0;
+        let mut blanks: usize = 0;
+
+        for c in row.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                blanks = blanks.checked_mul(10)?.checked_add(digit as usize)?;
+                continue;
+            }
+            x = x.checked_add(blanks)?;
+            blanks = 0;
+            if x >= width as usize {
+                return None;
+            }
+            board.set_unsafe(x as u8, y, Piece::from_char(c)?);
+            x += 1;
+        }
+
+        if x.checked_add(blanks)? != width as usize {
+            return None;
+        }
+    }
+
+    Some(board)
+}
+
+/// Writes the pieces of `board` as a list of rows, in the format read by `board_from_rows`
+pub fn board_to_rows(board: &Board) -> String {
+    let mut res = String::new();
+
+    for y in (0..board.height).rev() {
+        let mut blanks = 0;
+        for x in 0..board.width {
+            let piece = board.get_unsafe(x, y);
+            if piece.is_blank() {
+                blanks += 1;
+            } else {
+                if blanks > 0 {
+                    res.push_str(&blanks.to_string());
+                    blanks = 0;
+                }
+                res.push_str(&piece.to_string());
+            }
+        }
+        if blanks > 0 {
+            res.push_str(&blanks.to_string());
+        }
+        if y > 0 {
+            res.push('/');
+        }
+    }
+
+    res
+}
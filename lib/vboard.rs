@@ -1,34 +1,53 @@
 use super::game::*;
+use super::moves::Move;
 use std::collections::HashMap;
-
-/// A generic "virtual boardset"; it is used to hold the state of a branch (while searching the move tree) without making unnecessary and expensive copies of `Game`
-/// The way the additional, "virtual" boards (as part of the state of a branch) are stored is implementation-dependent.
-pub trait VirtualBoardset<'a> {
-    /// Crates a new virtual boardset from a game instance and a set of boards
-    fn new(game: &'a Game, boards: Vec<Board>) -> Self;
-
+use std::sync::Arc;
+
+/**
+    A generic "virtual boardset": a game instance along with additional, "virtual" boards.
+    It is used to hold the state of a branch (while searching the move tree) without making unnecessary and expensive copies of `Game`;
+    move generation, check detection and scoring are generic over it.
+    The way the virtual boards are stored is implementation-dependent, but looking up a board should not require going through all of them.
+    `Game` is itself a virtual boardset without any virtual board.
+**/
+pub trait VirtualBoardset {
     /// Returns the board at (l, t) or None if none was found.
     /// If `(l, t)` corresponds to a board within the game instance, then that board should be returned.
-    fn get_board(&'a self, l: i32, t: isize) -> Option<&'a Board>;
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board>;
 
     /// Returns the tile at (l, t) or None if the board isn't found or the tile is out of bounds
-    fn get(&'a self, l: i32, t: isize, x: u8, y: u8) -> Option<Piece> {
+    fn get(&self, l: i32, t: isize, x: u8, y: u8) -> Option<Piece> {
         self.get_board(l, t).map(|b| b.get(x, y)).flatten()
     }
 
     /// Returns an iterator over the virtual boards stored within the virtual boardset
-    fn virtual_boards(&'a self) -> Box<dyn Iterator<Item=&'a Board> + '_>;
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_>;
 
     /// Returns the underlying Game instance
-    fn game(&'a self) -> &'a Game;
+    fn game(&self) -> &Game;
+
+    /// Returns whether or not `board` is the last board of its timeline; as timelines have no gap, this only looks for the board that follows it
+    fn is_last(&self, board: &Board) -> bool {
+        self.get_board(board.l, board.t + 1).is_none()
+    }
+}
+
+impl VirtualBoardset for Game {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
+        Game::get_board(self, l, t)
+    }
+
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
+        Box::new(std::iter::empty())
+    }
 
-    /// Appends a set of boards to a boardset.
-    /// It is expected that `∀l ∀t, (a.get_board(l, t) is Some) => (push(a, ...).get_board(l, t) is Some)` (ie. `a ⊂ push(a, ...)`)
-    fn push(&'a self, boards: Vec<Board>) -> Self;
+    fn game(&self) -> &Game {
+        self
+    }
 }
 
 pub fn empty<'b>(game: &'b Game) -> EmptyVirtualBoardset<'b> {
-    EmptyVirtualBoardset::new(game, vec![])
+    EmptyVirtualBoardset::new(game)
 }
 
 #[derive(Debug, Clone)]
@@ -37,8 +56,9 @@ pub struct SimpleVirtualBoardset<'a> {
     pub virtual_boards: HashMap<(i32, isize), Board>,
 }
 
-impl<'a> VirtualBoardset<'a> for SimpleVirtualBoardset<'a> {
-    fn new(game: &'a Game, boards: Vec<Board>) -> Self {
+impl<'a> SimpleVirtualBoardset<'a> {
+    /// Creates a new virtual boardset from a game instance and a set of boards
+    pub fn new(game: &'a Game, boards: Vec<Board>) -> Self {
         let mut res = Self {
             game,
             virtual_boards: HashMap::with_capacity(boards.len()),
@@ -51,13 +71,8 @@ impl<'a> VirtualBoardset<'a> for SimpleVirtualBoardset<'a> {
         res
     }
 
-    fn get_board(&'a self, l: i32, t: isize) -> Option<&'a Board> {
-        self.game.get_board(l, t).or_else(|| {
-            self.virtual_boards.get(&(l, t))
-        })
-    }
-
-    fn push(&'a self, boards: Vec<Board>) -> Self {
+    /// Returns a copy of this boardset with `boards` appended to it
+    pub fn push(&self, boards: Vec<Board>) -> Self {
         let mut res = self.clone();
         for board in boards.into_iter() {
             res.virtual_boards.insert((board.l, board.t), board);
@@ -65,12 +80,20 @@ impl<'a> VirtualBoardset<'a> for SimpleVirtualBoardset<'a> {
 
         res
     }
+}
 
-    fn game(&'a self) -> &'a Game {
+impl<'a> VirtualBoardset for SimpleVirtualBoardset<'a> {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
+        self.game.get_board(l, t).or_else(|| {
+            self.virtual_boards.get(&(l, t))
+        })
+    }
+
+    fn game(&self) -> &Game {
         self.game
     }
 
-    fn virtual_boards(&'a self) -> Box<dyn Iterator<Item=&'a Board> + '_> {
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
         Box::new(self.virtual_boards.values())
     }
 }
@@ -82,8 +105,9 @@ pub struct RecursiveVirtualBoardset<'a> {
     pub parent: Option<&'a RecursiveVirtualBoardset<'a>>,
 }
 
-impl<'a> VirtualBoardset<'a> for RecursiveVirtualBoardset<'a> {
-    fn new(game: &'a Game, boards: Vec<Board>) -> Self {
+impl<'a> RecursiveVirtualBoardset<'a> {
+    /// Creates a new virtual boardset from a game instance and a set of boards
+    pub fn new(game: &'a Game, boards: Vec<Board>) -> Self {
         let mut res = Self {
             game,
             virtual_boards: HashMap::with_capacity(boards.len()),
@@ -97,13 +121,8 @@ impl<'a> VirtualBoardset<'a> for RecursiveVirtualBoardset<'a> {
         res
     }
 
-    fn get_board(&'a self, l: i32, t: isize) -> Option<&'a Board> {
-        self.game.get_board(l, t).or_else(|| {
-            self.virtual_boards.get(&(l, t))
-        })
-    }
-
-    fn push(&'a self, boards: Vec<Board>) -> Self {
+    /// Returns a new boardset holding `boards`, whose parent is this boardset
+    pub fn push(&'a self, boards: Vec<Board>) -> Self {
         let mut res = RecursiveVirtualBoardset {
             game: self.game,
             virtual_boards: HashMap::with_capacity(boards.len()),
@@ -116,12 +135,21 @@ impl<'a> VirtualBoardset<'a> for RecursiveVirtualBoardset<'a> {
 
         res
     }
+}
 
-    fn game(&'a self) -> &'a Game {
+impl<'a> VirtualBoardset for RecursiveVirtualBoardset<'a> {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
         self.game
+            .get_board(l, t)
+            .or_else(|| self.virtual_boards.get(&(l, t)))
+            .or_else(|| self.parent.and_then(|parent| parent.get_board(l, t)))
     }
 
-    fn virtual_boards(&'a self) -> Box<dyn Iterator<Item=&'a Board> + '_> {
+    fn game(&self) -> &Game {
+        self.game
+    }
+
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
         Box::new(RecursiveVirtualBoardsetIter {
             rvb: Some(self),
             iter: self.virtual_boards.values(),
@@ -159,30 +187,345 @@ impl<'a> Iterator for RecursiveVirtualBoardsetIter<'a> {
     }
 }
 
+/**
+    Persistent history of a branch of the move tree: the movesets played since the root and the boards they created.
+    Each `push` adds a node pointing to the previous one, which is shared (through an `Arc`) rather than copied;
+    pushing is thus O(new boards) and a branch's children share all of their parent's history.
+    Boards are also indexed by `(l, t)` in a persistent hash trie (whose untouched nodes are shared the same way), so that `get_board` doesn't go through the whole history.
+    It is `Send + Sync`, so branches can be handed to worker threads as they are.
+**/
+#[derive(Debug, Clone, Default)]
+pub struct Multiverse {
+    head: Option<Arc<MultiverseNode>>,
+    index: BoardIndex,
+}
+
+#[derive(Debug)]
+struct MultiverseNode {
+    moves: Vec<Move>,
+    boards: Vec<Arc<Board>>,
+    parent: Option<Arc<MultiverseNode>>,
+    depth: usize,
+}
+
+impl Drop for MultiverseNode {
+    /// Unlinks the chain of parents iteratively, so that dropping a deep history doesn't overflow the stack
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(node) = parent {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => parent = node.parent.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl Multiverse {
+    /// Creates an empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new history made of this one followed by the moveset `moves` and the boards it created; `self` is left untouched
+    pub fn push(&self, moves: Vec<Move>, boards: Vec<Board>) -> Self {
+        let boards: Vec<Arc<Board>> = boards.into_iter().map(Arc::new).collect();
+        let mut index = self.index.clone();
+        for board in boards.iter() {
+            index = index.insert(board.clone());
+        }
+        Self {
+            head: Some(Arc::new(MultiverseNode {
+                moves,
+                boards,
+                parent: self.head.clone(),
+                depth: self.depth() + 1,
+            })),
+            index,
+        }
+    }
+
+    /**
+        Returns the history without its last moveset and the boards it created; the empty history is its own parent.
+        The last moveset's boards are expected to be new positions: a board it replaced isn't brought back into the index.
+    **/
+    pub fn parent(&self) -> Self {
+        match &self.head {
+            Some(node) => {
+                let mut index = self.index.clone();
+                for board in node.boards.iter() {
+                    index = index.remove(board.l, board.t);
+                }
+                Self {
+                    head: node.parent.clone(),
+                    index,
+                }
+            }
+            None => Self::new(),
+        }
+    }
+
+    /// Returns the number of movesets pushed since the empty history
+    pub fn depth(&self) -> usize {
+        self.head.as_ref().map(|node| node.depth).unwrap_or(0)
+    }
+
+    /// Iterates over the nodes, from the most recent one to the oldest one
+    fn nodes(&self) -> impl Iterator<Item = &MultiverseNode> {
+        std::iter::successors(self.head.as_deref(), |node| node.parent.as_deref())
+    }
+
+    /// Returns the board at `(l, t)` among the boards of the history, None if none was found
+    pub fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
+        self.index.get(l, t)
+    }
+
+    /// Returns the boards of the history, in the order they were pushed
+    pub fn boards(&self) -> Vec<&Board> {
+        let mut nodes: Vec<&MultiverseNode> = self.nodes().collect();
+        nodes.reverse();
+        nodes.into_iter().flat_map(|node| node.boards.iter().map(|b| &**b)).collect()
+    }
+
+    /// Iterates over the boards of the history without collecting them, from the most recent moveset to the oldest one
+    pub fn iter_boards(&self) -> impl Iterator<Item = &Board> {
+        self.nodes().flat_map(|node| node.boards.iter().map(|b| &**b))
+    }
+
+    /// Iterates over the boards created by the last moveset pushed (none if the history is empty)
+    pub fn last_boards(&self) -> impl Iterator<Item = &Board> {
+        self.head.iter().flat_map(|node| node.boards.iter().map(|b| &**b))
+    }
+
+    /// Returns the movesets of the history, in the order they were pushed
+    pub fn movesets(&self) -> Vec<&Vec<Move>> {
+        let mut res: Vec<&Vec<Move>> = self.nodes().map(|node| &node.moves).collect();
+        res.reverse();
+        res
+    }
+
+    /// Returns the last moveset pushed, None if the history is empty
+    pub fn last_moveset(&self) -> Option<&Vec<Move>> {
+        self.head.as_ref().map(|node| &node.moves)
+    }
+}
+
+/// Number of bits of the key consumed by each level of `BoardIndex`
+const INDEX_BITS: u32 = 4;
+const INDEX_WIDTH: usize = 1 << INDEX_BITS;
+
+/**
+    Persistent map from `(l, t)` to boards, used by `Multiverse`: a hash trie whose levels each consume `INDEX_BITS` bits of the key's hash.
+    Inserting or removing copies the O(log n) nodes on the path to the key and shares the rest with the previous version.
+**/
+#[derive(Debug, Clone, Default)]
+struct BoardIndex {
+    root: Option<Arc<IndexNode>>,
+}
+
+#[derive(Debug)]
+enum IndexNode {
+    Branch([Option<Arc<IndexNode>>; INDEX_WIDTH]),
+    /// The boards whose key has the hash `.0`
+    Leaf(u64, Vec<Arc<Board>>),
+}
+
+impl BoardIndex {
+    fn key_hash(l: i32, t: isize) -> u64 {
+        // splitmix64's finalizer, so that neighbouring boards spread over the trie
+        let mut z = ((l as u32 as u64) << 32 ^ t as u64).wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn slot(hash: u64, shift: u32) -> usize {
+        ((hash >> shift) as usize) & (INDEX_WIDTH - 1)
+    }
+
+    fn get(&self, l: i32, t: isize) -> Option<&Board> {
+        let hash = Self::key_hash(l, t);
+        let mut shift = 0;
+        let mut node = self.root.as_deref()?;
+        loop {
+            match node {
+                IndexNode::Branch(children) => {
+                    node = children[Self::slot(hash, shift)].as_deref()?;
+                    shift += INDEX_BITS;
+                }
+                IndexNode::Leaf(h, boards) => {
+                    return if *h == hash {
+                        boards.iter().find(|b| b.l == l && b.t == t).map(|b| &**b)
+                    } else {
+                        None
+                    };
+                }
+            }
+        }
+    }
+
+    /// Returns a new index holding `board` in addition to (or in place of the board at the same `(l, t)` of) this one's
+    fn insert(&self, board: Arc<Board>) -> Self {
+        let hash = Self::key_hash(board.l, board.t);
+        Self {
+            root: Some(Self::insert_rec(self.root.as_ref(), hash, 0, board)),
+        }
+    }
+
+    fn insert_rec(node: Option<&Arc<IndexNode>>, hash: u64, shift: u32, board: Arc<Board>) -> Arc<IndexNode> {
+        match node.map(|n| &**n) {
+            None => Arc::new(IndexNode::Leaf(hash, vec![board])),
+            Some(IndexNode::Leaf(h, boards)) if *h == hash => {
+                let mut boards: Vec<Arc<Board>> =
+                    boards.iter().filter(|b| b.l != board.l || b.t != board.t).cloned().collect();
+                boards.push(board);
+                Arc::new(IndexNode::Leaf(hash, boards))
+            }
+            Some(IndexNode::Leaf(h, _)) => {
+                // Two different hashes always part ways before the end of the key
+                let mut children: [Option<Arc<IndexNode>>; INDEX_WIDTH] = Default::default();
+                children[Self::slot(*h, shift)] = node.cloned();
+                let branch = Arc::new(IndexNode::Branch(children));
+                Self::insert_rec(Some(&branch), hash, shift, board)
+            }
+            Some(IndexNode::Branch(children)) => {
+                let mut children = children.clone();
+                let i = Self::slot(hash, shift);
+                children[i] = Some(Self::insert_rec(children[i].as_ref(), hash, shift + INDEX_BITS, board));
+                Arc::new(IndexNode::Branch(children))
+            }
+        }
+    }
+
+    /// Returns a new index without the board at `(l, t)`; empty branches are kept, as they are rare and harmless
+    fn remove(&self, l: i32, t: isize) -> Self {
+        let hash = Self::key_hash(l, t);
+        Self {
+            root: Self::remove_rec(self.root.as_ref(), hash, 0, l, t),
+        }
+    }
+
+    fn remove_rec(node: Option<&Arc<IndexNode>>, hash: u64, shift: u32, l: i32, t: isize) -> Option<Arc<IndexNode>> {
+        match node.map(|n| &**n) {
+            None => None,
+            Some(IndexNode::Leaf(h, boards)) if *h == hash => {
+                let boards: Vec<Arc<Board>> = boards.iter().filter(|b| b.l != l || b.t != t).cloned().collect();
+                if boards.is_empty() {
+                    None
+                } else {
+                    Some(Arc::new(IndexNode::Leaf(hash, boards)))
+                }
+            }
+            Some(IndexNode::Leaf(_, _)) => node.cloned(),
+            Some(IndexNode::Branch(children)) => {
+                let mut children = children.clone();
+                let i = Self::slot(hash, shift);
+                children[i] = Self::remove_rec(children[i].as_ref(), hash, shift + INDEX_BITS, l, t);
+                Some(Arc::new(IndexNode::Branch(children)))
+            }
+        }
+    }
+}
+
+/// A virtual boardset backed by a `Multiverse`: pushing boards shares the parent's boards instead of copying them
+#[derive(Debug, Clone)]
+pub struct SharedVirtualBoardset<'a> {
+    pub game: &'a Game,
+    pub multiverse: Multiverse,
+}
+
+impl<'a> SharedVirtualBoardset<'a> {
+    /// Creates a new virtual boardset from a game instance and a set of boards
+    pub fn new(game: &'a Game, boards: Vec<Board>) -> Self {
+        let multiverse = if boards.is_empty() {
+            Multiverse::new()
+        } else {
+            Multiverse::new().push(vec![], boards)
+        };
+        Self { game, multiverse }
+    }
+
+    /// Returns a new boardset made of this one, followed by the moveset `moves` and the boards it created; `self` is left untouched
+    pub fn push(&self, moves: Vec<Move>, boards: Vec<Board>) -> Self {
+        Self {
+            game: self.game,
+            multiverse: self.multiverse.push(moves, boards),
+        }
+    }
+}
+
+impl<'a> VirtualBoardset for SharedVirtualBoardset<'a> {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
+        self.game.get_board(l, t).or_else(|| {
+            self.multiverse.get_board(l, t)
+        })
+    }
+
+    fn game(&self) -> &Game {
+        self.game
+    }
+
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
+        Box::new(self.multiverse.iter_boards())
+    }
+}
+
 pub struct EmptyVirtualBoardset<'a>(&'a Game);
 
-impl<'a> VirtualBoardset<'a> for EmptyVirtualBoardset<'a> {
-    fn new(game: &'a Game, _boards: Vec<Board>) -> Self {
+impl<'a> EmptyVirtualBoardset<'a> {
+    /// Creates a new virtual boardset holding no virtual board
+    pub fn new(game: &'a Game) -> Self {
         Self(game)
     }
+}
 
-    fn get_board(&'a self, l: i32, t: isize) -> Option<&'a Board> {
+impl<'a> VirtualBoardset for EmptyVirtualBoardset<'a> {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
         self.0.get_board(l, t)
     }
 
-    fn push(&'a self, _boards: Vec<Board>) -> Self {
-        Self(self.0)
-    }
-
-    fn game(&'a self) -> &'a Game {
+    fn game(&self) -> &Game {
         self.0
     }
 
-    fn virtual_boards(&'a self) -> Box<dyn Iterator<Item=&'a Board> + '_> {
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
         Box::new(std::iter::empty())
     }
 }
 
+/**
+    A virtual boardset made of another one and of a few borrowed boards, usually those created by the moveset being played.
+    The borrowed boards are looked up one by one, which is cheaper than hashing them when there are only a handful of them;
+    as it only borrows its boards, it is meant to be built on the fly and thrown away.
+**/
+pub struct OverlayVirtualBoardset<'b, T> {
+    pub base: &'b T,
+    pub boards: Vec<&'b Board>,
+}
+
+impl<'b, T: VirtualBoardset> OverlayVirtualBoardset<'b, T> {
+    /// Creates a new virtual boardset made of the boards of `base` and of `boards`
+    pub fn new(base: &'b T, boards: Vec<&'b Board>) -> Self {
+        Self { base, boards }
+    }
+}
+
+impl<'b, T: VirtualBoardset> VirtualBoardset for OverlayVirtualBoardset<'b, T> {
+    fn get_board(&self, l: i32, t: isize) -> Option<&Board> {
+        self.base.get_board(l, t).or_else(|| {
+            self.boards.iter().copied().find(|b| b.l == l && b.t == t)
+        })
+    }
+
+    fn game(&self) -> &Game {
+        self.base.game()
+    }
+
+    fn virtual_boards(&self) -> Box<dyn Iterator<Item=&Board> + '_> {
+        Box::new(self.base.virtual_boards().chain(self.boards.iter().copied()))
+    }
+}
+
 impl<'a> From<EmptyVirtualBoardset<'a>> for SimpleVirtualBoardset<'a> {
     fn from(empty: EmptyVirtualBoardset<'a>) -> Self {
         Self::new(empty.0, vec![])
@@ -226,3 +569,12 @@ impl<'a> From<&'a SimpleVirtualBoardset<'a>> for RecursiveVirtualBoardset<'a> {
         }
     }
 }
+
+impl<'a> From<EmptyVirtualBoardset<'a>> for SharedVirtualBoardset<'a> {
+    fn from(empty: EmptyVirtualBoardset<'a>) -> Self {
+        Self {
+            game: empty.0,
+            multiverse: Multiverse::new(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+/*
+    Zobrist hashing of boards and of whole multiverse positions, used to detect transpositions while searching.
+
+    Every `(l, t, x, y, piece)` tuple has a pseudo-random key; a position's hash is the XOR of the keys of all of its pieces, combined with the key of its `GameInfo`.
+    Every `(l, t, x, y)` square whose piece has moved also has a key, so that positions which only differ by their castling or double-step rights are told apart.
+    Keys are split in two halves so that they can be kept up to date cheaply:
+
+    - `Board::hash` holds the XOR of the `(x, y, piece)` and moved `(x, y)` keys of a board and is updated by `Board::set` and `Board::set_moved` (and thus by `Move::generate_vboards`)
+    - `board_key` mixes that value with the board's `(l, t)` coordinates
+*/
+
+use super::{game::*, vboard::*};
+
+/// Finalizer of the SplitMix64 generator; used to derive the keys from their coordinates
+#[inline]
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the key of `piece` on the `(x, y)` square of any board; blank squares have a key of `0`
+#[inline]
+pub fn piece_key(x: u8, y: u8, piece: Piece) -> u64 {
+    if piece.is_blank() {
+        0
+    } else {
+        mix(((usize::from(piece) as u64) << 16) | ((y as u64) << 8) | x as u64)
+    }
+}
+
+/// Returns the key of the `(x, y)` square of any board when its piece has moved
+#[inline]
+pub fn moved_key(x: u8, y: u8) -> u64 {
+    mix((1 << 40) | ((y as u64) << 8) | x as u64)
+}
+
+/// Returns the hash of the content of `board`, regardless of its `(l, t)` coordinates; `board.hash` should be equal to this value
+pub fn content_hash(board: &Board) -> u64 {
+    let mut res = 0;
+    for y in 0..board.height {
+        for x in 0..board.width {
+            res ^= piece_key(x, y, board.get_unsafe(x, y));
+            if board.has_moved(x, y) {
+                res ^= moved_key(x, y);
+            }
+        }
+    }
+    res
+}
+
+/// Returns the key of `board` as part of a multiverse position, which accounts for its `(l, t)` coordinates
+#[inline]
+pub fn board_key(board: &Board) -> u64 {
+    mix(board.hash ^ mix(((board.l as u32 as u64) << 32) | (board.t as u32 as u64)))
+}
+
+/// Returns the key of the `GameInfo` fields that are relevant to a position (present, active player and timeline bounds)
+pub fn info_key(info: &GameInfo) -> u64 {
+    mix(info.present as u64)
+        ^ mix(((info.min_timeline as u32 as u64) << 32) | (info.max_timeline as u32 as u64)).rotate_left(17)
+        ^ if info.active_player { 0x5A5A_5A5A_5A5A_5A5A } else { 0 }
+}
+
+/// Returns the XOR of the keys of a set of boards
+pub fn boards_hash<'a, I: Iterator<Item = &'a Board>>(boards: I) -> u64 {
+    boards.fold(0, |acc, board| acc ^ board_key(board))
+}
+
+/// Returns the XOR of the keys of every board in `game`; does not include `game.info`
+pub fn game_hash(game: &Game) -> u64 {
+    boards_hash(game.timelines.values().flat_map(|tl| tl.states.iter()))
+}
+
+/**
+    Returns the hash of the position made of `virtual_boards` (the boards of its game included) and `info`.
+    Two movesets that only differ by the order of non-interacting moves lead to the same hash.
+**/
+pub fn position_hash<T: VirtualBoardset>(virtual_boards: &T, info: &GameInfo) -> u64 {
+    game_hash(virtual_boards.game()) ^ boards_hash(virtual_boards.virtual_boards()) ^ info_key(info)
+}
+
+/**
+    Incrementally updates a position hash (as returned by `position_hash`) once a moveset was played:
+    `new_boards` are the boards created by the moveset and the information goes from `old_info` to `new_info`.
+**/
+pub fn update_position_hash(
+    hash: u64,
+    old_info: &GameInfo,
+    new_info: &GameInfo,
+    new_boards: &[Board],
+) -> u64 {
+    hash ^ info_key(old_info) ^ info_key(new_info) ^ boards_hash(new_boards.iter())
+}
@@ -1,17 +1,52 @@
 #[allow(unused_imports)]
-use chess5dlib::{game::*, moves::*, moveset::*, resolve::*, tree::*, parse::parse};
+use chess5dlib::{game::*, moves::*, moveset::*, resolve::*, tree::*, parse::parse, transposition::*, perft::divide, notation::write_moveset, replay::replay_notation, svg::render_svg};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 extern crate json;
 
-// TODO: move replay, game analysis, args
+// TODO: move game analysis, args
+
+/// Default memory budget of the transposition table, in megabytes; it can be changed with the `TABLE_SIZE` environment variable
+const DEFAULT_TABLE_SIZE: usize = 64;
 
 fn main() -> std::io::Result<()> {
     env_logger::builder()
         .format_timestamp(None)
         .init();
 
+    // `chess5dtools perft <depth> <path>` counts the legal movesets to `depth`, broken down by first moveset
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 4 && args[1] == "perft" {
+        let res = match args[2].parse::<usize>() {
+            Ok(depth) if depth > 0 => perft(depth, &args[3]),
+            _ => Err(format!("Invalid depth: {} (expected a positive integer)", args[2])),
+        };
+        if let Err(message) = res {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // `chess5dtools replay <path> <moveset>...` plays the movesets, written in 5D notation, on the game at `path` and prints the resulting position as 5D FEN
+    if args.len() >= 3 && args[1] == "replay" {
+        if let Err(message) = replay(&args[2], args[3..].iter().map(String::as_str)) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // `chess5dtools svg <path>` prints the game at `path` as an SVG image
+    if args.len() == 3 && args[1] == "svg" {
+        if let Err(message) = svg(&args[2]) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // This is a simple example which will take the 40 most promising movesets, sort them by their score and display the 3 best movesets
     let path = env::args().last().unwrap();
 
@@ -22,10 +57,8 @@ fn main() -> std::io::Result<()> {
 
     let mut game = parse(&contents).expect("Couldn't parse JSON");
 
-    let virtual_boards: Vec<&Board> = vec![];
-
     println!("Boards:");
-    let own_boards = get_own_boards(&game, &virtual_boards, &game.info);
+    let own_boards = get_own_boards(&game, &game.info);
     for b in own_boards {
         println!("{}", b);
         println!("({}T{}{}) - {}/{}\n", write_timeline(b.l, game.info.even_initial_timelines), b.t / 2 + 1, if b.active_player() {"w"} else {"b"}, b.l, b.t);
@@ -57,7 +90,14 @@ fn main() -> std::io::Result<()> {
         game.info.present
     );
     println!("Candidates:");
-    // let best_move = dfs::dfs(&game, 3, 10000, 64, 256, 16);
+    let table = match table_size() {
+        Ok(size) => TranspositionTable::new(size),
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+    // let best_move = dfs::dfs(&game, 3, 10000, 64, 256, 16, &table);
     // let best_move = bfs::bfs(
     //     &game,
     //     10000,
@@ -69,6 +109,7 @@ fn main() -> std::io::Result<()> {
     //     0.95,
     //     16,
     //     std::time::Duration::new(60 * 4, 0),
+    //     &table,
     // );
     let best_move = iddfs::iddfs_bfs(
         &game,
@@ -77,6 +118,7 @@ fn main() -> std::io::Result<()> {
         1024,
         16,
         std::time::Duration::new(5, 0),
+        &table,
     );
     if let Some((best, value)) = best_move {
         println!("Best move:");
@@ -85,13 +127,12 @@ fn main() -> std::io::Result<()> {
             println!("{}", b);
             println!("({}T{}{})\n", write_timeline(b.l, game.info.even_initial_timelines), b.t / 2 + 1, if b.active_player() {"w"} else {"b"});
         }
-        game.commit_moves(best.1);
-        game.info = best.2;
+        game.make_moveset(best.0, best.1, best.2).expect("Couldn't make the best moveset");
     } else {
-        if is_draw(&game, &virtual_boards, &game.info) {
-            println!("Draw!");
-        } else {
-            println!("Checkmate! {} wins!", if game.info.active_player {"Black"} else {"White"});
+        match is_checkmate(&game, &game.info) {
+            MateResult::Stalemate => println!("Draw!"),
+            MateResult::Checkmate => println!("Checkmate! {} wins!", if game.info.active_player {"Black"} else {"White"}),
+            MateResult::Playable(moveset, _, _) => println!("No moveset was found by the search, but {:?} can be played", moveset),
         }
         // break;
     }
@@ -118,3 +159,63 @@ fn main() -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// Prints the game at `path` as an SVG image, with the timelines as rows and the turns as columns; returns why if the game couldn't be read
+fn svg(path: &str) -> Result<(), String> {
+    let game = read_game(path)?;
+    print!("{}", render_svg(&game, &game.info, &[]));
+
+    Ok(())
+}
+
+/// Reads the game at `path`; returns why if it couldn't be read or parsed
+fn read_game(path: &str) -> Result<Game, String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+
+    parse(&contents).map_err(|err| format!("Couldn't parse {}: {}", path, err))
+}
+
+/// Prints the number of legal movesets to `depth` that follow each legal moveset of the game at `path`, and their sum; returns why if the game couldn't be read
+fn perft(depth: usize, path: &str) -> Result<(), String> {
+    let game = read_game(path)?;
+
+    let mut total: u64 = 0;
+    for (moveset, count) in divide(&game, &game.info, depth) {
+        let written = write_moveset(&game, &game.info, &moveset).unwrap_or_else(|| format!("{:?}", moveset));
+        println!("{}: {}", written, count);
+        total += count;
+    }
+    println!("\nTotal: {}", total);
+
+    Ok(())
+}
+
+/// Replays `movesets` on the game at `path`, printing the present after each of them and the final position as 5D FEN; returns why if a moveset couldn't be played
+fn replay<'a, I: IntoIterator<Item = &'a str>>(path: &str, movesets: I) -> Result<(), String> {
+    let mut game = read_game(path)?;
+    let movesets: Vec<&str> = movesets.into_iter().collect();
+
+    let steps = replay_notation(&mut game, movesets.iter().copied()).map_err(|err| err.to_string())?;
+    for (raw, (_moves, _boards, info)) in movesets.iter().zip(steps.iter()) {
+        println!("{}: turn {}, {} to play", raw, info.present / 2 + 1, if info.active_player { "white" } else { "black" });
+    }
+    println!("{}", game.to_fen());
+
+    Ok(())
+}
+
+/// Reads the memory budget of the transposition table, in bytes, from the `TABLE_SIZE` environment variable (in megabytes)
+fn table_size() -> Result<usize, String> {
+    let megabytes = match env::var("TABLE_SIZE") {
+        Ok(raw) => raw
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid TABLE_SIZE: {} (expected a size in megabytes)", raw))?,
+        Err(_) => DEFAULT_TABLE_SIZE,
+    };
+    megabytes
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| format!("Invalid TABLE_SIZE: {} (too large)", megabytes))
+}
@@ -0,0 +1,32 @@
+use chess5dlib::{bitboard::*, game::*};
+
+#[test]
+fn test_wide_board() {
+    let mut board = Board::new(0, 0, 10, 10);
+    assert!(matches!(board.moved, Bitboard::Wide(_)));
+
+    board.set(9, 9, Piece::QueenB).unwrap();
+    board.set(3, 7, Piece::KnightW).unwrap();
+    assert_eq!(board.get(9, 9), Some(Piece::QueenB));
+    assert_eq!(board.get(3, 7), Some(Piece::KnightW));
+    assert_eq!(board.get(10, 9), None);
+
+    board.set(9, 9, Piece::Blank).unwrap();
+    assert_eq!(board.get(9, 9), Some(Piece::Blank));
+    assert_eq!(board.bitboards.occupied().iter().collect::<Vec<_>>(), vec![73]);
+}
+
+#[test]
+fn test_slider_attacks() {
+    let mut board = Board::new(0, 0, 8, 8);
+    board.set(0, 0, Piece::RookW).unwrap();
+    board.set(0, 3, Piece::PawnW).unwrap();
+    board.set(5, 0, Piece::KnightB).unwrap();
+
+    let attacks: Vec<usize> = board_attacks(&board, Piece::RookW, 0, 0).iter().collect();
+    assert_eq!(attacks, vec![1, 2, 3, 4, 5, 8, 16, 24]);
+
+    let attacks = board_attacks(&board, Piece::KnightB, 5, 0);
+    assert_eq!(attacks.count(), 4);
+    assert!(attacks.get(board.index(4, 2)));
+}
@@ -0,0 +1,99 @@
+use chess5dlib::{game::*, moves::*, replay::*};
+
+/// Returns the castling moves that can be made on the last board of timeline `l`
+fn castles(game: &Game, l: i32) -> Vec<Move> {
+    let board = game.timelines[&l].states.last().unwrap();
+    probable_moves(game, board).unwrap().into_iter().filter(|mv| mv.castle).collect()
+}
+
+/// Returns whether the long and the short castling are available on the single board of `rows`, with white to move
+fn castling_sides(rows: &str) -> (bool, bool) {
+    let game = Game::from_fen(&format!("8x8 w 0 0@0:{},KQ", rows)).unwrap();
+    let moves = castles(&game, 0);
+    (
+        moves.iter().any(|mv| mv.castle_long),
+        moves.iter().any(|mv| !mv.castle_long),
+    )
+}
+
+#[test]
+fn test_castling_squares() {
+    let game = Game::from_fen("8x8 w 0 0@0:r3k2r/8/8/8/8/8/8/R3K2R,KQkq").unwrap();
+    let moves = castles(&game, 0);
+    assert_eq!(moves.len(), 2);
+
+    for mv in moves {
+        let (_info, boards) = mv.generate_vboards(&game, &game.info, &[]).unwrap();
+        let board = &boards[0];
+        let (king_x, rook_x) = if mv.castle_long { (2, 3) } else { (6, 5) };
+
+        assert_eq!(board.get(king_x, 0), Some(Piece::KingW));
+        assert_eq!(board.get(rook_x, 0), Some(Piece::RookW));
+        assert_eq!(board.get(4, 0), Some(Piece::Blank));
+        assert_eq!(board.get(mv.dst.2, 0), Some(Piece::Blank));
+        assert_eq!(board.castling_rook(true, true), None);
+        assert_eq!(board.castling_rook(true, false), None);
+        assert_eq!(board.castling_rook(false, true), Some(((4, 7), 0)));
+        assert_eq!(board.castling_rook(false, false), Some(((4, 7), 7)));
+    }
+}
+
+#[test]
+fn test_castling_obstructed() {
+    assert_eq!(castling_sides("7k/8/8/8/8/8/8/RN2K1NR"), (false, false));
+    assert_eq!(castling_sides("7k/8/8/8/8/8/8/R3KB1R"), (true, false));
+}
+
+#[test]
+fn test_castling_out_of_check() {
+    assert_eq!(castling_sides("4r2k/8/8/8/8/8/8/R3K2R"), (false, false));
+}
+
+#[test]
+fn test_castling_through_check() {
+    assert_eq!(castling_sides("5r1k/8/8/8/8/8/8/R3K2R"), (true, false));
+    assert_eq!(castling_sides("3r3k/8/8/8/8/8/8/R3K2R"), (false, true));
+}
+
+#[test]
+fn test_castling_into_check() {
+    assert_eq!(castling_sides("6rk/8/8/8/8/8/8/R3K2R"), (true, false));
+    assert_eq!(castling_sides("2r4k/8/8/8/8/8/8/R3K2R"), (false, true));
+}
+
+#[test]
+fn test_castling_past_attacked_rook_square() {
+    // The king doesn't cross b1, so it may be attacked
+    assert_eq!(castling_sides("1r5k/8/8/8/8/8/8/R3K2R"), (true, true));
+}
+
+#[test]
+fn test_castling_attacked_from_another_timeline() {
+    // The rook on (+0T1)f1 can move to (-0T1)f1, which the king would cross
+    let game = Game::from_fen("8x8 w 0 -0@0:7k/8/8/8/8/8/8/4K2R,K +0@1:k6K/8/8/8/8/8/8/5r2,-").unwrap();
+    assert!(castles(&game, -1).is_empty());
+
+    let game = Game::from_fen("8x8 w 0 -0@0:7k/8/8/8/8/8/8/4K2R,K +0@1:k6K/8/8/8/8/8/8/8,-").unwrap();
+    assert_eq!(castles(&game, -1).len(), 1);
+}
+
+#[test]
+fn test_castling_attacked_from_the_future() {
+    // The queen on (+0T2)f1 can travel back to (-0T1)f1, which the king would cross
+    let game = Game::from_fen("8x8 w 0 -0@0:7k/8/8/8/8/8/8/4K2R,K +0@3:k6K/8/8/8/8/8/8/5q2,-").unwrap();
+    assert!(castles(&game, -1).is_empty());
+
+    let game = Game::from_fen("8x8 w 0 -0@0:7k/8/8/8/8/8/8/4K2R,K +0@3:k6K/8/8/8/8/8/8/q7,-").unwrap();
+    assert_eq!(castles(&game, -1).len(), 1);
+}
+
+#[test]
+fn test_castling_after_rook_returns() {
+    let mut game = Game::from_fen("8x8 w 0 0@0:r3k2r/8/8/8/8/8/8/R3K2R,KQkq").unwrap();
+    replay_notation(&mut game, vec!["(0T1)Rh1g1", "(0T1)Ra8b8", "(0T2)Rg1h1", "(0T2)Rb8a8"]).unwrap();
+
+    let moves = castles(&game, 0);
+    assert_eq!(moves.len(), 1);
+    assert!(moves[0].castle_long);
+    assert_eq!(game.to_fen().rsplit(',').next(), Some("Qk"));
+}
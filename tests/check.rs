@@ -0,0 +1,70 @@
+use chess5dlib::{check::*, game::*};
+
+/// The rook on e1 of timeline 1 attacks the king on e1 of timeline -1, through timeline 0
+const ACROSS_TIMELINES: &str = "8x8 w 0 -1@0:4k3/8/8/8/8/8/8/4K3,- 0@0:8/8/8/8/8/8/8/8,- 1@0:4k3/8/8/8/8/8/8/4r3,-";
+
+fn checks_of(fen: &str) -> Vec<Check> {
+    let game = Game::from_fen(fen).unwrap();
+    checks(&game, &game.info)
+}
+
+#[test]
+fn test_slider_check() {
+    // The rook on a1 attacks the king on e1, along the first rank
+    let res = checks_of("8x8 w 0 0@0:4k3/8/8/8/8/8/8/r3K3,-");
+    assert_eq!(
+        res,
+        vec![Check {
+            attacker: (0, 0, 0, 0),
+            piece: Piece::RookB,
+            king: (0, 0, 4, 0),
+            path: vec![(0, 0, 1, 0), (0, 0, 2, 0), (0, 0, 3, 0)],
+        }]
+    );
+
+    // The bishop on h4 attacks the king on e1, along a diagonal
+    let res = checks_of("8x8 w 0 0@0:4k3/8/8/8/7b/8/8/4K3,-");
+    assert_eq!(res.len(), 1);
+    assert_eq!((res[0].attacker, res[0].piece, res[0].king), ((0, 0, 7, 3), Piece::BishopB, (0, 0, 4, 0)));
+    assert_eq!(res[0].path, vec![(0, 0, 6, 2), (0, 0, 5, 1)]);
+
+    // Adjacent sliders have an empty path, and blocked ones don't give check
+    let res = checks_of("8x8 w 0 0@0:4k3/8/8/8/8/8/8/3qK3,-");
+    assert_eq!(res.len(), 1);
+    assert_eq!((res[0].attacker, res[0].piece), ((0, 0, 3, 0), Piece::QueenB));
+    assert!(res[0].path.is_empty());
+    assert!(checks_of("8x8 w 0 0@0:4k3/8/8/8/8/8/8/r1N1K3,-").is_empty());
+}
+
+#[test]
+fn test_knight_check() {
+    let res = checks_of("8x8 w 0 0@0:4k3/8/8/8/8/3n4/8/4K3,-");
+    assert_eq!(
+        res,
+        vec![Check {
+            attacker: (0, 0, 3, 2),
+            piece: Piece::KnightB,
+            king: (0, 0, 4, 0),
+            path: vec![],
+        }]
+    );
+}
+
+#[test]
+fn test_check_from_another_timeline() {
+    let res = checks_of(ACROSS_TIMELINES);
+    assert_eq!(
+        res,
+        vec![Check {
+            attacker: (1, 0, 4, 0),
+            piece: Piece::RookB,
+            king: (-1, 0, 4, 0),
+            path: vec![(0, 0, 4, 0)],
+        }]
+    );
+    let game = Game::from_fen(ACROSS_TIMELINES).unwrap();
+    assert!(is_in_check(&game, &game.info));
+
+    // A piece on timeline 0 blocks it
+    assert!(checks_of(&ACROSS_TIMELINES.replace("0@0:8/8/8/8/8/8/8/8", "0@0:8/8/8/8/8/8/8/4N3")).is_empty());
+}
@@ -0,0 +1,157 @@
+use chess5dlib::{game::*, moves::*, replay::*, variants::*, Error};
+
+const VARIANTS: [Variant; 10] = [
+    Variant::Standard,
+    Variant::Princess,
+    Variant::TurnZero,
+    Variant::TwoTimelines,
+    Variant::DefendedPawn,
+    Variant::HalfReflected,
+    Variant::Small,
+    Variant::SmallOpen,
+    Variant::VerySmall,
+    Variant::VerySmallOpen,
+];
+
+/// Three timelines, the last of which holds a board with two white kings (one of them having travelled there) and white's short castling right
+const MULTIVERSE: &str = "4x4 b 7 0@0:nbrk/3p/P3/KRBN,Kq:nbrk/2Np/P3/KRB1,Kq:1brk/2Np/Pn2/KRB1,Kq:1brk/P1Np/1n2/KRB1,Kq:1brk/P1N1/1n1p/KRB1,Kq:1brk/P3/1n1p/KRB1,Kq:1brk/P3/3p/KRBn,Kq:1Rrk/P3/3p/K1Bn,q:1Rrk/P3/4/K1qn,q 1@1:nbrk/3N/P3/KRBN,Kq:nb1k/3N/P3/KRrN,K:nb1k/3N/P3/K1RN,-:n2k/b2N/P3/K1RN,-:n2k/b2N/P3/2RN,-:n3/b2k/P3/2RN,-:n1R1/b2k/P3/3N,- 2@3:1brk/2Np/PK2/KRB1,Kq";
+
+/// Writes `game` as a 5D FEN string and reads it back, checking that the same position (and thus the same moves and hashes) was read
+fn round_trip(game: &Game) -> Game {
+    let fen = game.to_fen();
+    let read = Game::from_fen(&fen).unwrap();
+    assert_eq!(read.to_fen(), fen);
+    assert_eq!(read.info, game.info);
+    assert_eq!((read.castling, read.pawn_double_step), (game.castling, game.pawn_double_step));
+    assert_eq!(read.promotions, game.promotions);
+    assert_eq!(read.timelines.len(), game.timelines.len());
+
+    for (l, timeline) in game.timelines.iter() {
+        let read_timeline = &read.timelines[l];
+        assert_eq!(read_timeline.begins_at, timeline.begins_at);
+        assert_eq!(read_timeline.emerges_from, timeline.emerges_from);
+        assert_eq!(read_timeline.states.len(), timeline.states.len());
+        for (read_board, board) in read_timeline.states.iter().zip(timeline.states.iter()) {
+            assert_eq!(read_board.bitboards, board.bitboards);
+            assert_eq!(read_board.moved, board.moved);
+            assert_eq!(read_board.hash, board.hash);
+            assert_eq!(probable_moves(&read, read_board).unwrap(), probable_moves(game, board).unwrap());
+        }
+    }
+    read
+}
+
+#[test]
+fn test_round_trip_variants() {
+    for variant in VARIANTS.iter() {
+        round_trip(&Game::variant(*variant));
+    }
+}
+
+#[test]
+fn test_round_trip_after_movesets() {
+    let mut game = Game::variant(Variant::Standard);
+    for _ in 0..6 {
+        let (moveset, boards, info, _score) = legal_movesets(&game, &game.info, 0, 0).next().unwrap();
+        game.make_moveset(moveset, boards, info).unwrap();
+        round_trip(&game);
+    }
+}
+
+#[test]
+fn test_multi_timeline() {
+    let game = round_trip(&Game::from_fen(MULTIVERSE).unwrap());
+    assert!(!game.info.active_player);
+    assert_eq!(game.info.present, 7);
+    assert_eq!((game.info.min_timeline, game.info.max_timeline), (0, 2));
+    assert!(!game.info.even_initial_timelines);
+    assert_eq!(game.timelines[&1].begins_at, 1);
+    assert_eq!(game.timelines[&2].states.len(), 1);
+
+    // The right belongs to the king that has a rook on its side, not to the one that travelled there
+    let board = game.get_board(2, 3).unwrap();
+    assert_eq!(board.get(1, 1), Some(Piece::KingW));
+    assert_eq!(board.castling_rook(true, false), Some(((0, 0), 1)));
+    assert_eq!(board.castling_rook(false, true), Some(((3, 3), 2)));
+
+    let branched = Game::from_fen("8x8 b 1 0@0:4k3/8/8/8/8/8/8/4K3,-:4k3/8/8/8/8/8/8/5K2,- 1@1~0:4k3/8/8/8/8/8/8/3K4,-").unwrap();
+    assert_eq!(branched.timelines[&1].emerges_from, Some(0));
+    assert_eq!((branched.info.min_timeline, branched.info.max_timeline), (0, 1));
+    round_trip(&branched);
+}
+
+#[test]
+fn test_even_timelines() {
+    let game = round_trip(&Game::variant(Variant::TwoTimelines));
+    assert!(game.info.even_initial_timelines);
+    assert_eq!((game.info.min_timeline, game.info.max_timeline), (-1, 0));
+    assert!(game.to_fen().contains(" -0@0:"));
+    assert!(game.to_fen().contains(" +0@0:"));
+
+    let read = Game::from_fen("8x8 w 0 -0@0:4k3/8/8/8/8/8/8/4K3,- +0@0:4k3/8/8/8/8/8/8/4K3,- -1@0~-0:4k3/8/8/8/8/8/8/4K3,-").unwrap();
+    assert!(read.info.even_initial_timelines);
+    assert_eq!(read.timelines[&-2].emerges_from, Some(-1));
+    assert_eq!((read.info.min_timeline, read.info.max_timeline), (-2, 0));
+    round_trip(&read);
+}
+
+#[test]
+fn test_settings() {
+    let game = Game::variant(Variant::Standard);
+    assert!(!game.to_fen().contains('='));
+
+    let princess = round_trip(&Game::variant(Variant::Princess));
+    assert!(princess.to_fen().contains(" =QSNRB,CD "));
+
+    let description = r#"{"width": 4, "height": 4, "timelines": [{"index": "0", "boards": ["nbrk/3p/P3/KRBN"]}], "castling": false, "pawn_double_step": false, "promotions": "N"}"#;
+    let game = round_trip(&load_variant(description).unwrap());
+    assert!(game.to_fen().starts_with("4x4 w 0 =N,- "));
+    assert!(!game.castling && !game.pawn_double_step);
+    assert_eq!(game.promotions, vec![Piece::KnightW]);
+    // Castling isn't allowed, so no right is written even though the king and rook haven't moved
+    assert!(game.to_fen().contains(":nbrk/3p/P3/KRBN,-,"));
+    assert!(!game.get_board(0, 0).unwrap().has_moved(0, 0));
+
+    let read = Game::from_fen("4x4 w 0 =-,C 0@0:nbrk/3p/P3/KRBN,-").unwrap();
+    assert!(read.promotions.is_empty());
+    assert!(read.castling && !read.pawn_double_step);
+    round_trip(&read);
+}
+
+#[test]
+fn test_moved_flags() {
+    // A pawn that reached its second rank by travelling there can't double-step
+    let game = round_trip(&Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/8/4P3/4K3,-,e2").unwrap());
+    let board = game.get_board(0, 0).unwrap();
+    assert!(board.has_moved(4, 1));
+    assert_eq!(probable_moves(&game, board).unwrap().iter().filter(|mv| mv.src_piece == Piece::PawnW).count(), 1);
+
+    // Pieces that moved are listed as such
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(&mut game, vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Ng1", "(0T2)Ng8"]).unwrap();
+    assert!(game.to_fen().ends_with(",KQkq,g1g8"));
+    round_trip(&game);
+}
+
+#[test]
+fn test_invalid() {
+    let invalid = [
+        ("", ""),
+        ("8x8", "player"),
+        ("8x8 x 0 0@0:8/8/8/8/8/8/8/8,-", "player"),
+        ("8y8 w 0 0@0:8/8/8/8/8/8/8/8,-", "size"),
+        ("8x8 w a 0@0:8/8/8/8/8/8/8/8,-", "present"),
+        ("8x8 w 0 =QK,CD 0@0:8/8/8/8/8/8/8/8,-", "settings"),
+        ("8x8 w 0", "timelines"),
+        ("8x8 w 0 0@0", "timelines[0]"),
+        ("8x8 w 0 0@0:8/8/8/8/8/8/8/8,- 0@0:8/8/8/8/8/8/8/8,-", "timelines[1]"),
+        ("8x8 w 0 0@0:8/8/8/8/8/8/8/8,- 1@0:8/8/8/8/8/8/8/8,K", "timelines[1].boards[0]"),
+        ("8x8 w 0 0@0:8/8/8/8/8/8/8/8,-,z9", "timelines[0].boards[0]"),
+    ];
+    for (fen, expected_path) in invalid.iter() {
+        match Game::from_fen(fen) {
+            Err(Error::Parse { path, .. }) => assert_eq!(&path, expected_path, "{}", fen),
+            res => panic!("expected a parse error for {}, got {:?}", fen, res),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use chess5dlib::{game::*, replay::*, variants::*, Error};
+
+const MOVESETS: [&str; 7] = [
+    "(0T1)Nf3",
+    "(0T1)Nf6",
+    "(0T2)Nc3",
+    "(0T2)Nc6",
+    "(0T3)Nc3>>(0T2)c5",
+    "(1T2)a6",
+    "(1T3)Nf3>>(0T1)f3",
+];
+
+#[test]
+fn test_unmake_branching_movesets() {
+    let initial = Game::variant(Variant::Standard);
+    let mut game = Game::variant(Variant::Standard);
+    let mut states = Vec::new();
+
+    for moveset in MOVESETS.iter() {
+        states.push((game.to_fen(), game.info));
+        replay_notation(&mut game, vec![*moveset]).unwrap();
+    }
+    assert_eq!(game.history.len(), MOVESETS.len());
+    assert_eq!(game.timelines.len(), 3);
+
+    while let Some((fen, info)) = states.pop() {
+        let made = game.unmake_moveset().unwrap();
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.info, info);
+        assert_eq!(made.previous_info, info);
+    }
+    assert_eq!(game.unmake_moveset(), None);
+    assert_eq!(game, initial);
+}
+
+#[test]
+fn test_make_existing_board() {
+    let mut game = Game::variant(Variant::Standard);
+    let steps = replay_notation(&mut game, vec!["(0T1)Nf3"]).unwrap();
+    let (moves, boards, info) = steps[0].clone();
+
+    let before = game.to_fen();
+    assert_eq!(game.make_moveset(moves, boards, info), Err(Error::BoardCollision(0, 1)));
+    assert_eq!(game.to_fen(), before);
+    assert_eq!(game.history.len(), 1);
+}
+
+#[test]
+fn test_make_duplicate_board() {
+    let mut played = Game::variant(Variant::Standard);
+    let (moves, boards, info) = replay_notation(&mut played, vec!["(0T1)Nf3"]).unwrap()[0].clone();
+    let mut game = Game::variant(Variant::Standard);
+
+    // Two versions of the same board can't be added by a single moveset
+    let mut twice = boards.clone();
+    twice.extend(boards.iter().cloned());
+    let before = game.to_fen();
+    assert_eq!(game.make_moveset(moves.clone(), twice, info), Err(Error::BoardCollision(0, 1)));
+    assert_eq!(game.to_fen(), before);
+    assert_eq!(game.history.len(), 0);
+
+    assert_eq!(game.make_moveset(moves, boards, info), Ok(()));
+    assert_eq!(game.get_timeline(0).unwrap().states.len(), 2);
+}
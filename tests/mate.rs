@@ -0,0 +1,75 @@
+use chess5dlib::{game::*, moves::*, moveset::*};
+
+/// A jump-heavy position in which every moveset leaves white in check; enumerating the movesets takes minutes
+const JUMPING_MATE: &str = "4x4 w 4 -1@4:n1rk/B3/2bb/KR1N,Kq 0@0:nbrk/4/4/KRBN,Kq:nbrk/4/1R2/K1BN,q:1brk/2n1/1R2/K1BN,q:1brk/2n1/1R2/K2N,q:1brk/4/1R2/K2n,q:1brk/1R2/4/K2n,q:2rk/bR2/4/K2n,q 1@1:nbrk/4/2B1/KRBN,Kq:n1rk/4/2Bb/KRBN,Kq:n1rk/B3/2Bb/KR1N,Kq:n1rk/B1b1/2B1/KR1N,Kq:n1rk/B1b1/2B1/1R1N,q:n1rk/B3/2B1/1R1N,q 2@3:n1rk/4/K1Bb/KRBN,Kq:nbrk/4/K1B1/KRBN,Kq";
+
+/// A position in which black must leave a board that they should play on untouched, and play on it by jumping there from another board
+const JUMP_ONTO_ACTIVE_BOARD: &str = "4x4 b 7 0@0:nbrk/3p/P3/KRBN,Kq:nbrk/2Np/P3/KRB1,Kq:1brk/2Np/Pn2/KRB1,Kq:1brk/P1Np/1n2/KRB1,Kq:1brk/P1N1/1n1p/KRB1,Kq:1brk/P3/1n1p/KRB1,Kq:1brk/P3/3p/KRBn,Kq:1Rrk/P3/3p/K1Bn,q:1Rrk/P3/4/K1qn,q 1@1:nbrk/3N/P3/KRBN,Kq:nb1k/3N/P3/KRrN,K:nb1k/3N/P3/K1RN,-:n2k/b2N/P3/K1RN,-:n2k/b2N/P3/2RN,-:n3/b2k/P3/2RN,-:n1R1/b2k/P3/3N,- 2@3:1brk/2Np/PK2/KRB1,Kq";
+
+/// A position in which white must leave a board that they should play on untouched: creating a timeline activates one that ends earlier, which moves the present back
+const ACTIVATE_TIMELINE: &str = "4x4 w 8 -2@6:1brk/4/2b1/KR1N,q:1brk/4/1Rb1/K2N,q -1@2:nbrk/4/1R2/K1nN,q:nbrk/4/1R2/K1BN,q:n1rk/2b1/1R2/K1BN,q:n1rk/2b1/1R1B/K2N,q:n1rk/4/1R1b/K2N,q:n1rk/4/R2b/K2N,q:n1rk/4/R3/K1bN,q 0@0:nbrk/4/4/KRBN,Kq:nbrk/4/1R2/K1BN,q:1brk/2n1/1R2/K1BN,q:1brk/2n1/4/KRBN,q:1brk/4/4/KRBN,q:1brk/4/4/KR1N,q:2rk/2b1/4/KR1N,q:2rk/2b1/1N2/KR2,q:2rk/4/1N2/KR2,q:2rk/4/KN2/1R2,q:1r1k/4/KN2/1R2,-";
+
+/// A position in which black has no move on (-4T4) that keeps their king safe, and the timelines that black would create are inactive, so the present can't be moved back
+const INACTIVE_NEW_TIMELINE: &str = "4x4 b 7 -5@6:1Rnk/2np/P3/K1BN,- -4@6:1Rnk/3p/n3/K1BN,-:1Rnk/3p/K3/2BN,- -3@8:1b1k/2nk/PB1p/K1R1,- -2@4:nb1k/2Np/nB2/KR2,K:nb1k/2Np/KB2/1R2,-:1b1k/2Np/KB2/1R2,-:1b1k/2Np/K3/1R2,- -1@2:nbnk/3p/P3/KRBN,K:nbnk/1R1p/P3/K1BN,-:1bnk/1R1p/P3/K1BN,-:1Rnk/3p/P3/K1BN,-:1Rn1/3p/P3/K1BN,-:2R1/3p/P3/K1BN,- 0@0:nbrk/3p/P3/KRBN,Kq:nbrk/3p/PB2/KR1N,Kq:nb1k/2rp/PB2/KR1N,K:nb1k/2Np/PB2/KR2,K:nb1k/2N1/PB1p/KR2,K:nb1k/4/PB1p/KR2,K:1b1k/2n1/PB1p/KR2,K:1b1k/2n1/PB1p/K1R1,-:1b1k/2n1/PB2/K1r1,-:1b1k/2n1/P3/K1B1,-:1b1k/4/P3/K1B1,-:1b1k/4/P3/K3,- 1@1:nbNk/3p/P3/KRBN,K:n1Nk/b2p/P3/KRBN,K:n1Nk/bR1p/P3/K1BN,-:2Nk/bR1p/P3/K1BN,-:2Nk/BR1p/P3/K2N,-:2k1/BR1p/P3/K2N,-:2k1/B2p/P3/KR1N,- 2@7:1b1k/2B1/PB1p/KR2,K 3@7:1B1k/2n1/PB1p/KR2,K";
+
+/// A position in which black can only play on (1T5) by jumping there from (2T6), and every such jump leaves one of their kings in check
+const JUMP_INTO_CHECK: &str = "4x4 b 9 -3@8:Rrrk/3p/K3/2BN,q:Rrrk/B2p/K3/3N,q -2@10:1r1k/4/1N1p/2Bb,-:1r1k/4/1NBp/2Bb,- -1@6:nRrk/3p/K3/2BN,q:R1rk/3p/K3/2BN,q:Rr1k/3p/K3/2BN,-:1r1k/R2p/K3/2BN,-:3k/R2p/K3/2BN,-:3k/R2p/K3/3N,- 0@0:nbrk/3p/P3/KRBN,Kq:nRrk/3p/P3/K1BN,q:1Rrk/2np/P3/K1BN,q:NRrk/2np/4/K1BN,q:NRrk/3p/n3/K1BN,q:NRrk/3p/K3/2BN,q:Nr1k/3p/K3/2BN,-:Nr1k/3p/4/2BN,-:Nr1k/4/3p/2BN,-:1r1k/4/1N1p/2BN,-:1r1k/4/1N2/2bN,-:Nr1k/4/4/2bN,-:Nr1k/4/4/3N,-:1r1k/4/4/3N,- 1@5:NRrk/K2p/n3/K1BN,q:NRrk/K2p/4/K1BN,q:N1Rk/K2p/4/K1BN,-:N1k1/K2p/4/K1BN,-:N1k1/K2p/4/2BN,- 2@11:1r1k/4/1N2/1KbN,- 3@9:NN1k/4/3p/2BN,-";
+
+/// Checks that `result` holds a legal moveset
+fn assert_playable(game: &Game, result: MateResult) -> Vec<Move> {
+    match result {
+        MateResult::Playable(moveset, boards, info) => {
+            let (expected_boards, expected_info) = validate_moveset(game, &game.info, &moveset).unwrap();
+            assert_eq!(boards, expected_boards);
+            assert_eq!(info, expected_info);
+            moveset
+        }
+        result => panic!("expected a legal moveset, got {:?}", result),
+    }
+}
+
+#[test]
+fn test_checkmate() {
+    // Back-rank mate
+    let game = Game::from_fen("8x8 w 0 0@0:7k/8/8/8/8/8/5PPP/r5K1,-").unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Checkmate));
+    assert!(find_legal_moveset(&game, &game.info).is_none());
+    assert_eq!(count_legal_movesets(&game, &game.info), 0);
+
+    let game = Game::from_fen(JUMPING_MATE).unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Checkmate));
+
+    let game = Game::from_fen(INACTIVE_NEW_TIMELINE).unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Checkmate));
+
+    let game = Game::from_fen(JUMP_INTO_CHECK).unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Checkmate));
+}
+
+#[test]
+fn test_stalemate() {
+    let game = Game::from_fen("8x8 b 1 0@1:7k/5Q2/6K1/8/8/8/8/8,-").unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Stalemate));
+    assert_eq!(count_legal_movesets(&game, &game.info), 0);
+}
+
+#[test]
+fn test_playable() {
+    // In check, but the king can step away
+    let game = Game::from_fen("8x8 w 0 0@0:7k/8/8/8/8/8/6PP/r5K1,-").unwrap();
+    let moveset = assert_playable(&game, is_checkmate(&game, &game.info));
+    assert_eq!(moveset.len(), 1);
+    assert_eq!(moveset[0].src_piece, Piece::KingW);
+
+    let game = Game::variant(chess5dlib::variants::Variant::Standard);
+    assert_playable(&game, is_checkmate(&game, &game.info));
+
+    for fen in [JUMP_ONTO_ACTIVE_BOARD, ACTIVATE_TIMELINE].iter() {
+        let game = Game::from_fen(fen).unwrap();
+        let moveset = assert_playable(&game, is_checkmate(&game, &game.info));
+        assert!(get_own_boards(&game, &game.info)
+            .into_iter()
+            .filter(|b| b.is_active(&game.info))
+            .any(|b| !moveset.iter().any(|mv| (mv.src.0, mv.src.1) == (b.l, b.t))));
+    }
+}
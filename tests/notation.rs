@@ -0,0 +1,154 @@
+use chess5dlib::{game::*, moves::*, notation::*, replay::*, variants::*};
+
+/// Replays `movesets` from the starting position of `variant`
+fn replayed(variant: Variant, movesets: Vec<&str>) -> Game {
+    let mut game = Game::variant(variant);
+    replay_notation(&mut game, movesets).unwrap();
+    game
+}
+
+/// White has branched once and plays on `1T3`; every white board of timeline `0` is in the past
+fn branched() -> Game {
+    replayed(
+        Variant::Standard,
+        vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5", "(1T2)a6"],
+    )
+}
+
+/// Both players have played a move on both timelines of the `TwoTimelines` variant
+fn two_timelines() -> Game {
+    replayed(Variant::TwoTimelines, vec!["(-0T1)Nf3 (+0T1)e3", "(-0T1)Nf6 (+0T1)e6"])
+}
+
+/// Asserts that `raw` is parsed into a move that is written back as `raw`
+fn assert_round_trip(game: &Game, raw: &str) -> Move {
+    let mv = parse_move(game, &game.info, raw).unwrap_or_else(|| panic!("Couldn't parse {}", raw));
+    assert_eq!(write_move(game, &mv), raw);
+    mv
+}
+
+#[test]
+fn test_physical_moves() {
+    let game = Game::variant(Variant::Standard);
+    let mv = assert_round_trip(&game, "(0T1)Ng1f3");
+    assert_eq!((mv.src, mv.dst), ((0, 0, 6, 0), (0, 0, 5, 2)));
+    assert_round_trip(&game, "(0T1)e4");
+
+    // Short forms are written with the source square of the piece
+    let mv = parse_move(&game, &game.info, "(0T1)Nf3").unwrap();
+    assert_eq!(write_move(&game, &mv), "(0T1)Ng1f3");
+    let mv = parse_move(&game, &game.info, "Nf3").unwrap();
+    assert_eq!(write_move(&game, &mv), "(0T1)Ng1f3");
+}
+
+#[test]
+fn test_captures() {
+    let game = replayed(Variant::Standard, vec!["(0T1)e4", "(0T1)d5"]);
+    let mv = assert_round_trip(&game, "(0T2)exd5");
+    assert_eq!(mv.dst_piece, Piece::PawnB);
+    assert_eq!(parse_move(&game, &game.info, "(0T2)ed5"), Some(mv));
+
+    let game = replayed(Variant::Standard, vec!["(0T1)e4", "(0T1)d5", "(0T2)exd5"]);
+    let mv = assert_round_trip(&game, "(0T2)Qd8xd5");
+    assert_eq!((mv.src_piece, mv.dst_piece), (Piece::QueenB, Piece::PawnW));
+
+    // Captures through time
+    let game = branched();
+    let mv = assert_round_trip(&game, "(1T3)Nc5>>x(0T3)c7");
+    assert_eq!((mv.dst, mv.dst_piece), ((0, 4, 2, 6), Piece::PawnB));
+}
+
+#[test]
+fn test_jumps() {
+    // `>>` is written for jumps to older boards, `>` for jumps to the last board of a timeline
+    let game = branched();
+    let mv = assert_round_trip(&game, "(1T3)Nf3>>(0T3)f5");
+    assert_eq!((mv.src, mv.dst), ((1, 4, 5, 2), (0, 4, 5, 4)));
+    let mv = assert_round_trip(&game, "(1T3)Nf3>>(0T1)f3");
+    assert_eq!(mv.dst, (0, 0, 5, 2));
+
+    let game = two_timelines();
+    let mv = assert_round_trip(&game, "(-0T2)Nf3>(+0T2)f5");
+    assert_eq!((mv.src, mv.dst), ((-1, 2, 5, 2), (0, 2, 5, 4)));
+    let mv = assert_round_trip(&game, "(+0T2)Ng1>>(+0T1)g3");
+    assert_eq!((mv.src, mv.dst), ((0, 2, 6, 0), (0, 0, 6, 2)));
+
+    // Both are read alike, and written as they should be
+    let mv = parse_move(&game, &game.info, "(+0T2)Ng1>(+0T1)g3").unwrap();
+    assert_eq!(write_move(&game, &mv), "(+0T2)Ng1>>(+0T1)g3");
+    let mv = parse_move(&game, &game.info, "(-0T2)Nf3>>(+0T2)f5").unwrap();
+    assert_eq!(write_move(&game, &mv), "(-0T2)Nf3>(+0T2)f5");
+}
+
+#[test]
+fn test_even_timelines() {
+    let game = Game::variant(Variant::TwoTimelines);
+    let mv = assert_round_trip(&game, "(+0T1)Ng1f3");
+    assert_eq!(mv.src.0, 0);
+    let mv = assert_round_trip(&game, "(-0T1)e3");
+    assert_eq!(mv.src.0, -1);
+    // `0` is read as `+0`
+    assert_eq!(parse_move(&game, &game.info, "(0T1)e3"), parse_move(&game, &game.info, "(+0T1)e3"));
+
+    let game = two_timelines();
+    let mv = assert_round_trip(&game, "(+0T2)Bf1>(-0T2)g1");
+    assert_eq!((mv.src.0, mv.dst.0), (0, -1));
+}
+
+#[test]
+fn test_castling() {
+    let game = Game::from_fen("8x8 w 0 0@0:r3k2r/8/8/8/8/8/8/R3K2R,KQkq").unwrap();
+    let mv = assert_round_trip(&game, "(0T1)O-O");
+    assert!(mv.castle && !mv.castle_long);
+    let mv = assert_round_trip(&game, "(0T1)O-O-O");
+    assert!(mv.castle && mv.castle_long);
+    assert_eq!(parse_move(&game, &game.info, "(0T1)0-0"), Some(parse_move(&game, &game.info, "(0T1)O-O").unwrap()));
+
+    let game = Game::from_fen("8x8 b 1 0@1:r3k2r/8/8/8/8/8/8/R3K2R,kq").unwrap();
+    let mv = assert_round_trip(&game, "(0T1)O-O-O");
+    assert_eq!(mv.src_piece, Piece::KingB);
+    assert!(mv.castle && mv.castle_long);
+}
+
+#[test]
+fn test_ambiguous_moves() {
+    // The knights of b1 and f1 can both go to d2
+    let game = Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/8/8/1N2KN2,-").unwrap();
+    assert_eq!(parse_move(&game, &game.info, "(0T1)Nd2"), None);
+    let mv = parse_move(&game, &game.info, "(0T1)Nbd2").unwrap();
+    assert_eq!(mv.src, (0, 0, 1, 0));
+    assert_eq!(write_move(&game, &mv), "(0T1)Nb1d2");
+    let mv = parse_move(&game, &game.info, "(0T1)Nfd2").unwrap();
+    assert_eq!(mv.src, (0, 0, 5, 0));
+    assert_round_trip(&game, "(0T1)Nf1d2");
+
+    // The knights of b1 and b3 can both go to d2
+    let game = Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/1N6/8/1N2K3,-").unwrap();
+    assert_eq!(parse_move(&game, &game.info, "(0T1)Nd2"), None);
+    assert_eq!(parse_move(&game, &game.info, "(0T1)Nbd2"), None);
+    let mv = parse_move(&game, &game.info, "(0T1)N3d2").unwrap();
+    assert_eq!(mv.src, (0, 0, 1, 2));
+    assert_eq!(write_move(&game, &mv), "(0T1)Nb3d2");
+    assert_round_trip(&game, "(0T1)Nb1d2");
+}
+
+#[test]
+fn test_movesets() {
+    let game = replayed(
+        Variant::Standard,
+        vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5", "(1T2)a6", "(1T3)Nf3>>(0T1)f3"],
+    );
+    for raw in ["(0T3)Nc6>>(0T2)c4 (1T3)a5", "(0T3)Nc6>>(0T2)c4 (1T3)a5 (2T1)a6"] {
+        let moveset = parse_moveset(&game, &game.info, raw).unwrap();
+        assert_eq!(write_moveset(&game, &game.info, &moveset).as_deref(), Some(raw));
+    }
+
+    // Moving onto a board that was played on earlier in the moveset creates a timeline
+    let game = two_timelines();
+    let moveset = parse_moveset(&game, &game.info, "(+0T2)a3 (-0T2)Nf3>(+0T2)f5").unwrap();
+    assert_eq!(write_moveset(&game, &game.info, &moveset).as_deref(), Some("(+0T2)a3 (-0T2)Nf3>>(+0T2)f5"));
+    // The other way around, the board of `+0` is already played on when `a3` is read
+    assert_eq!(parse_moveset(&game, &game.info, "(-0T2)Nf3>(+0T2)f5 (+0T2)a3"), None);
+
+    assert_eq!(write_moveset(&game, &game.info, &[]).as_deref(), Some(""));
+}
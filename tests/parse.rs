@@ -0,0 +1,150 @@
+use chess5dlib::{game::*, moves::find_present, parse::*, replay::*, variants::*, Error};
+
+const STANDARD_BOARD: [usize; 64] = [
+    4, 2, 3, 5, 6, 3, 2, 4,
+    1, 1, 1, 1, 1, 1, 1, 1,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    33, 33, 33, 33, 33, 33, 33, 33,
+    36, 34, 35, 37, 38, 35, 34, 36,
+];
+
+fn timeline_json(index: f32, begins_at: isize, n_states: usize, emerges_from: Option<f32>) -> String {
+    let board = format!("{:?}", STANDARD_BOARD.to_vec());
+    format!(
+        "{{\"index\": {}, \"states\": [{}], \"width\": 8, \"height\": 8, \"begins_at\": {}, \"emerges_from\": {}}}",
+        index,
+        vec![board; n_states].join(", "),
+        begins_at,
+        emerges_from.map(|x| x.to_string()).unwrap_or(String::from("null")),
+    )
+}
+
+fn game_json(timelines: Vec<String>, active_player: bool) -> String {
+    format!(
+        "{{\"timelines\": [{}], \"width\": 8, \"height\": 8, \"active_player\": {}}}",
+        timelines.join(", "),
+        active_player
+    )
+}
+
+#[test]
+fn test_export_standard() {
+    let game = parse(&game_json(vec![timeline_json(0.0, 0, 1, None)], true)).unwrap();
+    assert_eq!(game.timelines.len(), 1);
+    assert_eq!(game.get(0, 0, 4, 0), Some(Piece::KingW));
+
+    let exported = export(&game);
+    assert_eq!(parse(&exported), Ok(game));
+}
+
+#[test]
+fn test_export_branching() {
+    let game = parse(&game_json(
+        vec![
+            timeline_json(0.0, 0, 6, None),
+            timeline_json(1.0, 3, 2, Some(0.0)),
+            timeline_json(-1.0, 4, 1, Some(0.0)),
+        ],
+        false,
+    ))
+    .unwrap();
+    assert_eq!(game.info.min_timeline, -1);
+    assert_eq!(game.info.max_timeline, 1);
+    assert_eq!(game.get_timeline(1).unwrap().emerges_from, Some(0));
+
+    let exported = export(&game);
+    assert_eq!(parse(&exported), Ok(game));
+}
+
+#[test]
+fn test_export_even_timelines() {
+    let game = parse(&game_json(
+        vec![
+            timeline_json(-0.5, 0, 3, None),
+            timeline_json(0.5, 0, 3, None),
+            timeline_json(-1.0, 2, 1, Some(-0.5)),
+            timeline_json(1.0, 1, 2, Some(0.5)),
+        ],
+        true,
+    ))
+    .unwrap();
+    assert!(game.info.even_initial_timelines);
+    assert_eq!(game.info.min_timeline, -2);
+    assert_eq!(game.info.max_timeline, 1);
+    assert_eq!(game.get_timeline(-2).unwrap().emerges_from, Some(-1));
+
+    let exported = export(&game);
+    assert_eq!(parse(&exported), Ok(game));
+}
+
+#[test]
+fn test_parse_present() {
+    // Timeline 2 is inactive, as black only has `-0`; its board at t = 1 doesn't hold the present back.
+    // `+0` comes first, which mustn't make `-0` the highest timeline
+    let game = parse(&game_json(
+        vec![
+            timeline_json(0.5, 0, 5, None),
+            timeline_json(-0.5, 0, 5, None),
+            timeline_json(1.0, 3, 2, Some(0.5)),
+            timeline_json(2.0, 1, 1, Some(0.5)),
+        ],
+        true,
+    ))
+    .unwrap();
+    assert_eq!((game.info.min_timeline, game.info.max_timeline), (-1, 2));
+    assert!(!game.info.is_timeline_active(2));
+    assert_eq!(game.info.present, 4);
+    assert_eq!(game.info.present, find_present(&game, &game.info));
+
+    let exported = export(&game);
+    assert_eq!(parse(&exported), Ok(game));
+}
+
+#[test]
+fn test_export_after_commit() {
+    let mut game = parse(&game_json(vec![timeline_json(0.0, 0, 1, None)], true)).unwrap();
+    let mut board = game.get_board(0, 0).unwrap().clone();
+    board.t = 1;
+    board.set(4, 1, Piece::Blank).unwrap();
+    board.set(4, 3, Piece::PawnW).unwrap();
+    board.set_moved(4, 3, true);
+    game.commit_moves(vec![board]).unwrap();
+
+    let exported = export(&game);
+    let reparsed = parse(&exported).unwrap();
+    assert_eq!(reparsed.timelines, game.timelines);
+    assert!(!reparsed.info.active_player);
+
+    // The timeline created by a branching jump keeps track of where it branched off from
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(&mut game, vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nf3>>(0T1)h3"]).unwrap();
+    assert_eq!(game.get_timeline(1).unwrap().emerges_from, Some(0));
+    assert!(game.timelines.values().flat_map(|tl| tl.states.iter()).all(|b| b.emerges_from.is_none()));
+
+    let reparsed = parse(&export(&game)).unwrap();
+    assert_eq!(reparsed.get_timeline(1).unwrap().emerges_from, Some(0));
+    assert_eq!(reparsed.timelines, game.timelines);
+    assert_eq!(reparsed.info, game.info);
+}
+
+#[test]
+fn test_parse_errors() {
+    let json = game_json(vec![timeline_json(0.0, 0, 1, None)], true);
+
+    match parse(&json.replace("\"begins_at\": 0", "\"begins_at\": \"zero\"")) {
+        Err(Error::Parse { path, .. }) => assert_eq!(path, "timelines[0].begins_at"),
+        res => panic!("Expected a parse error, got {:?}", res.map(|_| ())),
+    }
+    assert!(matches!(parse("{}"), Err(Error::Parse { .. })));
+    assert!(matches!(
+        parse(&game_json(vec![], true)),
+        Err(Error::Parse { path, .. }) if path == "timelines"
+    ));
+    assert_eq!(
+        parse(&json.replacen("[4, 2, 3", "[4, 99, 3", 1)).map(|_| ()),
+        Err(Error::InvalidPiece(99))
+    );
+}
@@ -0,0 +1,119 @@
+use chess5dlib::{game::*, moves::*, perft::*, variants::*};
+use std::collections::HashSet;
+
+/// Reference move generator for `perft`: tries every ordered sequence of moves from distinct boards
+/// through `validate_moveset` and keeps one moveset per resulting set of boards.
+fn reference_movesets(game: &Game) -> Vec<(Vec<Move>, Vec<Board>, GameInfo)> {
+    fn recurse(
+        game: &Game,
+        candidates: &[Vec<Move>],
+        used: &mut Vec<bool>,
+        current: &mut Vec<Move>,
+        seen: &mut HashSet<Vec<(i32, isize, u64)>>,
+        res: &mut Vec<(Vec<Move>, Vec<Board>, GameInfo)>,
+    ) {
+        if !current.is_empty() {
+            if let Ok((boards, info)) = validate_moveset(game, &game.info, current) {
+                let mut key: Vec<(i32, isize, u64)> = boards.iter().map(|b| (b.l, b.t, b.hash)).collect();
+                key.sort();
+                if seen.insert(key) {
+                    res.push((current.clone(), boards, info));
+                }
+            }
+        }
+        for n in 0..candidates.len() {
+            if used[n] {
+                continue;
+            }
+            used[n] = true;
+            for mv in candidates[n].iter() {
+                current.push(*mv);
+                recurse(game, candidates, used, current, seen, res);
+                current.pop();
+            }
+            used[n] = false;
+        }
+    }
+
+    let candidates: Vec<Vec<Move>> = get_own_boards(game, &game.info)
+        .into_iter()
+        .map(|board| probable_moves(game, board).unwrap())
+        .collect();
+    let mut res = Vec::new();
+    recurse(game, &candidates, &mut vec![false; candidates.len()], &mut Vec::new(), &mut HashSet::new(), &mut res);
+    res
+}
+
+fn reference_perft(game: &mut Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut total = 0;
+    for (moves, boards, info) in reference_movesets(game) {
+        game.make_moveset(moves, boards, info).unwrap();
+        total += reference_perft(game, depth - 1);
+        game.unmake_moveset();
+    }
+    total
+}
+
+#[test]
+fn test_perft_standard() {
+    let game = Game::variant(Variant::Standard);
+    assert_eq!(perft(&game, &game.info, 0), 1);
+    assert_eq!(perft(&game, &game.info, 1), 20);
+    assert_eq!(perft(&game, &game.info, 2), 400);
+    // Differs from 2D chess (8902) because of the jumps available on the third ply
+    assert_eq!(perft(&game, &game.info, 3), 9822);
+}
+
+#[test]
+fn test_perft_reference() {
+    let cases = [
+        (Variant::Standard, 3, 9822),
+        (Variant::TwoTimelines, 1, 484),
+        (Variant::VerySmall, 3, 840),
+        (Variant::Small, 3, 746),
+    ];
+    for (variant, depth, expected) in cases.iter() {
+        let mut game = Game::variant(*variant);
+        assert_eq!(perft(&game, &game.info, *depth), *expected, "{:?} at depth {}", variant, depth);
+        assert_eq!(reference_perft(&mut game, *depth), *expected, "{:?} at depth {}", variant, depth);
+    }
+}
+
+#[test]
+fn test_perft_branching() {
+    // After 1. Nf3 / Nf6, white's knight can jump back to T1 and create a new timeline
+    let mut game = Game::variant(Variant::Standard);
+    for _ in 0..2 {
+        let (moves, boards, info) = reference_movesets(&game)
+            .into_iter()
+            .find(|(moves, _, _)| moves[0].src.2 == 6 && moves[0].dst.2 == 5 && moves[0].dst.1 == moves[0].src.1)
+            .unwrap();
+        game.make_moveset(moves, boards, info).unwrap();
+    }
+    let branching = reference_movesets(&game)
+        .into_iter()
+        .filter(|(_, boards, _)| boards.iter().any(|b| !game.timelines.contains_key(&b.l)))
+        .count();
+    assert!(branching > 0);
+}
+
+#[test]
+fn test_divide() {
+    for variant in [Variant::Standard, Variant::VerySmall].iter() {
+        let game = Game::variant(*variant);
+        let divided = divide(&game, &game.info, 2);
+        assert_eq!(divided.len() as u64, perft(&game, &game.info, 1));
+        assert_eq!(divided.iter().map(|(_, count)| count).sum::<u64>(), perft(&game, &game.info, 2));
+    }
+}
+
+#[test]
+fn test_divide_with_jumps() {
+    let game = Game::variant(Variant::Standard);
+    let divided = divide(&game, &game.info, 3);
+    assert_eq!(divided.len(), 20);
+    assert_eq!(divided.iter().map(|(_, count)| count).sum::<u64>(), 9822);
+}
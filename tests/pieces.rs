@@ -0,0 +1,68 @@
+use chess5dlib::{check::*, game::*, moves::*};
+
+/// A white brawn on b2 of (0T2), with black knights that it can capture one turn back on (0T1) and on the timeline below
+const BRAWN: &str = "4x4 w 2 -1@2:k3/4/2n1/K3,- 0@0:k3/1n2/4/K3,-:k3/1n2/4/K3,-:k3/1n2/1W2/K3,- 1@2:k3/4/2n1/K3,-";
+
+/// Black's pawn just double-stepped to d5, next to white's pawn-like piece on e5 (`X`)
+const EN_PASSANT: &str = "8x8 w 2 0@0:4k3/3p4/8/4X3/8/8/8/4K3,-:4k3/3p4/8/4X3/8/8/8/4K3,-:4k3/8/8/3pX3/8/8/8/4K3,-";
+
+fn captures(game: &Game, l: i32, t: isize, piece: Piece) -> Vec<(i32, isize, u8, u8)> {
+    let board = game.get_board(l, t).unwrap();
+    probable_moves(game, board).unwrap()
+        .into_iter()
+        .filter(|mv| mv.src_piece == piece && !mv.dst_piece.is_blank())
+        .map(|mv| mv.dst)
+        .collect()
+}
+
+#[test]
+fn test_brawn_captures() {
+    let game = Game::from_fen(BRAWN).unwrap();
+    let mut captures = captures(&game, 0, 2, Piece::BrawnW);
+    captures.sort();
+    // Along `l` onto c2 of the timeline below, and along `t` onto b3 one turn back
+    assert_eq!(captures, vec![(-1, 2, 2, 1), (0, 0, 1, 2)]);
+
+    // A pawn can't capture any of them
+    let game = Game::from_fen(&BRAWN.replace('W', "P")).unwrap();
+    assert!(self::captures(&game, 0, 2, Piece::PawnW).is_empty());
+}
+
+#[test]
+fn test_brawn_en_passant() {
+    let en_passant = |game: &Game, piece: Piece| {
+        let board = game.get_board(0, 2).unwrap();
+        probable_moves(game, board).unwrap()
+            .into_iter()
+            .any(|mv| mv.src_piece == piece && mv.dst == (0, 2, 3, 5))
+    };
+
+    let game = Game::from_fen(&EN_PASSANT.replace('X', "P")).unwrap();
+    assert!(en_passant(&game, Piece::PawnW));
+
+    let game = Game::from_fen(&EN_PASSANT.replace('X', "W")).unwrap();
+    assert!(!en_passant(&game, Piece::BrawnW));
+}
+
+#[test]
+fn test_royal_queen_check() {
+    let game = Game::from_fen("8x8 w 0 0@0:r3k3/8/8/8/8/8/8/Y7,-").unwrap();
+    let checks = checks(&game, &game.info);
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].attacker, (0, 0, 0, 7));
+    assert_eq!(checks[0].piece, Piece::RookB);
+    assert_eq!(checks[0].king, (0, 0, 0, 0));
+    assert!(is_in_check(&game, &game.info));
+}
+
+#[test]
+fn test_common_king_not_in_check() {
+    // The common king on a1 can be captured, but only the king on h1 is royal
+    let black_to_move = Game::from_fen("8x8 b 1 0@1:r3k3/8/8/8/8/8/8/C6K,-").unwrap();
+    assert!(captures(&black_to_move, 0, 1, Piece::RookB).contains(&(0, 1, 0, 0)));
+
+    let game = Game::from_fen("8x8 w 0 0@0:r3k3/8/8/8/8/8/8/C6K,-").unwrap();
+    assert!(checks(&game, &game.info).is_empty());
+    assert!(!is_in_check(&game, &game.info));
+    assert!(!Piece::CommonKingW.is_king() && Piece::CommonKingW.is_common_king());
+}
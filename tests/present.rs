@@ -0,0 +1,102 @@
+use chess5dlib::{game::*, moves::*, notation::*, replay::*, variants::*};
+
+/// Replays `movesets` from the standard starting position
+fn standard(movesets: Vec<&str>) -> Game {
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(&mut game, movesets).unwrap();
+    game
+}
+
+/// White branches twice, the second timeline (`2`) being inactive and starting on black's first board
+fn two_white_branches() -> Game {
+    standard(vec![
+        "(0T1)Nf3",
+        "(0T1)Nf6",
+        "(0T2)Nc3",
+        "(0T2)Nc6",
+        "(0T3)Nc3>>(0T2)c5",
+        "(1T2)a6",
+        "(1T3)Nf3>>(0T1)f3",
+    ])
+}
+
+#[test]
+fn test_present_without_branching() {
+    let game = standard(vec!["(0T1)Nf3"]);
+    assert_eq!(game.info.present, 1);
+    assert!(!game.info.active_player);
+
+    let game = standard(vec!["(0T1)Nf3", "(0T1)Nf6"]);
+    assert_eq!(game.info.present, 2);
+    assert!(game.info.active_player);
+}
+
+#[test]
+fn test_branching_moves_present_back() {
+    let game = standard(vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5"]);
+    assert_eq!(game.info.max_timeline, 1);
+    assert_eq!(game.info.present, 3);
+    assert!(!game.info.active_player);
+
+    // Black only has to play on the new timeline
+    let game = standard(vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5", "(1T2)a6"]);
+    assert_eq!(game.info.present, 4);
+    assert!(game.info.active_player);
+}
+
+#[test]
+fn test_inactive_timeline() {
+    let game = two_white_branches();
+    assert_eq!(game.info.max_timeline, 2);
+    assert!(game.info.is_timeline_active(1));
+    assert!(!game.info.is_timeline_active(2));
+    // The inactive timeline's board at t = 1 doesn't hold the present back
+    assert_eq!(game.info.present, 5);
+    assert!(!game.info.active_player);
+}
+
+#[test]
+fn test_reactivation() {
+    let game = two_white_branches();
+
+    // Black's new timeline reactivates timeline 2, whose last board is then part of the present
+    let moveset = parse_moveset(&game, &game.info, "(0T3)Nc6>>(0T2)c4 (1T3)a5").unwrap();
+    assert_eq!(
+        validate_moveset(&game, &game.info, &moveset).map(|x| x.1),
+        Err(MovesetError::BoardNotPlayed((2, 1)))
+    );
+
+    let moveset = parse_moveset(&game, &game.info, "(0T3)Nc6>>(0T2)c4 (1T3)a5 (2T1)a6").unwrap();
+    let (_boards, info) = validate_moveset(&game, &game.info, &moveset).unwrap();
+    assert_eq!(info.min_timeline, -1);
+    assert!(info.is_timeline_active(2));
+    assert_eq!(info.present, 2);
+    assert!(info.active_player);
+}
+
+#[test]
+fn test_timeline_activity() {
+    let info = |min_timeline, max_timeline, even_initial_timelines| GameInfo {
+        present: 0,
+        active_player: true,
+        min_timeline,
+        max_timeline,
+        even_initial_timelines,
+    };
+
+    // One initial timeline
+    assert!(info(0, 0, false).is_timeline_active(0));
+    assert!(info(0, 1, false).is_timeline_active(1));
+    assert!(!info(0, 2, false).is_timeline_active(2));
+    assert!(info(-1, 2, false).is_timeline_active(2));
+    assert!(!info(-3, 1, false).is_timeline_active(-3));
+    assert!(info(-2, 1, false).is_timeline_active(-2));
+
+    // Two initial timelines (`-0` is `l = -1`, `-1` is `l = -2`)
+    assert!(info(-1, 0, true).is_timeline_active(-1));
+    assert!(info(-1, 1, true).is_timeline_active(1));
+    assert!(!info(-1, 2, true).is_timeline_active(2));
+    assert!(info(-2, 2, true).is_timeline_active(2));
+    assert!(info(-2, 0, true).is_timeline_active(-2));
+    assert!(!info(-3, 0, true).is_timeline_active(-3));
+}
@@ -0,0 +1,67 @@
+use chess5dlib::{game::*, moves::*, notation::*};
+
+/// White's pawn on b7 can promote on b8, or by capturing the rook on c8
+const PROMOTION: &str = "8x8 w 0 0@0:2r4k/1P6/8/8/8/8/8/K7,-";
+
+fn promotions(game: &Game) -> Vec<Move> {
+    let board = game.get_board(0, 0).unwrap();
+    probable_moves(game, board).unwrap()
+        .into_iter()
+        .filter(|mv| mv.src_piece == Piece::PawnW)
+        .collect()
+}
+
+#[test]
+fn test_promotion_choices() {
+    let game = Game::from_fen(PROMOTION).unwrap();
+    let moves = promotions(&game);
+    assert_eq!(moves.len(), 2 * game.promotions.len());
+    for dst_x in [1, 2].iter() {
+        let pieces: Vec<Piece> = moves.iter().filter(|mv| mv.dst.2 == *dst_x).filter_map(|mv| mv.promotion).collect();
+        assert_eq!(pieces, game.promotions);
+    }
+
+    // Each promotion leaves its own piece on the last rank
+    for mv in moves.iter() {
+        let (_info, boards) = mv.generate_vboards(&game, &game.info, &[]).unwrap();
+        assert_eq!(boards[0].get(mv.dst.2, mv.dst.3), mv.promotion);
+    }
+
+    let game = Game::from_fen(&PROMOTION.replace(" 0@0", " =SU,CD 0@0")).unwrap();
+    let pieces: Vec<Piece> = promotions(&game).into_iter().filter(|mv| mv.dst.2 == 1).filter_map(|mv| mv.promotion).collect();
+    assert_eq!(pieces, vec![Piece::PrincessW, Piece::UnicornW]);
+
+    // Black promotes to black pieces, on the first rank
+    let game = Game::from_fen("8x8 b 1 0@1:7k/8/8/8/8/8/1p6/7K,-").unwrap();
+    let board = game.get_board(0, 1).unwrap();
+    let pieces: Vec<Piece> = probable_moves(&game, board).unwrap().into_iter().filter_map(|mv| mv.promotion).collect();
+    assert_eq!(pieces, vec![Piece::QueenB, Piece::KnightB, Piece::RookB, Piece::BishopB]);
+}
+
+#[test]
+fn test_parse_promotion() {
+    let game = Game::from_fen(PROMOTION).unwrap();
+
+    let mv = parse_move(&game, &game.info, "(0T1)b8=N").unwrap();
+    assert_eq!(mv.promotion, Some(Piece::KnightW));
+    let mv = parse_move(&game, &game.info, "(0T1)bxc8=R+").unwrap();
+    assert_eq!((mv.dst.2, mv.promotion), (2, Some(Piece::RookW)));
+    assert_eq!(parse_move(&game, &game.info, "(0T1)b8=K"), None);
+
+    // Without `=X`, the pawn promotes to the first piece of `Game::promotions`
+    let mv = parse_move(&game, &game.info, "(0T1)b8").unwrap();
+    assert_eq!(mv.promotion, Some(Piece::QueenW));
+    let game = Game::from_fen(&PROMOTION.replace(" 0@0", " =NQ,CD 0@0")).unwrap();
+    let mv = parse_move(&game, &game.info, "(0T1)b8").unwrap();
+    assert_eq!(mv.promotion, Some(Piece::KnightW));
+}
+
+#[test]
+fn test_promotion_round_trip() {
+    let game = Game::from_fen(PROMOTION).unwrap();
+    for mv in promotions(&game) {
+        let written = write_move(&game, &mv);
+        assert!(written.ends_with(&format!("={}", mv.promotion.unwrap().as_uppercase())), "{}", written);
+        assert_eq!(parse_move(&game, &game.info, &written), Some(mv), "{}", written);
+    }
+}
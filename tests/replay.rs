@@ -0,0 +1,80 @@
+use chess5dlib::{game::*, moves::*, replay::*, variants::*, Error};
+
+#[test]
+fn test_replay_illegal_moveset() {
+    // The knight on g1 can't reach g5
+    let mut game = Game::variant(Variant::Standard);
+    let knight = Move::new((0, 0, 6, 0), (0, 0, 6, 4), &game).unwrap();
+    let res = replay(&mut game, vec![vec![knight]]);
+    assert!(matches!(res, Err(Error::IllegalMove { index: 0, reason: MovesetError::IllegalMove(mv), .. }) if mv == knight), "{:?}", res);
+    assert_eq!(game, Game::variant(Variant::Standard));
+
+    // The rook on a1 can't go through its own pawn; the movesets before it stay played
+    let mut expected = Game::variant(Variant::Standard);
+    let steps = replay_notation(&mut expected, vec!["(0T1)e4", "(0T1)e5"]).unwrap();
+    let rook = Move::new((0, 2, 0, 0), (0, 2, 0, 4), &expected).unwrap();
+    let mut game = Game::variant(Variant::Standard);
+    let res = replay(&mut game, vec![steps[0].0.clone(), steps[1].0.clone(), vec![rook]]);
+    assert!(matches!(res, Err(Error::IllegalMove { index: 2, reason: MovesetError::IllegalMove(mv), .. }) if mv == rook), "{:?}", res);
+    assert_eq!(game, expected);
+}
+
+#[test]
+fn test_replay_reasons() {
+    // White's board was already played on
+    let mut game = Game::variant(Variant::Standard);
+    let pawn = Move::new((0, 0, 3, 1), (0, 0, 3, 3), &game).unwrap();
+    replay_notation(&mut game, vec!["(0T1)e4"]).unwrap();
+    let res = replay(&mut game, vec![vec![pawn]]);
+    assert!(matches!(res, Err(Error::IllegalMove { reason: MovesetError::NotPlayable((0, 0)), .. })), "{:?}", res);
+
+    // Black is left in check by the queen on h5
+    let mut game = Game::variant(Variant::Standard);
+    let res = replay_notation(&mut game, vec!["(0T1)e4", "(0T1)f6", "(0T2)Qh5", "(0T2)Nc6"]);
+    match res {
+        Err(Error::IllegalMove { index: 3, moveset, reason: MovesetError::InCheck(check) }) => {
+            assert_eq!(moveset, "(0T2)Nc6");
+            assert_eq!((check.piece, check.attacker), (Piece::QueenW, (0, 4, 7, 4)));
+        }
+        res => panic!("Expected the moveset to leave black in check, got {:?}", res.map(|_| ())),
+    }
+
+    // Notation that can't be read isn't an illegal moveset
+    let mut game = Game::variant(Variant::Standard);
+    let res = replay_notation(&mut game, vec!["(0T1)Nf3", "(0T1)Nf9"]);
+    assert_eq!(res.map(|_| ()), Err(Error::InvalidNotation { index: 1, moveset: String::from("(0T1)Nf9") }));
+}
+
+#[test]
+fn test_replay_steps() {
+    let mut game = Game::variant(Variant::Standard);
+    let steps = replay_notation(
+        &mut game,
+        vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5", "(1T2)a6"],
+    )
+    .unwrap();
+
+    let summary: Vec<(Vec<(i32, isize)>, isize, bool)> = steps
+        .iter()
+        .map(|(_moves, boards, info)| (boards.iter().map(|b| (b.l, b.t)).collect(), info.present, info.active_player))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (vec![(0, 1)], 1, false),
+            (vec![(0, 2)], 2, true),
+            (vec![(0, 3)], 3, false),
+            (vec![(0, 4)], 4, true),
+            // The jump leaves c3 on timeline 0 and lands on c5 of a new timeline, branching off from 0T2; the present goes back
+            (vec![(0, 5), (1, 3)], 3, false),
+            (vec![(1, 4)], 4, true),
+        ]
+    );
+
+    let (moves, boards, info) = &steps[4];
+    assert_eq!(moves.len(), 1);
+    assert_eq!(boards[1].get(2, 4), Some(Piece::KnightW));
+    assert_eq!((info.min_timeline, info.max_timeline), (0, 1));
+    assert_eq!(game.get_timeline(1).unwrap().emerges_from, Some(0));
+    assert_eq!(game.info, steps[5].2);
+}
@@ -0,0 +1,40 @@
+use chess5dlib::{game::*, moves::*, replay::*, schema::*, variants::*};
+
+#[test]
+fn test_game_round_trip() {
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(&mut game, vec!["(0T1)e4", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5"]).unwrap();
+
+    let json = serde_json::to_string(&game).unwrap();
+    let read: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, game);
+    assert_eq!(read.history.len(), 5);
+}
+
+#[test]
+fn test_board_schema() {
+    let game = Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/8/4P3/4K2R,K").unwrap();
+    let board = game.get_board(0, 0).unwrap();
+    let json = serde_json::to_value(board).unwrap();
+
+    assert_eq!(json["pieces"], "4k3/8/8/8/8/8/4P3/4K2R");
+    assert_eq!(json["l"], 0);
+    assert_eq!(json["moved"], serde_json::json!([[4, 7]]));
+    assert_eq!(json["king_w"], serde_json::json!([4, 0]));
+    assert_eq!(serde_json::from_value::<Board>(json).unwrap(), *board);
+
+    assert_eq!(serde_json::to_string(&Piece::KnightB).unwrap(), "\"n\"");
+    assert!(serde_json::from_str::<Piece>("\"Kk\"").is_err());
+}
+
+#[test]
+fn test_search_node_round_trip() {
+    let game = Game::variant(Variant::Standard);
+    let mv = Move::new((0, 0, 6, 0), (0, 0, 5, 2), &game).unwrap();
+    let (info, boards) = mv.generate_vboards(&game, &game.info, &[]).unwrap();
+    let node = SearchNode::from((vec![mv], boards, info, f32::NEG_INFINITY));
+
+    let json = serde_json::to_string(&node).unwrap();
+    assert!(json.contains("\"score\":\"-inf\""));
+    assert_eq!(serde_json::from_str::<SearchNode>(&json).unwrap(), node);
+}
@@ -0,0 +1,31 @@
+use chess5dlib::{game::*, notation::*, replay::*, svg::*, variants::*};
+
+#[test]
+fn test_render_moveset() {
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(&mut game, vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6"]).unwrap();
+    let moveset = parse_moveset(&game, &game.info, "(0T3)Nc3>>(0T2)c5").unwrap();
+
+    let svg = render_svg(&game, &game.info, &moveset);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("marker-end=").count(), 1);
+    assert_eq!(svg.matches("stroke=\"#808080\"").count(), 5);
+    assert!(svg.contains(">T3w<"));
+    assert!(!svg.contains("grayscale"));
+}
+
+#[test]
+fn test_render_inactive_timeline() {
+    let mut game = Game::variant(Variant::Standard);
+    replay_notation(
+        &mut game,
+        vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Nc3", "(0T2)Nc6", "(0T3)Nc3>>(0T2)c5", "(1T2)a6", "(1T3)Nf3>>(0T1)f3"],
+    )
+    .unwrap();
+    assert!(!game.info.is_timeline_active(2));
+
+    let svg = render_svg(&game, &game.info, &[]);
+    assert_eq!(svg.matches("grayscale").count(), game.get_timeline(2).unwrap().states.len());
+    assert!(svg.contains(">L2<"));
+}
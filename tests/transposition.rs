@@ -0,0 +1,74 @@
+use chess5dlib::{game::*, moves::*, transposition::*};
+
+fn entry(hash: u64, depth: usize, bound: Bound, score: f32) -> TTEntry {
+    TTEntry { hash, depth, bound, score, best: None }
+}
+
+#[test]
+fn test_probe_store() {
+    let game = Game::from_fen("8x8 b 1 0@1:r6k/6pp/8/8/8/8/5PPP/6K1,-").unwrap();
+    let best = legal_movesets(&game, &game.info, 0, 0).next().unwrap().0;
+    let table = TranspositionTable::new(1 << 16);
+    assert!(table.probe(42).is_none());
+
+    table.store(TTEntry { hash: 42, depth: 3, bound: Bound::Exact, score: 1.5, best: Some(best.clone()) });
+    let stored = table.probe(42).unwrap();
+    assert_eq!((stored.hash, stored.depth, stored.bound, stored.score), (42, 3, Bound::Exact, 1.5));
+    assert_eq!(stored.best, Some(best));
+
+    // A position that shares the slot isn't mistaken for the stored one
+    assert!(table.probe(42 + table.capacity() as u64).is_none());
+
+    table.clear();
+    assert!(table.probe(42).is_none());
+}
+
+#[test]
+fn test_replacement() {
+    let table = TranspositionTable::new(1 << 16);
+    let other = 42 + table.capacity() as u64;
+
+    // Entries of another position only replace the stored one if they were searched at least as deep
+    table.store(entry(42, 3, Bound::Exact, 1.0));
+    table.store(entry(other, 2, Bound::Exact, 2.0));
+    assert_eq!(table.probe(42).unwrap().score, 1.0);
+    assert!(table.probe(other).is_none());
+
+    table.store(entry(other, 3, Bound::Exact, 3.0));
+    assert!(table.probe(42).is_none());
+    assert_eq!(table.probe(other).unwrap().score, 3.0);
+
+    // A newer entry of the same position always replaces the older one
+    table.store(entry(other, 1, Bound::Lower, 4.0));
+    let stored = table.probe(other).unwrap();
+    assert_eq!((stored.depth, stored.bound, stored.score), (1, Bound::Lower, 4.0));
+
+    // Terminal entries are never replaced by entries of other positions
+    table.store(entry(42, TERMINAL_DEPTH, Bound::Exact, f32::INFINITY));
+    table.store(entry(other, 100, Bound::Exact, 5.0));
+    assert_eq!(table.probe(42).unwrap().depth, TERMINAL_DEPTH);
+}
+
+#[test]
+fn test_bounds() {
+    assert_eq!(bound_of(0.5, 0.0, 1.0), Bound::Exact);
+    assert_eq!(bound_of(0.0, 0.0, 1.0), Bound::Upper);
+    assert_eq!(bound_of(-1.0, 0.0, 1.0), Bound::Upper);
+    assert_eq!(bound_of(1.0, 0.0, 1.0), Bound::Lower);
+    assert_eq!(bound_of(2.0, 0.0, 1.0), Bound::Lower);
+    assert_eq!(bound_of(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::INFINITY), Bound::Upper);
+
+    let table = TranspositionTable::new(1 << 16);
+    for bound in [Bound::Exact, Bound::Lower, Bound::Upper] {
+        table.store(entry(7, 2, bound, 0.25));
+        assert_eq!(table.probe(7).unwrap().bound, bound);
+    }
+}
+
+#[test]
+fn test_capacity() {
+    assert_eq!(TranspositionTable::new(100 * TranspositionTable::entry_size()).capacity(), 100);
+    // The heap-allocated best moveset is part of the estimate
+    assert!(TranspositionTable::entry_size() > std::mem::size_of::<std::sync::Mutex<Option<TTEntry>>>());
+    assert_eq!(TranspositionTable::new(0).capacity(), 1);
+}
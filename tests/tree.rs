@@ -0,0 +1,67 @@
+use chess5dlib::{game::*, moves::*, transposition::*, tree::*, zobrist::*};
+use std::time::Duration;
+
+/// Black can mate in one with Ra1
+const MATE_IN_ONE: &str = "8x8 b 1 0@1:r6k/6pp/8/8/8/8/5PPP/6K1,-";
+
+/// White is in check and down a queen, but can step away with Kf2
+const LOST_BUT_NOT_MATED: &str = "8x8 w 0 0@0:7k/6pp/2q5/8/8/8/6PP/r5K1,-";
+
+/// A lost position for black, whose only legal movesets are left out by `legal_movesets`: the king has to capture on 0T4 and a bishop has to jump to the board that the king leaves
+const HIDDEN_MOVESET: &str = "4x4 b 7 -1@4:bbrk/4/1N2/KR2,Kq:bbNk/4/4/KR2,K:bbk1/4/4/KR2,K:bbk1/4/K3/1R2,- 0@0:nbrk/4/4/KRBN,Kq:nbrk/4/1B2/KR1N,Kq:1brk/4/1n2/KR1N,Kq:1brk/4/1N2/KR2,Kq:2rk/2b1/1N2/KR2,Kq:2rk/2b1/1N2/1R2,q:1r1k/2b1/1N2/1R2,-:1r1k/2bN/4/1R2,- 1@3:1brk/4/1K2/KR1N,Kq:2rk/4/1K2/KR1N,Kq:2rk/4/4/KR1N,Kq:r2k/4/4/KR1N,K:r2k/4/4/1R1N,- 2@7:1r1k/2b1/KN2/1R2,-:3k/2b1/Kr2/1R2,-:3k/2b1/1r2/KR2,- 3@7:1r1k/2b1/1N2/KR2,-";
+
+#[test]
+fn test_dfs_mate() {
+    let game = Game::from_fen(MATE_IN_ONE).unwrap();
+    let table = TranspositionTable::new(1 << 20);
+    let (node, score) = dfs::dfs(&game, 2, 1, 0, 8, 1, &table).unwrap();
+    assert_eq!(score, f32::NEG_INFINITY);
+    assert_eq!(node.0.len(), 1);
+    assert_eq!((node.0[0].dst.2, node.0[0].dst.3), (0, 0));
+}
+
+#[test]
+fn test_iddfs_mate() {
+    let game = Game::from_fen(MATE_IN_ONE).unwrap();
+    let table = TranspositionTable::new(1 << 20);
+    let (node, score) = iddfs::iddfs_bfs(&game, 64, 64, 4, 1, Duration::from_secs(5), &table).unwrap();
+    assert_eq!(score, f32::NEG_INFINITY);
+    assert_eq!((node.0[0].dst.2, node.0[0].dst.3), (0, 0));
+}
+
+#[test]
+fn test_not_mated() {
+    let game = Game::from_fen(LOST_BUT_NOT_MATED).unwrap();
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Playable(..)));
+
+    let table = TranspositionTable::new(1 << 20);
+    let (node, score) = dfs::dfs(&game, 2, 1, 0, 8, 1, &table).unwrap();
+    assert!(score.is_finite() && score < 0.0, "{}", score);
+    assert_eq!(node.0[0].src_piece, Piece::KingW);
+}
+
+#[test]
+fn test_hidden_moveset() {
+    let game = Game::from_fen(HIDDEN_MOVESET).unwrap();
+    assert_eq!(legal_movesets(&game, &game.info, 0, 64).count(), 0);
+    assert!(matches!(is_checkmate(&game, &game.info), MateResult::Playable(..)));
+
+    // The root isn't declared lost for lack of movesets: the witness of `is_checkmate` is searched instead
+    let table = TranspositionTable::new(1 << 20);
+    let (node, _score) = iddfs::iddfs_bfs(&game, 64, 8, 4, 1, Duration::from_secs(2), &table).unwrap();
+    assert!(validate_moveset(&game, &game.info, &node.0).is_ok());
+}
+
+#[test]
+fn test_bfs_table_cutoff() {
+    let game = Game::from_fen(MATE_IN_ONE).unwrap();
+    let initial_node = legal_movesets(&game, &game.info, 0, 0).next().unwrap();
+    let hash = update_position_hash(position_hash(&game, &game.info), &game.info, &initial_node.2, &initial_node.1);
+
+    // An exact entry searched deeper than the search would go is used instead of expanding the branch
+    let table = TranspositionTable::new(1 << 20);
+    table.store(TTEntry { hash, depth: 8, bound: Bound::Exact, score: 123.0, best: None });
+    let (node, score) = bfs::bfs(&game, 64, 8, 4, 64, 1, 0.0, 0.5, 1, Duration::from_secs(5), &table).unwrap();
+    assert_eq!(node.0, initial_node.0);
+    assert_eq!(score, 123.0);
+}
@@ -0,0 +1,128 @@
+use chess5dlib::{error::Error, game::*, variants::*};
+
+/// A 4x4 variant with two initial timelines, without castling
+const CUSTOM: &str = r#"{
+    "width": 4,
+    "height": 4,
+    "timelines": [
+        {"index": "-0", "boards": ["nbrk/3p/P3/KRBN"]},
+        {"index": "+0", "boards": ["nbrk/3p/P3/KRBN"]}
+    ],
+    "castling": false,
+    "promotions": "QN"
+}"#;
+
+/// Returns the path of a `Parse` error
+fn error_path(res: Result<Game, Error>) -> String {
+    match res {
+        Err(Error::Parse { path, .. }) => path,
+        res => panic!("Expected a parse error, got {:?}", res.map(|game| game.to_fen())),
+    }
+}
+
+#[test]
+fn test_custom_variant() {
+    let game = load_variant(CUSTOM).unwrap();
+    assert_eq!((game.width, game.height), (4, 4));
+    assert!(game.info.even_initial_timelines);
+    assert_eq!((game.info.min_timeline, game.info.max_timeline), (-1, 0));
+    assert_eq!((game.info.present, game.info.active_player), (0, true));
+    assert!(!game.castling);
+    assert!(game.pawn_double_step);
+    assert_eq!(game.promotions, vec![Piece::QueenW, Piece::KnightW]);
+
+    for l in [-1, 0] {
+        let board = game.get_board(l, 0).unwrap();
+        assert_eq!(board.get(0, 0), Some(Piece::KingW));
+        assert_eq!(board.get(3, 3), Some(Piece::KingB));
+        assert_eq!(board.get(0, 1), Some(Piece::PawnW));
+        assert_eq!(board.get(3, 2), Some(Piece::PawnB));
+        // Pieces start unmoved
+        assert!(!board.has_moved(0, 1));
+        assert!(!board.has_moved(1, 0));
+    }
+
+    // Descriptions are read back as they were written
+    let description = VariantDescription::from_json(CUSTOM).unwrap();
+    assert_eq!(VariantDescription::from_json(&description.to_json()).unwrap(), description);
+    assert_eq!(VariantDescription::from_ron(&description.to_ron()).unwrap(), description);
+}
+
+#[test]
+fn test_ron_variant() {
+    let raw = r#"(
+        width: 4,
+        height: 4,
+        timelines: [
+            (index: "-0", boards: ["nbrk/3p/P3/KRBN"]),
+            (index: "+0", boards: ["nbrk/3p/P3/KRBN"]),
+        ],
+        castling: false,
+        promotions: "QN",
+    )"#;
+    assert_eq!(VariantDescription::from_ron(raw).unwrap(), VariantDescription::from_json(CUSTOM).unwrap());
+    assert_eq!(load_variant_ron(raw).unwrap().to_fen(), load_variant(CUSTOM).unwrap().to_fen());
+
+    assert_eq!(error_path(load_variant_ron("(width: 4, height: 4, timelines: [(index: 0)])")), "timelines[0].index");
+    assert!(matches!(load_variant_ron("(width: 4"), Err(Error::Parse { .. })));
+}
+
+#[test]
+fn test_even_timelines() {
+    // `+0` alone also makes the initial timelines even
+    let game = load_variant(r#"{"width": 4, "height": 4, "timelines": [{"index": "+0", "boards": ["nbrk/3p/P3/KRBN"]}]}"#).unwrap();
+    assert!(game.info.even_initial_timelines);
+    assert_eq!(game.timelines.keys().copied().collect::<Vec<_>>(), vec![0]);
+
+    let game = load_variant(r#"{"width": 4, "height": 4, "timelines": [{"index": "0", "boards": ["nbrk/3p/P3/KRBN"]}]}"#).unwrap();
+    assert!(!game.info.even_initial_timelines);
+
+    // The order of the timelines doesn't matter
+    let mut description = Variant::TwoTimelines.description();
+    description.timelines.reverse();
+    assert_eq!(description.build().unwrap().to_fen(), Game::variant(Variant::TwoTimelines).to_fen());
+}
+
+#[test]
+fn test_invalid_variants() {
+    let with_timelines = |timelines: &str| format!(r#"{{"width": 4, "height": 4, "timelines": [{}]}}"#, timelines);
+    let board = r#"{"index": "0", "boards": ["nbrk/3p/P3/KRBN"]}"#;
+
+    // Duplicate timelines
+    assert_eq!(error_path(load_variant(&with_timelines(&format!("{}, {}", board, board)))), "timelines[1].index");
+    assert_eq!(
+        error_path(load_variant(&with_timelines(r#"{"index": "+0", "boards": ["4/4/4/4"]}, {"index": "0", "boards": ["4/4/4/4"]}"#))),
+        "timelines[1].index"
+    );
+
+    // Empty board list, and no timelines at all
+    assert_eq!(error_path(load_variant(&with_timelines(r#"{"index": "0", "boards": []}"#))), "timelines[0].boards");
+    assert_eq!(error_path(load_variant(&with_timelines(""))), "timelines");
+
+    // Invalid boards, including numbers that would overflow
+    assert_eq!(error_path(load_variant(&with_timelines(r#"{"index": "0", "boards": ["4/4/4"]}"#))), "timelines[0].boards[0]");
+    assert_eq!(error_path(load_variant(&with_timelines(r#"{"index": "0", "boards": ["4/4/4/X3"]}"#))), "timelines[0].boards[0]");
+    assert_eq!(
+        error_path(load_variant(&with_timelines(r#"{"index": "0", "boards": ["4/4/4/99999999999999999999999"]}"#))),
+        "timelines[0].boards[0]"
+    );
+    assert_eq!(board_from_rows("4/4/4/99999999999999999999999", 0, 0, 4, 4), None);
+    assert_eq!(board_from_rows("4/4/4/18446744073709551615k", 0, 0, 4, 4), None);
+
+    // Bad promotion letters: unknown pieces, kings and pawns
+    for promotions in ["QX", "QK", "P", "q"] {
+        let raw = format!(r#"{{"width": 4, "height": 4, "timelines": [{}], "promotions": "{}"}}"#, board, promotions);
+        assert_eq!(error_path(load_variant(&raw)), "promotions");
+    }
+}
+
+#[test]
+fn test_json_errors() {
+    // The path of the offending value is given, or an empty path if the input as a whole is at fault
+    assert_eq!(error_path(load_variant(r#"{"width": 4, "height": "4", "timelines": []}"#)), "height");
+    assert_eq!(error_path(load_variant(r#"{"width": 4, "height": 4, "timelines": [{"index": "0", "boards": [4]}]}"#)), "timelines[0].boards[0]");
+    assert_eq!(error_path(load_variant(r#"{"width": 4, "height": 4}"#)), "");
+    assert!(matches!(load_variant(r#"{"width": 4, "height": 4, "timelines": []"#), Err(Error::Parse { .. })));
+    assert_eq!(error_path(load_variant(r#"{"width": 4, "height": 4, "timelines": []} trailing"#)), "");
+    assert_eq!(error_path(load_variant("")), "");
+}
@@ -0,0 +1,81 @@
+use chess5dlib::{game::*, moves::*, vboard::*};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_multiverse_is_send_sync() {
+    assert_send_sync::<Multiverse>();
+    assert_send_sync::<SharedVirtualBoardset>();
+}
+
+#[test]
+fn test_multiverse_sharing() {
+    let game = Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/8/8/4K3,-").unwrap();
+    let board = game.get_board(0, 0).unwrap();
+    let mv = Move::new((0, 0, 4, 0), (0, 0, 4, 1), &game).unwrap();
+    let (_info, boards) = mv.generate_vboards(&game, &game.info, &[]).unwrap();
+
+    let root = Multiverse::new();
+    let child = root.push(vec![mv], boards);
+    let mut next = board.clone();
+    next.t = 2;
+    let grandchild = child.push(vec![], vec![next]);
+
+    assert_eq!(root.depth(), 0);
+    assert_eq!(grandchild.depth(), 2);
+    assert!(root.get_board(0, 1).is_none());
+    assert_eq!(child.get_board(0, 1).and_then(|b| b.get(4, 1)), Some(Piece::KingW));
+    assert!(child.get_board(0, 2).is_none());
+    assert_eq!(grandchild.boards().iter().map(|b| b.t).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(grandchild.movesets().len(), 2);
+
+    let vboards = SharedVirtualBoardset { game: &game, multiverse: grandchild };
+    assert_eq!(vboards.get(0, 1, 4, 1), Some(Piece::KingW));
+    assert_eq!(vboards.get(0, 0, 4, 0), Some(Piece::KingW));
+    assert_eq!(vboards.virtual_boards().count(), 2);
+}
+
+#[test]
+fn test_multiverse_index() {
+    let game = Game::from_fen("8x8 w 0 0@0:4k3/8/8/8/8/8/8/4K3,-").unwrap();
+    let board = game.get_board(0, 0).unwrap();
+    let at = |l: i32, t: isize| {
+        let mut b = board.clone();
+        b.l = l;
+        b.t = t;
+        b
+    };
+
+    let mut history = Multiverse::new();
+    for t in 1..=40 {
+        history = history.push(vec![], (-3..=3).map(|l| at(l, t)).collect());
+    }
+    for t in 1..=40 {
+        for l in -3..=3 {
+            let found = history.get_board(l, t).unwrap();
+            assert_eq!((found.l, found.t), (l, t));
+        }
+    }
+    assert!(history.get_board(4, 1).is_none());
+    assert!(history.get_board(0, 41).is_none());
+    assert_eq!(history.iter_boards().count(), 280);
+    assert_eq!(history.last_boards().count(), 7);
+
+    let parent = history.parent();
+    assert_eq!(parent.depth(), 39);
+    assert!(parent.get_board(0, 40).is_none());
+    assert!(parent.get_board(0, 39).is_some());
+    assert!(history.get_board(0, 40).is_some());
+}
+
+#[test]
+fn test_multiverse_deep_drop() {
+    let mut history = Multiverse::new();
+    for _ in 0..1_000_000 {
+        history = history.push(vec![], vec![]);
+    }
+    let shared = history.clone();
+    drop(history);
+    assert_eq!(shared.depth(), 1_000_000);
+    drop(shared);
+}
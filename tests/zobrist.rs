@@ -0,0 +1,54 @@
+use chess5dlib::{game::*, moves::*, notation::*, replay::*, variants::*, zobrist::*};
+
+#[test]
+fn test_moved_flags_hash() {
+    let game = Game::variant(Variant::Standard);
+    let board = game.get_board(0, 0).unwrap();
+    let mut moved = board.clone();
+    moved.set_moved(4, 0, true);
+    assert_ne!(moved.hash, board.hash);
+    assert_eq!(moved.hash, content_hash(&moved));
+    assert_ne!(board_key(&moved), board_key(board));
+
+    moved.set_moved(4, 0, false);
+    assert_eq!(moved.hash, board.hash);
+
+    // The rook went back to h1, but white lost their short castling rights
+    let mut played = Game::variant(Variant::Standard);
+    replay_notation(&mut played, vec!["(0T1)Nf3", "(0T1)Nf6", "(0T2)Rg1", "(0T2)Ng8", "(0T3)Rh1", "(0T3)Nf6"]).unwrap();
+    let mut transposed = Game::variant(Variant::Standard);
+    replay_notation(&mut transposed, vec!["(0T1)Nf3", "(0T1)Nf6"]).unwrap();
+    let last = played.get_board(0, 6).unwrap();
+    let expected = transposed.get_board(0, 2).unwrap();
+    assert_eq!(last.pieces().collect::<Vec<_>>(), expected.pieces().collect::<Vec<_>>());
+    assert_ne!(last.hash, expected.hash);
+    assert_eq!(last.hash, content_hash(last));
+}
+
+#[test]
+fn test_update_position_hash() {
+    let mut game = Game::variant(Variant::Standard);
+    let mut hash = position_hash(&game, &game.info);
+    for moveset in ["(0T1)e4", "(0T1)d5", "(0T2)e5", "(0T2)f5", "(0T3)exf6", "(0T3)Nf6", "(0T4)Ng1>>(0T3)g3", "(1T3)a6"].iter() {
+        let info = game.info;
+        let (_moves, boards, new_info) = replay_notation(&mut game, vec![*moveset]).unwrap().remove(0);
+        hash = update_position_hash(hash, &info, &new_info, &boards);
+        assert_eq!(hash, position_hash(&game, &game.info), "{}", moveset);
+    }
+}
+
+#[test]
+fn test_moveset_order() {
+    let game = Game::variant(Variant::TwoTimelines);
+    let moveset = parse_moveset(&game, &game.info, "(-0T1)e4 (+0T1)d4").unwrap();
+    let reversed: Vec<Move> = moveset.iter().rev().copied().collect();
+    assert_ne!(moveset, reversed);
+
+    let hash = position_hash(&game, &game.info);
+    let (boards, info) = validate_moveset(&game, &game.info, &moveset).unwrap();
+    let (reversed_boards, reversed_info) = validate_moveset(&game, &game.info, &reversed).unwrap();
+    assert_eq!(
+        update_position_hash(hash, &game.info, &info, &boards),
+        update_position_hash(hash, &game.info, &reversed_info, &reversed_boards)
+    );
+}